@@ -1,4 +1,8 @@
-use std::{collections::BTreeMap, io::Read, sync::Arc};
+use std::{
+    collections::BTreeMap,
+    io::Read,
+    sync::{Arc, Mutex},
+};
 
 use cap_std::fs_utf8::Dir;
 use egui::Style;
@@ -20,6 +24,8 @@ struct ThemeUIData {
     tab: ThemeUITab,
     theme_name: String,
     current_theme_name: String,
+    /// the theme imported from a file in the background. it is added to the themes and applied in the next frame
+    imported_theme: Arc<Mutex<Option<(String, Theme)>>>,
 }
 #[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 enum ThemeUITab {
@@ -28,11 +34,42 @@ enum ThemeUITab {
     Config,
 }
 /// This holds all the theme settings for jokolay
-#[derive(Debug, Default, Serialize, Deserialize)]
+/// Missing or unknown fields (eg: from a theme of an older/newer egui version) fall back to the defaults.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 struct Theme {
     pub style: Style,
 }
+impl Theme {
+    fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .into_diagnostic()
+            .wrap_err("failed to deserialize theme")
+    }
+    fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .into_diagnostic()
+            .wrap_err("failed to serialize theme")
+    }
+    /// reads a theme file (eg: shared by another user) and copies it into the themes dir. The name of the theme is the file name
+    fn import(path: &std::path::Path, themes_dir: &Dir) -> Result<(String, Self)> {
+        let theme_name = path
+            .file_stem()
+            .and_then(std::ffi::OsStr::to_str)
+            .ok_or_else(|| miette::miette!("invalid theme file name {path:?}"))?
+            .to_string();
+        let theme = Self::from_json(
+            &std::fs::read_to_string(path)
+                .into_diagnostic()
+                .wrap_err("failed to read theme file")?,
+        )?;
+        themes_dir
+            .write(format!("{theme_name}.json"), theme.to_json()?)
+            .into_diagnostic()
+            .wrap_err("failed to write imported theme to themes dir")?;
+        Ok((theme_name, theme))
+    }
+}
 #[derive(Debug, Serialize, Deserialize)]
 struct ThemeManagerConfig {
     default_theme: String,
@@ -78,7 +115,7 @@ impl ThemeManager {
             .into_diagnostic()
             .wrap_err("failed to open themes dir")?
             .into();
-        if !fonts_dir.exists(&format!("{}.ttf", Self::DEFAULT_FONT_NAME)) {
+        if !fonts_dir.exists(format!("{}.ttf", Self::DEFAULT_FONT_NAME)) {
             fonts_dir
                 .write(
                     format!("{}.ttf", Self::DEFAULT_FONT_NAME),
@@ -87,7 +124,7 @@ impl ThemeManager {
                 .into_diagnostic()
                 .wrap_err("failed to write roboto/default font file to fonts dir")?;
         }
-        if !themes_dir.exists(&format!("{}.json", Self::DEFAULT_THEME_NAME)) {
+        if !themes_dir.exists(format!("{}.json", Self::DEFAULT_THEME_NAME)) {
             themes_dir
                 .write(
                     format!("{}.json", Self::DEFAULT_THEME_NAME),
@@ -210,6 +247,12 @@ impl ThemeManager {
         }
     }
     pub fn gui(&mut self, etx: &egui::Context, open: &mut bool) {
+        let imported = self.ui_data.imported_theme.lock().unwrap().take();
+        if let Some((theme_name, theme)) = imported {
+            etx.set_style(theme.style.clone());
+            self.ui_data.current_theme_name = theme_name.clone();
+            self.themes.insert(theme_name, theme);
+        }
         egui::Window::new("Theme Manager")
             .open(open)
             .scroll2([false, true])
@@ -270,6 +313,71 @@ impl ThemeManager {
                                 }
                                 self.themes.insert(theme_name, theme);
                             }
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .button("export")
+                                    .on_hover_text("save the current theme to a file to share it")
+                                    .clicked()
+                                {
+                                    let theme = Theme {
+                                        style: etx.style().as_ref().clone(),
+                                    };
+                                    let file_name = format!("{}.json", self.ui_data.theme_name);
+                                    match theme.to_json() {
+                                        Ok(theme_json) => {
+                                            rayon::spawn(move || {
+                                                if let Some(path) = rfd::FileDialog::default()
+                                                    .add_filter("themes", &["json"])
+                                                    .set_file_name(file_name)
+                                                    .save_file()
+                                                {
+                                                    match std::fs::write(&path, theme_json) {
+                                                        Ok(_) => {
+                                                            info!(notify = 3.0f64, ?path, "exported theme");
+                                                        }
+                                                        Err(e) => {
+                                                            error!(?e, ?path, "failed to export theme");
+                                                        }
+                                                    }
+                                                }
+                                            });
+                                        }
+                                        Err(e) => {
+                                            error!(?e, "failed to export theme");
+                                        }
+                                    }
+                                }
+                                if ui
+                                    .button("import")
+                                    .on_hover_text("add a theme from a file and apply it")
+                                    .clicked()
+                                {
+                                    match self.themes_dir.try_clone() {
+                                        Ok(themes_dir) => {
+                                            let imported = self.ui_data.imported_theme.clone();
+                                            rayon::spawn(move || {
+                                                if let Some(path) = rfd::FileDialog::default()
+                                                    .add_filter("themes", &["json"])
+                                                    .pick_file()
+                                                {
+                                                    match Theme::import(&path, &themes_dir) {
+                                                        Ok((theme_name, theme)) => {
+                                                            info!(notify = 3.0f64, theme_name, "imported theme");
+                                                            *imported.lock().unwrap() = Some((theme_name, theme));
+                                                        }
+                                                        Err(e) => {
+                                                            error!(?e, ?path, "failed to import theme");
+                                                        }
+                                                    }
+                                                }
+                                            });
+                                        }
+                                        Err(e) => {
+                                            error!(?e, "failed to clone themes dir to import theme");
+                                        }
+                                    }
+                                }
+                            });
                             etx.style_ui(ui);
                         });
                     }
@@ -390,3 +498,39 @@ impl ThemeManager {
             });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_round_trip() {
+        let mut style = Style {
+            visuals: egui::Visuals::light(),
+            animation_time: 0.5,
+            ..Default::default()
+        };
+        style.spacing.item_spacing = egui::vec2(20.0, 3.0);
+        let theme = Theme { style };
+        let parsed = Theme::from_json(&theme.to_json().unwrap()).unwrap();
+        assert_eq!(parsed, theme);
+
+        let etx = egui::Context::default();
+        etx.set_style(parsed.style.clone());
+        assert_eq!(etx.style().as_ref(), &theme.style);
+    }
+
+    #[test]
+    fn theme_with_missing_and_unknown_fields_uses_defaults() {
+        let theme = Theme::from_json(
+            r#"{"style": {"spacing": {"item_spacing": [20.0, 3.0], "from_the_future": 1}}, "version": 2}"#,
+        )
+        .unwrap();
+        assert_eq!(theme.style.spacing.item_spacing, egui::vec2(20.0, 3.0));
+        assert_eq!(
+            theme.style.spacing.button_padding,
+            Style::default().spacing.button_padding
+        );
+        assert_eq!(theme.style.visuals, Style::default().visuals);
+    }
+}
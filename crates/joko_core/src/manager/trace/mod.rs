@@ -7,12 +7,21 @@ use miette::{Context, IntoDiagnostic, Result};
 use ringbuffer::{AllocRingBuffer, RingBuffer};
 use tracing::{field::Visit, span, Event, Level, Subscriber};
 use tracing_subscriber::Layer;
+
+pub use joko_log::LogRotationConfig;
+use joko_log::RotatingLogWriter;
+pub use joko_log::UI_NOTIFICATIONS_TARGET;
+/// The notification is shown for this many seconds, unless the event has a `notify` field
+const UI_NOTIFICATION_SECONDS: f32 = 7.0;
+
 pub struct JokolayTracingLayer;
 static JKL_TRACING_DATA: OnceLock<Mutex<GlobalTracingData>> = OnceLock::new();
 
 impl JokolayTracingLayer {
+    /// `rotation` decides when the `jokolay.log` file is rotated. use [LogRotationConfig::from_env] to let users configure it.
     pub fn install_tracing(
         jokolay_dir: &Dir,
+        rotation: LogRotationConfig,
     ) -> Result<tracing_appender::non_blocking::WorkerGuard> {
         use tracing_subscriber::prelude::*;
         use tracing_subscriber::{fmt, EnvFilter};
@@ -22,12 +31,16 @@ impl JokolayTracingLayer {
             .into_diagnostic()
             .wrap_err("failed to parse log filter levels from env")?;
         // create log file in the data dir. This will also serve as a check that the directory is "writeable" by us
-        let writer = std::io::BufWriter::new(
+        let writer = RotatingLogWriter::new(
             jokolay_dir
-                .create("jokolay.log")
+                .try_clone()
                 .into_diagnostic()
-                .wrap_err("failed to create jokolay.log file")?,
-        );
+                .wrap_err("failed to clone jokolay dir for logging")?,
+            "jokolay.log",
+            rotation,
+        )
+        .into_diagnostic()
+        .wrap_err("failed to create jokolay.log file")?;
         let (nb, guard) = tracing_appender::non_blocking(writer);
         let fmt_layer = fmt::layer()
             .with_ansi(false)
@@ -116,6 +129,8 @@ struct TracingEvent {
     /// This is recorded as a field from the event. So, make sure to set it to 0u64 if you don't want to display the log as a notification.
     /// the value must be u64 and in seconds.
     notify: f32,
+    /// The component which reported the event to [UI_NOTIFICATIONS_TARGET]. used as the title of the notification
+    component: Option<String>,
 }
 impl Default for TracingEvent {
     fn default() -> Self {
@@ -124,6 +139,7 @@ impl Default for TracingEvent {
             target: Default::default(),
             message: Default::default(),
             notify: Default::default(),
+            component: None,
         }
     }
 }
@@ -137,6 +153,9 @@ impl Visit for EventVisitor<'_> {
             "log.target" => {
                 self.0.target = format!("{value:?}");
             }
+            "component" => {
+                self.0.component = Some(format!("{value:?}"));
+            }
             _ => {}
         }
     }
@@ -149,6 +168,14 @@ impl Visit for EventVisitor<'_> {
         self.record_debug(field, &value)
     }
 
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        if field.name() == "component" {
+            self.0.component = Some(value.to_string());
+        } else {
+            self.record_debug(field, &value)
+        }
+    }
+
     fn record_f64(&mut self, field: &tracing::field::Field, value: f64) {
         if field.name() == "notify" {
             self.0.notify = value as _;
@@ -191,6 +218,7 @@ impl<S: Subscriber> Layer<S> for JokolayTracingLayer {
             .current_span()
             .metadata()
             .zip(ctx.current_span().id().cloned());
+        let ui_notification = event.metadata().target() == UI_NOTIFICATIONS_TARGET;
         let target = if event.metadata().target() == "log" {
             Default::default()
         } else {
@@ -200,6 +228,7 @@ impl<S: Subscriber> Layer<S> for JokolayTracingLayer {
             level,
             target,
             notify: match level {
+                _ if ui_notification => UI_NOTIFICATION_SECONDS,
                 Level::TRACE | Level::DEBUG | Level::INFO => 0.0,
                 Level::WARN => 4.0,
                 Level::ERROR => 7.0,
@@ -210,7 +239,17 @@ impl<S: Subscriber> Layer<S> for JokolayTracingLayer {
 
         let mut global_tracing_data = JKL_TRACING_DATA.get().unwrap().lock().unwrap();
         if te.notify > 0.1 {
-            if let Some((md, id)) = sp {
+            if ui_notification {
+                global_tracing_data
+                    .notifications
+                    .current
+                    .push(Notification {
+                        title: te.component.clone().unwrap_or_else(|| te.target.clone()),
+                        message: te.message.clone(),
+                        level: te.level,
+                        time_to_live: te.notify,
+                    });
+            } else if let Some((md, id)) = sp {
                 let message = te.message.clone();
                 global_tracing_data
                     .notifications
@@ -0,0 +1,10 @@
+//! Logging pieces shared by jokolay and the jokolink dll.
+//! jokolink is also built as a standalone dll, so this crate must stay small and must not depend on the other joko crates.
+pub mod rotating;
+
+pub use rotating::{LogRotationConfig, RotatingLogWriter};
+
+/// Events with this target are always shown as notifications by jokolay, even if their level is below warn.
+/// Components use it to report human readable errors to the user. The `component` field (if any) is used as the title.
+/// eg: `error!(target: "ui:notifications", component = "mumble", "failed to read mumble link")`
+pub const UI_NOTIFICATIONS_TARGET: &str = "ui:notifications";
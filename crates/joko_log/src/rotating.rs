@@ -0,0 +1,143 @@
+//! Size based rotation of log files. Shared by jokolay's `jokolay.log` and the jokolink dll's `jokolink.log`.
+
+use std::io::{BufWriter, Write};
+
+use cap_std::fs_utf8::{Dir, File};
+
+/// Configuration for the size based rotation of the log file.
+/// When the log file grows beyond [Self::max_file_size], it is renamed to `name.1`, the older `name.1` to `name.2` and so on.
+/// Anything older than `name.{max_rotated_files}` is deleted.
+#[derive(Debug, Clone, Copy)]
+pub struct LogRotationConfig {
+    /// max size of each log file in bytes
+    pub max_file_size: u64,
+    /// number of rotated log files to keep around, in addition to the current log file
+    pub max_rotated_files: usize,
+}
+impl Default for LogRotationConfig {
+    fn default() -> Self {
+        Self {
+            max_file_size: 10 * 1024 * 1024,
+            max_rotated_files: 3,
+        }
+    }
+}
+impl LogRotationConfig {
+    /// `JOKOLAY_LOG_MAX_SIZE_MB` sets the max file size in MiB and `JOKOLAY_LOG_MAX_FILES` sets the number of rotated files.
+    /// If they are missing or invalid, we use the defaults.
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Some(size) = std::env::var("JOKOLAY_LOG_MAX_SIZE_MB")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+        {
+            config.max_file_size = size * 1024 * 1024;
+        }
+        if let Some(files) = std::env::var("JOKOLAY_LOG_MAX_FILES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+        {
+            config.max_rotated_files = files;
+        }
+        config
+    }
+}
+
+/// A writer which rotates the log file inside `dir` when it grows beyond the configured size.
+pub struct RotatingLogWriter {
+    dir: Dir,
+    file_name: String,
+    config: LogRotationConfig,
+    file: BufWriter<File>,
+    /// bytes written to the current file
+    written: u64,
+}
+
+impl RotatingLogWriter {
+    /// creates (truncates) the log file `file_name` inside `dir`.
+    pub fn new(dir: Dir, file_name: &str, config: LogRotationConfig) -> std::io::Result<Self> {
+        let file = BufWriter::new(dir.create(file_name)?);
+        Ok(Self {
+            dir,
+            file_name: file_name.to_string(),
+            config,
+            file,
+            written: 0,
+        })
+    }
+    fn rotated_name(&self, index: usize) -> String {
+        format!("{}.{index}", self.file_name)
+    }
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        if self.config.max_rotated_files == 0 {
+            self.dir.remove_file(&self.file_name)?;
+        } else {
+            let oldest = self.rotated_name(self.config.max_rotated_files);
+            if self.dir.exists(&oldest) {
+                self.dir.remove_file(&oldest)?;
+            }
+            for index in (1..self.config.max_rotated_files).rev() {
+                let from = self.rotated_name(index);
+                if self.dir.exists(&from) {
+                    self.dir
+                        .rename(&from, &self.dir, self.rotated_name(index + 1))?;
+                }
+            }
+            self.dir
+                .rename(&self.file_name, &self.dir, self.rotated_name(1))?;
+        }
+        self.file = BufWriter::new(self.dir.create(&self.file_name)?);
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingLogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.written != 0 && self.written + buf.len() as u64 > self.config.max_file_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cap_std::{ambient_authority, fs_utf8::camino::Utf8PathBuf};
+
+    use super::*;
+
+    #[test]
+    fn rotates_past_the_size_cap() {
+        let path = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("joko_log_rotating_test_{}", std::process::id()));
+        Dir::create_ambient_dir_all(&path, ambient_authority()).unwrap();
+        let dir = Dir::open_ambient_dir(&path, ambient_authority()).unwrap();
+        let config = LogRotationConfig {
+            max_file_size: 10,
+            max_rotated_files: 2,
+        };
+        let mut writer =
+            RotatingLogWriter::new(dir.try_clone().unwrap(), "test.log", config).unwrap();
+        // every line is 8 bytes, so each one after the first rotates the log
+        for line in ["line: 1\n", "line: 2\n", "line: 3\n", "line: 4\n"] {
+            writer.write_all(line.as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+
+        assert_eq!(dir.read_to_string("test.log").unwrap(), "line: 4\n");
+        assert_eq!(dir.read_to_string("test.log.1").unwrap(), "line: 3\n");
+        assert_eq!(dir.read_to_string("test.log.2").unwrap(), "line: 2\n");
+        // we only keep `max_rotated_files`, so the first line is gone
+        assert!(!dir.exists("test.log.3"));
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -1,5 +1,7 @@
 use crate::{
-    pack::{Category, CommonAttributes, Marker, PackCore, RelativePath, TBin, Trail},
+    pack::{
+        Category, CommonAttributes, Marker, PackCore, RelativePath, TBin, Trail, TrailParseConfig,
+    },
     BASE64_ENGINE,
 };
 use base64::Engine;
@@ -7,14 +9,22 @@ use cap_std::fs_utf8::Dir;
 use glam::Vec3;
 use indexmap::IndexMap;
 use miette::{bail, Context, IntoDiagnostic, Result};
-use std::{collections::BTreeMap, io::Read};
+use rayon::prelude::*;
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::Read,
+    time::Instant,
+};
 use tracing::{info, info_span, instrument, warn};
 use uuid::Uuid;
 use xot::{Node, Xot};
 
-use super::XotAttributeNameIDs;
+use super::{ImportPhase, MissingAsset, PackImportReport, XotAttributeNameIDs};
 
-pub(crate) fn load_pack_core_from_dir(dir: &Dir) -> Result<PackCore> {
+pub(crate) fn load_pack_core_from_dir(
+    dir: &Dir,
+    trail_config: &TrailParseConfig,
+) -> Result<PackCore> {
     let mut pack = PackCore::default();
     // walks the directory and loads all files into the hashmap
     recursive_walk_dir_and_read_images_and_tbins(
@@ -22,6 +32,7 @@ pub(crate) fn load_pack_core_from_dir(dir: &Dir) -> Result<PackCore> {
         &mut pack.textures,
         &mut pack.tbins,
         &RelativePath::default(),
+        trail_config,
     )
     .wrap_err("failed to walk dir when loading a markerpack")?;
 
@@ -84,6 +95,7 @@ fn recursive_walk_dir_and_read_images_and_tbins(
     images: &mut BTreeMap<RelativePath, Vec<u8>>,
     tbins: &mut BTreeMap<RelativePath, TBin>,
     parent_path: &RelativePath,
+    trail_config: &TrailParseConfig,
 ) -> Result<()> {
     for entry in dir
         .entries()
@@ -114,7 +126,7 @@ fn recursive_walk_dir_and_read_images_and_tbins(
                 if name.ends_with("png") {
                     images.insert(path, bytes);
                 } else if name.ends_with("trl") {
-                    if let Some(tbin) = parse_tbin_from_slice(&bytes) {
+                    if let Some(tbin) = parse_tbin_from_slice(&bytes, trail_config) {
                         tbins.insert(path, tbin);
                     } else {
                         info!("invalid tbin: {path}");
@@ -127,12 +139,13 @@ fn recursive_walk_dir_and_read_images_and_tbins(
                 images,
                 tbins,
                 &path,
+                trail_config,
             )?;
         }
     }
     Ok(())
 }
-fn parse_tbin_from_slice(bytes: &[u8]) -> Option<TBin> {
+fn parse_tbin_from_slice(bytes: &[u8], trail_config: &TrailParseConfig) -> Option<TBin> {
     let content_length = bytes.len();
     // content_length must be atleast 8 to contain version + map_id
     if content_length < 8 {
@@ -141,7 +154,7 @@ fn parse_tbin_from_slice(bytes: &[u8]) -> Option<TBin> {
     }
 
     let mut version_bytes = [0_u8; 4];
-    version_bytes.copy_from_slice(&bytes[4..8]);
+    version_bytes.copy_from_slice(&bytes[0..4]);
     let version = u32::from_ne_bytes(version_bytes);
     let mut map_id_bytes = [0_u8; 4];
     map_id_bytes.copy_from_slice(&bytes[4..8]);
@@ -179,11 +192,10 @@ fn parse_tbin_from_slice(bytes: &[u8]) -> Option<TBin> {
             Vec3::from_array(arr)
         })
         .collect();
-    Some(TBin {
-        map_id,
-        version,
-        nodes,
-    })
+    // the nodes are kept as they are, the config is only applied to the rendered trail
+    let mut tbin = TBin::new(map_id, version, nodes);
+    tbin.config = *trail_config;
+    Some(tbin)
 }
 // a recursive function to parse the marker category tree.
 fn recursive_marker_category_parser(
@@ -206,7 +218,7 @@ fn recursive_marker_category_parser(
             continue;
         }
         let mut ca = CommonAttributes::default();
-        ca.update_common_attributes_from_element(ele, names);
+        ca.update_common_attributes_from_element(tree, ele, names);
 
         let display_name = ele.get_attribute(names.display_name).unwrap_or_default();
 
@@ -338,7 +350,7 @@ fn parse_map_file(map_id: u32, map_xml_str: &str, pack: &mut PackCore) -> Result
                     .parse::<f32>()
                     .into_diagnostic()?;
                 let mut ca = CommonAttributes::default();
-                ca.update_common_attributes_from_element(child, &names);
+                ca.update_common_attributes_from_element(&tree, child, &names);
 
                 let marker = Marker {
                     position: [xpos, ypos, zpos].into(),
@@ -359,7 +371,7 @@ fn parse_map_file(map_id: u32, map_xml_str: &str, pack: &mut PackCore) -> Result
                     bail!("mapid doesn't match the file name");
                 }
                 let mut ca = CommonAttributes::default();
-                ca.update_common_attributes_from_element(child, &names);
+                ca.update_common_attributes_from_element(&tree, child, &names);
 
                 let trail = Trail {
                     category,
@@ -394,7 +406,7 @@ fn recursive_marker_category_parser_categories_xml(
             }
             let span_guard = info_span!("category {name}").entered();
             let mut ca = CommonAttributes::default();
-            ca.update_common_attributes_from_element(ele, names);
+            ca.update_common_attributes_from_element(tree, ele, names);
 
             let display_name = ele.get_attribute(names.display_name).unwrap_or_default();
 
@@ -435,16 +447,36 @@ fn recursive_marker_category_parser_categories_xml(
     }
 }
 
+/// What to do when a marker or trail references a category which doesn't exist in the pack
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportMode {
+    /// fails the import with the list of the offending markers and trails
+    Strict,
+    /// imports them anyway. They are not shown, as their category can't be enabled
+    #[default]
+    Lenient,
+}
 /// This first parses all the files in a zipfile into the memory and then it will try to parse a zpack out of all the files.
 /// will return error if there's an issue with zipfile.
 ///
 /// but any other errors like invalid attributes or missing markers etc.. will just be logged.
 /// the intention is "best effort" parsing and not "validating" xml marker packs.
 /// we will ignore any issues like unknown attributes or xml tags. "unknown" attributes means Any attributes that jokolay doesn't parse into Zpack.
+///
+/// calls `progress` with the phase and the number of files done out of the total files of that phase.
+/// `done` never decreases within a phase and every phase ends with a call where `done == total`.
 #[instrument(skip_all)]
-pub(crate) fn get_pack_from_taco_zip(taco: &[u8]) -> Result<PackCore> {
+pub(crate) fn get_pack_from_taco_zip_with_progress(
+    taco: &[u8],
+    trail_config: &TrailParseConfig,
+    mode: ImportMode,
+    mut progress: impl FnMut(ImportPhase, usize, usize),
+) -> Result<(PackCore, PackImportReport)> {
+    let import_start = Instant::now();
     // all the contents of ZPack
     let mut pack = PackCore::default();
+    let mut report = PackImportReport::default();
+    let mut seen_guids = HashSet::new();
     // parse zip file
     let mut zip_archive = zip::ZipArchive::new(std::io::Cursor::new(taco))
         .into_diagnostic()
@@ -457,6 +489,7 @@ pub(crate) fn get_pack_from_taco_zip(taco: &[u8]) -> Result<PackCore> {
     // we collect the names first, because reading a file from zip is a mutating operation.
     // So, we can't iterate AND read the file at the same time
     for name in zip_archive.file_names() {
+        check_zip_entry_name(name)?;
         if name.ends_with("png") {
             images.push(name.to_string());
         } else if name.ends_with("trl") {
@@ -469,29 +502,54 @@ pub(crate) fn get_pack_from_taco_zip(taco: &[u8]) -> Result<PackCore> {
             info!("ignoring file: {name}");
         }
     }
-    for name in images {
-        let span = info_span!("load image", name).entered();
-        let file_path: RelativePath = name.parse().unwrap();
-        if let Some(bytes) = read_file_bytes_from_zip_by_name(&name, &mut zip_archive) {
+    let texture_loading = Instant::now();
+    // reading from zip needs a mutable archive, so we only decode the images in parallel
+    let images_count = images.len();
+    let image_bytes: Vec<(String, Vec<u8>)> = images
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, name)| {
+            progress(ImportPhase::Textures, index, images_count);
+            read_file_bytes_from_zip_by_name(&name, &mut zip_archive).map(|bytes| (name, bytes))
+        })
+        .collect();
+    let textures: Vec<(String, Vec<u8>)> = image_bytes
+        .into_par_iter()
+        .filter_map(|(name, bytes)| {
+            let _span = info_span!("load image", name).entered();
             match image::load_from_memory_with_format(&bytes, image::ImageFormat::Png) {
-                Ok(_) => assert!(
-                    pack.textures.insert(file_path, bytes).is_none(),
-                    "duplicate image file {name}"
-                ),
+                Ok(_) => Some((name, bytes)),
                 Err(e) => {
                     info!(?e, "failed to parse image file");
+                    None
                 }
             }
-        }
-        std::mem::drop(span);
+        })
+        .collect();
+    info!(
+        count = textures.len(),
+        elapsed = ?texture_loading.elapsed(),
+        "loaded textures"
+    );
+    for (name, bytes) in textures {
+        let file_path: RelativePath = name.parse().unwrap();
+        assert!(
+            pack.textures.insert(file_path, bytes).is_none(),
+            "duplicate image file {name}"
+        );
     }
+    progress(ImportPhase::Textures, images_count, images_count);
+    report.telemetry_mut().texture_loading = texture_loading.elapsed();
+    let tbin_loading = Instant::now();
 
-    for name in tbins {
+    let tbins_count = tbins.len();
+    for (index, name) in tbins.into_iter().enumerate() {
+        progress(ImportPhase::Tbins, index, tbins_count);
         let span = info_span!("load tbin {name}").entered();
 
         let file_path: RelativePath = name.parse().unwrap();
         if let Some(bytes) = read_file_bytes_from_zip_by_name(&name, &mut zip_archive) {
-            if let Some(tbin) = parse_tbin_from_slice(&bytes) {
+            if let Some(tbin) = parse_tbin_from_slice(&bytes, trail_config) {
                 assert!(
                     pack.tbins.insert(file_path, tbin).is_none(),
                     "duplicate tbin file {name}"
@@ -504,7 +562,13 @@ pub(crate) fn get_pack_from_taco_zip(taco: &[u8]) -> Result<PackCore> {
         }
         std::mem::drop(span);
     }
-    for name in xmls {
+    progress(ImportPhase::Tbins, tbins_count, tbins_count);
+    report.telemetry_mut().tbin_loading = tbin_loading.elapsed();
+    let xmls_count = xmls.len();
+    for (index, name) in xmls.into_iter().enumerate() {
+        // files which fail to parse are skipped, so the progress advances before each file
+        progress(ImportPhase::Xml, index, xmls_count);
+        let xml_parsing = Instant::now();
         let mut xml_str = String::new();
         let xml_file_name = name.clone();
         let span_guard = info_span!("deserialize xml", xml_file_name).entered();
@@ -531,7 +595,7 @@ pub(crate) fn get_pack_from_taco_zip(taco: &[u8]) -> Result<PackCore> {
         let od = match tree
             .document_element(root_node)
             .ok()
-            .filter(|od| (tree.element(*od).unwrap().name() == names.overlay_data))
+            .filter(|od| tree.element(*od).unwrap().name() == names.overlay_data)
         {
             Some(od) => od,
             None => {
@@ -551,11 +615,14 @@ pub(crate) fn get_pack_from_taco_zip(taco: &[u8]) -> Result<PackCore> {
             Some(pois) => pois,
             None => {
                 info!("missing pois tag");
+                report.telemetry_mut().xml_parsing += xml_parsing.elapsed();
                 continue;
             }
         };
+        report.telemetry_mut().xml_parsing += xml_parsing.elapsed();
+        let elements_registering = Instant::now();
 
-        for child_node in tree.children(pois) {
+        for (index, child_node) in tree.children(pois).enumerate() {
             let child = match tree.element(child_node) {
                 Some(ele) => ele,
                 None => continue,
@@ -564,20 +631,18 @@ pub(crate) fn get_pack_from_taco_zip(taco: &[u8]) -> Result<PackCore> {
                 .get_attribute(names.category)
                 .unwrap_or_default()
                 .to_lowercase();
-            let guid = child
-                .get_attribute(names.guid)
-                .and_then(|guid| {
-                    let mut buffer = [0u8; 20];
-                    BASE64_ENGINE
-                        .decode_slice(guid, &mut buffer)
-                        .ok()
-                        .and_then(|_| Uuid::from_slice(&buffer[..16]).ok())
-                        .or_else(|| {
-                            info!(guid, "failed to deserialize guid");
-                            None
-                        })
-                })
-                .unwrap_or_else(Uuid::new_v4);
+            let guid = child.get_attribute(names.guid).and_then(|guid| {
+                let mut buffer = [0u8; 20];
+                BASE64_ENGINE
+                    .decode_slice(guid, &mut buffer)
+                    .ok()
+                    .and_then(|_| Uuid::from_slice(&buffer[..16]).ok())
+                    .or_else(|| {
+                        info!(guid, "failed to deserialize guid");
+                        None
+                    })
+            });
+            let guid = deterministic_guid(guid, &mut seen_guids, &name, index);
 
             if category.is_empty() {
                 info!(?guid, "missing category (type) attribute on marker");
@@ -603,10 +668,16 @@ pub(crate) fn get_pack_from_taco_zip(taco: &[u8]) -> Result<PackCore> {
                         .parse::<f32>()
                         .unwrap_or_default();
                     let mut common_attributes = CommonAttributes::default();
-                    common_attributes.update_common_attributes_from_element(child, &names);
+                    common_attributes.update_common_attributes_from_element(&tree, child, &names);
                     if let Some(icon_file) = common_attributes.get_icon_file() {
                         if !pack.textures.contains_key(icon_file) {
                             info!(%icon_file, "failed to find this texture in this pack");
+                            report.missing_texture(MissingAsset {
+                                path: icon_file.clone(),
+                                category: category.clone(),
+                                guid,
+                                source_file: name.clone(),
+                            });
                         }
                     } else if let Some(icf) = child.get_attribute(names.icon_file) {
                         info!(icf, "marker's icon file attribute failed to parse");
@@ -631,10 +702,18 @@ pub(crate) fn get_pack_from_taco_zip(taco: &[u8]) -> Result<PackCore> {
                     })
                 {
                     let mut common_attributes = CommonAttributes::default();
-                    common_attributes.update_common_attributes_from_element(child, &names);
+                    common_attributes.update_common_attributes_from_element(&tree, child, &names);
 
                     if let Some(tex) = common_attributes.get_texture() {
-                        if !pack.textures.contains_key(tex) {}
+                        if !pack.textures.contains_key(tex) {
+                            info!(%tex, "failed to find this trail texture in this pack");
+                            report.missing_texture(MissingAsset {
+                                path: tex.clone(),
+                                category: category.clone(),
+                                guid,
+                                source_file: name.clone(),
+                            });
+                        }
                     }
 
                     let trail = Trail {
@@ -649,16 +728,95 @@ pub(crate) fn get_pack_from_taco_zip(taco: &[u8]) -> Result<PackCore> {
                     let rp: RelativePath = td.unwrap_or_default().parse().unwrap();
                     let tbin = pack.tbins.get(&rp).map(|tbin| (tbin.map_id, tbin.version));
                     info!("missing map_id: {td:?} {rp} {tbin:?}");
+                    if tbin.is_none() {
+                        report.missing_trail(MissingAsset {
+                            path: rp,
+                            category,
+                            guid,
+                            source_file: name.clone(),
+                        });
+                    }
                 }
             } else {
                 info!("unknown tag: {:?}", child.name());
             }
         }
+        report.telemetry_mut().elements_registering += elements_registering.elapsed();
 
         drop(span_guard);
     }
+    progress(ImportPhase::Xml, xmls_count, xmls_count);
+    if mode == ImportMode::Strict {
+        check_orphan_categories(&pack)?;
+    }
+    report.telemetry_mut().total = import_start.elapsed();
+    info!(telemetry = ?report.telemetry(), "imported pack");
 
-    Ok(pack)
+    Ok((pack, report))
+}
+/// fails with the list of all the markers and trails whose category doesn't exist in the pack
+fn check_orphan_categories(pack: &PackCore) -> Result<()> {
+    let mut orphans = vec![];
+    for (map_id, map) in pack.maps.iter() {
+        for marker in map.markers.iter() {
+            if pack.category(&marker.category).is_none() {
+                orphans.push(format!(
+                    "marker {} on map {map_id} references the category `{}`",
+                    marker.guid, marker.category
+                ));
+            }
+        }
+        for trail in map.trails.iter() {
+            if pack.category(&trail.category).is_none() {
+                orphans.push(format!(
+                    "trail {} on map {map_id} references the category `{}`",
+                    trail.guid, trail.category
+                ));
+            }
+        }
+    }
+    if !orphans.is_empty() {
+        bail!(
+            "{} markers/trails reference categories which don't exist:\n{}",
+            orphans.len(),
+            orphans.join("\n")
+        );
+    }
+    Ok(())
+}
+/// The pack is saved into a directory with the paths of the zip entries. So, an entry like `../evil.png` or `C:/evil.png`
+/// would escape that directory. A leading `/` is fine, as [RelativePath] trims it.
+fn check_zip_entry_name(name: &str) -> Result<()> {
+    let name = name.replace('\\', "/");
+    let is_drive = name
+        .split('/')
+        .next()
+        .map(|first| first.contains(':'))
+        .unwrap_or_default();
+    if is_drive || RelativePath::default().resolve_relative(&name).is_none() {
+        bail!("zip entry {name} escapes the pack directory");
+    }
+    Ok(())
+}
+/// Taco packs often have markers without a guid or with the guid of another marker.
+/// Instead of random guids, we derive v5 guids from the source file and the position of the marker inside it.
+/// So, importing the same pack again gives the same guids and the activation data/category selection stays valid.
+fn deterministic_guid(
+    guid: Option<Uuid>,
+    seen_guids: &mut HashSet<Uuid>,
+    source_file: &str,
+    index: usize,
+) -> Uuid {
+    let name = format!("{source_file}/{index}");
+    let mut guid = guid.unwrap_or_else(|| Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes()));
+    let original = guid;
+    let mut attempt = 0;
+    // already present
+    while !seen_guids.insert(guid) {
+        attempt += 1;
+        guid = Uuid::new_v5(&original, format!("{name}/{attempt}").as_bytes());
+    }
+    guid
 }
 #[instrument(skip(zip_archive))]
 fn read_file_bytes_from_zip_by_name<T: std::io::Read + std::io::Seek>(
@@ -685,6 +843,287 @@ fn read_file_bytes_from_zip_by_name<T: std::io::Read + std::io::Seek>(
     }
     None
 }
+#[cfg(test)]
+mod tests {
+    use super::ImportMode;
+    use crate::{io::ImportPhase, pack::TrailParseConfig, test_utils::zip_pack};
+    use glam::Vec3;
+
+    #[test]
+    fn reimported_pack_has_the_same_guids() {
+        let zip = zip_pack(&[(
+            "pois.xml",
+            br#"<OverlayData>
+    <POIs>
+        <POI type="cat" MapID="15" xpos="1" ypos="2" zpos="3" GUID="AAAAAAAAAAAAAAAAAAAAAA==" />
+        <POI type="cat" MapID="15" xpos="1" ypos="2" zpos="3" GUID="AAAAAAAAAAAAAAAAAAAAAA==" />
+        <POI type="cat" MapID="15" xpos="4" ypos="5" zpos="6" />
+        <POI type="cat" MapID="15" xpos="4" ypos="5" zpos="6" GUID="invalid" />
+    </POIs>
+</OverlayData>"#,
+        )]);
+        let guids = || -> Vec<uuid::Uuid> {
+            let (pack, _) = super::get_pack_from_taco_zip_with_progress(
+                &zip,
+                &Default::default(),
+                ImportMode::Lenient,
+                |_, _, _| {},
+            )
+            .unwrap();
+            pack.maps[&15].markers.iter().map(|m| m.guid).collect()
+        };
+        let first = guids();
+        assert_eq!(first, guids());
+        // the first marker keeps its guid and the others get unique ones
+        assert_eq!(first[0], uuid::Uuid::nil());
+        let unique: std::collections::HashSet<_> = first.iter().collect();
+        assert_eq!(unique.len(), 4);
+    }
+
+    #[test]
+    fn escaping_zip_entries_are_refused() {
+        for name in [
+            "../evil.txt",
+            "icons/../../evil.png",
+            "C:/evil.png",
+            "..\\evil.xml",
+        ] {
+            let zip = zip_pack(&[(name, b"evil")]);
+            assert!(
+                super::get_pack_from_taco_zip_with_progress(
+                    &zip,
+                    &Default::default(),
+                    ImportMode::Lenient,
+                    |_, _, _| {}
+                )
+                .is_err(),
+                "{name} was not refused"
+            );
+        }
+        // these stay inside the pack
+        let zip = zip_pack(&[
+            ("/pois.xml", b"<OverlayData />"),
+            ("icons/../pois2.xml", b"<OverlayData />"),
+        ]);
+        super::get_pack_from_taco_zip_with_progress(
+            &zip,
+            &Default::default(),
+            ImportMode::Lenient,
+            |_, _, _| {},
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn progress_reaches_the_total_of_every_phase() {
+        let mut png = std::io::Cursor::new(vec![]);
+        image::RgbaImage::new(2, 2)
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        let xml = br#"<OverlayData><POIs><POI type="cat" MapID="15" /></POIs></OverlayData>"#;
+        let icons: Vec<String> = (0..5).map(|index| format!("icons/{index}.png")).collect();
+        let xmls: Vec<String> = (0..3).map(|index| format!("{index}.xml")).collect();
+        let mut files: Vec<(&str, &[u8])> = icons
+            .iter()
+            .map(|name| (name.as_str(), png.get_ref().as_slice()))
+            .collect();
+        files.extend(xmls.iter().map(|name| (name.as_str(), xml.as_slice())));
+        let zip = zip_pack(&files);
+
+        let mut calls = vec![];
+        super::get_pack_from_taco_zip_with_progress(
+            &zip,
+            &Default::default(),
+            ImportMode::Lenient,
+            |phase, done, total| calls.push((phase, done, total)),
+        )
+        .unwrap();
+        for (phase, total) in [
+            (ImportPhase::Textures, 5),
+            (ImportPhase::Tbins, 0),
+            (ImportPhase::Xml, 3),
+        ] {
+            let done: Vec<usize> = calls
+                .iter()
+                .filter(|(p, _, _)| *p == phase)
+                .map(|(_, done, t)| {
+                    assert_eq!(*t, total);
+                    *done
+                })
+                .collect();
+            assert!(done.windows(2).all(|w| w[0] <= w[1]), "{phase:?} {done:?}");
+            assert_eq!(done.last(), Some(&total), "{phase:?}");
+        }
+        // once before each xml file and once when they are all done
+        let xml_calls: Vec<usize> = calls
+            .iter()
+            .filter(|(p, _, _)| *p == ImportPhase::Xml)
+            .map(|(_, done, _)| *done)
+            .collect();
+        assert_eq!(xml_calls, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn strict_mode_refuses_orphan_markers() {
+        let zip = zip_pack(&[(
+            "pois.xml",
+            br#"<OverlayData>
+    <MarkerCategory name="Cat"><MarkerCategory name="Sub" /></MarkerCategory>
+    <POIs>
+        <POI type="cat.sub" MapID="15" xpos="1" ypos="2" zpos="3" />
+        <POI type="cat.missing" MapID="15" xpos="4" ypos="5" zpos="6" GUID="AAAAAAAAAAAAAAAAAAAAAA==" />
+    </POIs>
+</OverlayData>"#,
+        )]);
+        let import = |mode| {
+            super::get_pack_from_taco_zip_with_progress(
+                &zip,
+                &Default::default(),
+                mode,
+                |_, _, _| {},
+            )
+        };
+        let (pack, _) = import(ImportMode::Lenient).unwrap();
+        assert_eq!(pack.maps[&15].markers.len(), 2);
+
+        let error = import(ImportMode::Strict).unwrap_err().to_string();
+        assert!(error.contains("cat.missing"), "{error}");
+        assert!(error.contains(&uuid::Uuid::nil().to_string()), "{error}");
+        assert!(!error.contains("cat.sub"), "{error}");
+    }
+
+    #[test]
+    fn copy_attributes_are_parsed() {
+        let zip = zip_pack(&[(
+            "pois.xml",
+            br#"<OverlayData>
+    <POIs>
+        <POI type="cat" MapID="15" xpos="1" ypos="2" zpos="3" copy="[&amp;BNIEAAA=]" copy-message="waypoint copied" />
+        <POI type="cat" MapID="15" xpos="4" ypos="5" zpos="6" />
+    </POIs>
+</OverlayData>"#,
+        )]);
+        let (pack, _) = super::get_pack_from_taco_zip_with_progress(
+            &zip,
+            &Default::default(),
+            ImportMode::Lenient,
+            |_, _, _| {},
+        )
+        .unwrap();
+        let markers = &pack.maps[&15].markers;
+        assert_eq!(
+            markers[0].attrs.get_copy().map(|c| c.as_str()),
+            Some("[&BNIEAAA=]")
+        );
+        assert_eq!(
+            markers[0].attrs.get_copy_message().map(|m| m.as_str()),
+            Some("waypoint copied")
+        );
+        assert!(markers[1].attrs.get_copy().is_none());
+    }
+
+    #[test]
+    fn all_textures_are_loaded_in_parallel() {
+        let mut png = std::io::Cursor::new(vec![]);
+        image::RgbaImage::new(2, 2)
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        let icons: Vec<String> = (0..100).map(|index| format!("icons/{index}.png")).collect();
+        let mut files: Vec<(&str, &[u8])> = icons
+            .iter()
+            .map(|name| (name.as_str(), png.get_ref().as_slice()))
+            .collect();
+        // this is not a valid png, so it must be skipped
+        files.push(("icons/broken.png", b"not a png"));
+        let zip = zip_pack(&files);
+
+        let (pack, _) = super::get_pack_from_taco_zip_with_progress(
+            &zip,
+            &Default::default(),
+            ImportMode::Lenient,
+            |_, _, _| {},
+        )
+        .unwrap();
+        assert_eq!(pack.textures.len(), 100);
+        for index in 0..100 {
+            let path: crate::pack::RelativePath = format!("icons/{index}.png").parse().unwrap();
+            assert!(pack.textures.contains_key(&path), "missing {path}");
+        }
+    }
+
+    #[test]
+    fn tbin_version_and_map_id_are_parsed() {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&2_u32.to_ne_bytes());
+        bytes.extend_from_slice(&15_u32.to_ne_bytes());
+        let tbin = super::parse_tbin_from_slice(&bytes, &TrailParseConfig::default()).unwrap();
+        assert_eq!(tbin.version, 2);
+        assert_eq!(tbin.map_id, 15);
+        assert!(tbin.nodes.is_empty());
+    }
+
+    #[test]
+    fn smaller_chunk_length_yields_more_nodes() {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&0_u32.to_le_bytes());
+        bytes.extend_from_slice(&15_u32.to_le_bytes());
+        for node in [[1.0_f32, 0.0, 0.0], [1.0, 0.0, 0.001], [1.0, 0.0, 100.0]] {
+            for f in node {
+                bytes.extend_from_slice(&f.to_le_bytes());
+            }
+        }
+        let nodes = |config: &TrailParseConfig| {
+            let tbin = super::parse_tbin_from_slice(&bytes, config).unwrap();
+            assert_eq!(tbin.nodes.len(), 3);
+            // the near duplicate second node is only dropped from the rendered trail
+            tbin.render_nodes(&tbin.config).len()
+        };
+        let default_nodes = nodes(&TrailParseConfig::default());
+        let small_nodes = nodes(&TrailParseConfig {
+            max_chunk_length: 1.0,
+            ..Default::default()
+        });
+        assert!(small_nodes > default_nodes);
+        assert_eq!(small_nodes, 101);
+    }
+
+    #[test]
+    fn tbin_nodes_are_kept_as_imported() {
+        // a near duplicate node and a closing node on top of the first one, which are both cleaned up for rendering
+        let input = [
+            [1.0_f32, 2.0, 3.0],
+            [1.0, 2.0, 3.001],
+            [50.0, 2.0, 3.0],
+            [1.0, 2.0, 3.05],
+        ];
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&0_u32.to_le_bytes());
+        bytes.extend_from_slice(&15_u32.to_le_bytes());
+        for f in input.iter().flatten() {
+            bytes.extend_from_slice(&f.to_le_bytes());
+        }
+        let tbin = super::parse_tbin_from_slice(&bytes, &TrailParseConfig::default()).unwrap();
+        let node_bytes: Vec<u8> = tbin
+            .nodes
+            .iter()
+            .flat_map(|node| node.to_array())
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        assert_eq!(node_bytes, bytes[8..]);
+        let render_nodes = tbin.render_nodes(&TrailParseConfig {
+            max_chunk_length: 1000.0,
+            ..Default::default()
+        });
+        assert_eq!(
+            render_nodes.as_ref(),
+            &[
+                Vec3::new(1.0, 2.0, 3.0),
+                Vec3::new(50.0, 2.0, 3.0),
+                Vec3::new(1.0, 2.0, 3.0)
+            ]
+        );
+    }
+}
 // #[cfg(test)]
 // mod test {
 
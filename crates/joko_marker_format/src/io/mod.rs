@@ -1,14 +1,19 @@
 //! This modules primarily deals with serializing and deserializing xml data from marker packs
 //!
 
+use std::collections::HashSet;
+
 use xot::{NameId, Xot};
 
 mod deserialize;
 mod error;
+mod report;
 mod serialize;
 
-pub(crate) use deserialize::{get_pack_from_taco_zip, load_pack_core_from_dir};
-pub(crate) use serialize::save_pack_core_to_dir;
+pub use deserialize::ImportMode;
+pub(crate) use deserialize::{get_pack_from_taco_zip_with_progress, load_pack_core_from_dir};
+pub use report::{ImportPhase, ImportTelemetry, MissingAsset, MissingAssets, PackImportReport};
+pub(crate) use serialize::{export_pack_to_taco_zip, save_pack_core_to_dir};
 pub(crate) struct XotAttributeNameIDs {
     // xml tags
     pub overlay_data: NameId,
@@ -51,6 +56,8 @@ pub(crate) struct XotAttributeNameIDs {
     pub height_offset: NameId,
     pub hide: NameId,
     pub icon_file: NameId,
+    pub icon_frames: NameId,
+    pub icon_frame_rate: NameId,
     pub icon_size: NameId,
     pub in_game_visibility: NameId,
     pub info: NameId,
@@ -66,6 +73,7 @@ pub(crate) struct XotAttributeNameIDs {
     pub min_size: NameId,
     pub mini_map_visibility: NameId,
     pub mount: NameId,
+    pub occlude: NameId,
     pub profession: NameId,
     pub race: NameId,
     pub reset_length: NameId,
@@ -87,88 +95,102 @@ pub(crate) struct XotAttributeNameIDs {
     pub trail_data: NameId,
     pub trail_scale: NameId,
     pub trigger_range: NameId,
+    /// all the names above. attributes with other names are kept as the extra attributes of [crate::pack::CommonAttributes]
+    pub known: HashSet<NameId>,
 }
 impl XotAttributeNameIDs {
     pub fn register_with_xot(tree: &mut Xot) -> Self {
-        Self {
+        let mut known = HashSet::new();
+        let mut add = |name: &str| {
+            let name = tree.add_name(name);
+            known.insert(name);
+            name
+        };
+        let mut names = Self {
             // tags
-            overlay_data: tree.add_name("OverlayData"),
-            marker_category: tree.add_name("MarkerCategory"),
-            pois: tree.add_name("POIs"),
-            poi: tree.add_name("POI"),
-            trail: tree.add_name("Trail"),
+            overlay_data: add("OverlayData"),
+            marker_category: add("MarkerCategory"),
+            pois: add("POIs"),
+            poi: add("POI"),
+            trail: add("Trail"),
             // non inheritable attributes
-            category: tree.add_name("type"),
-            xpos: tree.add_name("xpos"),
-            ypos: tree.add_name("ypos"),
-            zpos: tree.add_name("zpos"),
-            map_id: tree.add_name("MapID"),
-            guid: tree.add_name("GUID"),
+            category: add("type"),
+            xpos: add("xpos"),
+            ypos: add("ypos"),
+            zpos: add("zpos"),
+            map_id: add("MapID"),
+            guid: add("GUID"),
 
             // marker category specific attrs
-            separator: tree.add_name("IsSeparator"),
-            default_enabled: tree.add_name("defaulttoggle"),
-            display_name: tree.add_name("DisplayName"),
-            name: tree.add_name("name"),
+            separator: add("IsSeparator"),
+            default_enabled: add("defaulttoggle"),
+            display_name: add("DisplayName"),
+            name: add("name"),
             // inheritable attributes
-            achievement_id: tree.add_name("achievementId"),
-            achievement_bit: tree.add_name("achievementBit"),
-            alpha: tree.add_name("alpha"),
-            anim_speed: tree.add_name("animSpeed"),
-            auto_trigger: tree.add_name("autotrigger"),
-            behavior: tree.add_name("behavior"),
-            color: tree.add_name("color"),
-            copy: tree.add_name("copy"),
-            copy_message: tree.add_name("copy-message"),
-            fade_near: tree.add_name("fadeNear"),
-            fade_far: tree.add_name("fadeFar"),
-            festival: tree.add_name("festival"),
-            has_countdown: tree.add_name("hasCountdown"),
-            height_offset: tree.add_name("heightOffset"),
-            icon_file: tree.add_name("iconFile"),
-            icon_size: tree.add_name("iconSize"),
-            in_game_visibility: tree.add_name("inGameVisibility"),
-            info: tree.add_name("info"),
-            info_range: tree.add_name("infoRange"),
-            map_display_size: tree.add_name("mapDisplaySize"),
-            map_visibility: tree.add_name("mapVisibility"),
-            max_size: tree.add_name("maxSize"),
-            min_size: tree.add_name("minSize"),
-            mini_map_visibility: tree.add_name("miniMapVisibility"),
-            mount: tree.add_name("mount"),
-            profession: tree.add_name("profession"),
-            race: tree.add_name("race"),
-            reset_length: tree.add_name("resetLength"),
-            reset_offset: tree.add_name("resetOffset"),
-            scale_on_map_with_zoom: tree.add_name("scaleOnMapWithZoom"),
-            tip_name: tree.add_name("tip-name"),
-            tip_description: tree.add_name("tip-description"),
-            toggle_category: tree.add_name("togglecateogry"),
-            texture: tree.add_name("texture"),
-            trail_data: tree.add_name("trailData"),
-            trail_scale: tree.add_name("trailScale"),
-            trigger_range: tree.add_name("triggerRange"),
-            bounce_delay: tree.add_name("bounce-delay"),
-            bounce_duration: tree.add_name("bounce-duration"),
-            bounce_height: tree.add_name("bounce-height"),
-            can_fade: tree.add_name("canfade"),
-            cull: tree.add_name("cull"),
-            hide: tree.add_name("hide"),
-            is_wall: tree.add_name("iswall"),
-            invert_behavior: tree.add_name("invertbehavior"),
-            map_type: tree.add_name("maptype"),
-            rotate: tree.add_name("rotate"),
-            rotate_x: tree.add_name("rotate-x"),
-            rotate_y: tree.add_name("rotate-y"),
-            rotate_z: tree.add_name("rotate-z"),
-            show: tree.add_name("show"),
-            specialization: tree.add_name("specialization"),
-            title: tree.add_name("title"),
-            title_color: tree.add_name("title-color"),
-            text: tree.add_name("text"),
-            bounce: tree.add_name("bounce"),
-            keep_on_map_edge: tree.add_name("keepOnMapEdge"),
-            map_fade_out_scale_level: tree.add_name("mapFadeoutScaleLevel"),
-        }
+            achievement_id: add("achievementId"),
+            achievement_bit: add("achievementBit"),
+            alpha: add("alpha"),
+            anim_speed: add("animSpeed"),
+            auto_trigger: add("autotrigger"),
+            behavior: add("behavior"),
+            color: add("color"),
+            copy: add("copy"),
+            copy_message: add("copy-message"),
+            fade_near: add("fadeNear"),
+            fade_far: add("fadeFar"),
+            festival: add("festival"),
+            has_countdown: add("hasCountdown"),
+            height_offset: add("heightOffset"),
+            icon_file: add("iconFile"),
+            icon_frames: add("iconFrames"),
+            icon_frame_rate: add("iconFrameRate"),
+            icon_size: add("iconSize"),
+            in_game_visibility: add("inGameVisibility"),
+            info: add("info"),
+            info_range: add("infoRange"),
+            map_display_size: add("mapDisplaySize"),
+            map_visibility: add("mapVisibility"),
+            max_size: add("maxSize"),
+            min_size: add("minSize"),
+            mini_map_visibility: add("miniMapVisibility"),
+            mount: add("mount"),
+            occlude: add("occlude"),
+            profession: add("profession"),
+            race: add("race"),
+            reset_length: add("resetLength"),
+            reset_offset: add("resetOffset"),
+            scale_on_map_with_zoom: add("scaleOnMapWithZoom"),
+            tip_name: add("tip-name"),
+            tip_description: add("tip-description"),
+            toggle_category: add("togglecateogry"),
+            texture: add("texture"),
+            trail_data: add("trailData"),
+            trail_scale: add("trailScale"),
+            trigger_range: add("triggerRange"),
+            bounce_delay: add("bounce-delay"),
+            bounce_duration: add("bounce-duration"),
+            bounce_height: add("bounce-height"),
+            can_fade: add("canfade"),
+            cull: add("cull"),
+            hide: add("hide"),
+            is_wall: add("iswall"),
+            invert_behavior: add("invertbehavior"),
+            map_type: add("maptype"),
+            rotate: add("rotate"),
+            rotate_x: add("rotate-x"),
+            rotate_y: add("rotate-y"),
+            rotate_z: add("rotate-z"),
+            show: add("show"),
+            specialization: add("specialization"),
+            title: add("title"),
+            title_color: add("title-color"),
+            text: add("text"),
+            bounce: add("bounce"),
+            keep_on_map_edge: add("keepOnMapEdge"),
+            map_fade_out_scale_level: add("mapFadeoutScaleLevel"),
+            known: HashSet::new(),
+        };
+        names.known = known;
+        names
     }
 }
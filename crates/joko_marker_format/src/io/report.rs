@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::pack::RelativePath;
+
+/// A summary of the problems found while importing a pack, which pack authors can use to fix their pack.
+#[derive(Debug, Default, Clone)]
+pub struct PackImportReport {
+    missing_assets: MissingAssets,
+    telemetry: ImportTelemetry,
+}
+impl PackImportReport {
+    /// The textures and trail binaries which are referenced by markers/trails, but don't exist in the pack
+    pub fn missing_assets(&self) -> &MissingAssets {
+        &self.missing_assets
+    }
+    /// how long each phase of the import took
+    pub fn telemetry(&self) -> &ImportTelemetry {
+        &self.telemetry
+    }
+    pub(crate) fn telemetry_mut(&mut self) -> &mut ImportTelemetry {
+        &mut self.telemetry
+    }
+    pub(crate) fn missing_texture(&mut self, asset: MissingAsset) {
+        self.missing_assets.textures.push(asset);
+    }
+    pub(crate) fn missing_trail(&mut self, asset: MissingAsset) {
+        self.missing_assets.trails.push(asset);
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MissingAssets {
+    /// png files referenced by `iconFile` of markers or `texture` of trails
+    pub textures: Vec<MissingAsset>,
+    /// trl files referenced by `trailData` of trails
+    pub trails: Vec<MissingAsset>,
+}
+impl MissingAssets {
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty() && self.trails.is_empty()
+    }
+}
+
+/// The time spent in each phase of importing a pack.
+/// The phases don't overlap, so their sum is atmost [Self::total]. The rest is spent reading the zip and logging.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ImportTelemetry {
+    /// reading the png files from the zip and validating them
+    pub texture_loading: Duration,
+    /// reading and parsing the trl files
+    pub tbin_loading: Duration,
+    /// reading and parsing the xml files into trees and parsing the categories
+    pub xml_parsing: Duration,
+    /// parsing the markers and trails and adding them to the maps of the pack
+    pub elements_registering: Duration,
+    /// the whole import
+    pub total: Duration,
+}
+impl ImportTelemetry {
+    /// the sum of all phases except total
+    pub fn phases_sum(&self) -> Duration {
+        self.texture_loading + self.tbin_loading + self.xml_parsing + self.elements_registering
+    }
+}
+
+/// The phases of importing a pack, in the order they run. They match the fields of [ImportTelemetry]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportPhase {
+    Textures,
+    Tbins,
+    /// each xml file has both categories and markers/trails, so this covers [ImportTelemetry::xml_parsing] and [ImportTelemetry::elements_registering]
+    Xml,
+}
+impl ImportPhase {
+    pub fn name(self) -> &'static str {
+        match self {
+            ImportPhase::Textures => "textures",
+            ImportPhase::Tbins => "trails",
+            ImportPhase::Xml => "categories, markers and trails",
+        }
+    }
+}
+
+/// A reference to a file which doesn't exist in the pack
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingAsset {
+    /// the path of the missing file
+    pub path: RelativePath,
+    /// the category (`type` attribute) of the marker/trail which refers to the file
+    pub category: String,
+    /// the guid of the marker/trail which refers to the file
+    pub guid: Uuid,
+    /// the xml file which contains the marker/trail
+    pub source_file: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fmt::Write, time::Duration};
+
+    use super::ImportTelemetry;
+    use crate::{
+        io::{get_pack_from_taco_zip_with_progress, ImportMode},
+        test_utils::zip_pack,
+    };
+
+    #[test]
+    fn missing_png_is_reported() {
+        let zip = zip_pack(&[(
+            "pois.xml",
+            br#"<OverlayData>
+    <MarkerCategory name="cat" />
+    <POIs>
+        <POI type="cat" MapID="15" xpos="1" ypos="2" zpos="3" iconFile="missing.png" GUID="AAAAAAAAAAAAAAAAAAAAAA==" />
+        <Trail type="cat" texture="missing.png" trailData="missing.trl" />
+    </POIs>
+</OverlayData>"#,
+        )]);
+        let (_, report) = get_pack_from_taco_zip_with_progress(
+            &zip,
+            &Default::default(),
+            ImportMode::Lenient,
+            |_, _, _| {},
+        )
+        .expect("failed to import pack");
+        let missing = report.missing_assets();
+        assert_eq!(missing.textures.len(), 1);
+        let texture = &missing.textures[0];
+        assert_eq!(texture.path.as_str(), "missing.png");
+        assert_eq!(texture.category, "cat");
+        assert_eq!(texture.guid, uuid::Uuid::nil());
+        assert_eq!(texture.source_file, "pois.xml");
+        assert_eq!(missing.trails.len(), 1);
+        assert_eq!(missing.trails[0].path.as_str(), "missing.trl");
+    }
+
+    #[test]
+    fn phases_sum_skips_total() {
+        let telemetry = ImportTelemetry {
+            texture_loading: Duration::from_millis(1),
+            tbin_loading: Duration::from_millis(20),
+            xml_parsing: Duration::from_millis(300),
+            elements_registering: Duration::from_millis(4000),
+            total: Duration::from_secs(10),
+        };
+        assert_eq!(telemetry.phases_sum(), Duration::from_millis(4321));
+    }
+
+    #[test]
+    fn telemetry_phases_fit_in_total() {
+        let mut xml = r#"<OverlayData><MarkerCategory name="cat" /><POIs>"#.to_string();
+        for index in 0..1000 {
+            write!(
+                xml,
+                r#"<POI type="cat" MapID="15" xpos="{index}" ypos="2" zpos="3" />"#
+            )
+            .unwrap();
+        }
+        xml.push_str("</POIs></OverlayData>");
+        let zip = zip_pack(&[("pois.xml", xml.as_bytes())]);
+        let (pack, report) = get_pack_from_taco_zip_with_progress(
+            &zip,
+            &Default::default(),
+            ImportMode::Lenient,
+            |_, _, _| {},
+        )
+        .expect("failed to import pack");
+        assert_eq!(pack.maps[&15].markers.len(), 1000);
+        // the actual durations depend on the machine, but the phases never overlap and always run inside the import
+        let telemetry = report.telemetry();
+        assert!(telemetry.phases_sum() <= telemetry.total, "{telemetry:?}");
+    }
+}
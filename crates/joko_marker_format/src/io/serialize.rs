@@ -1,14 +1,19 @@
 use crate::{
-    pack::{Category, Marker, PackCore, RelativePath, Trail},
+    pack::{Category, MapData, Marker, PackCore, RelativePath, TBin, Trail},
     BASE64_ENGINE,
 };
 use base64::Engine;
 use cap_std::fs_utf8::Dir;
 use indexmap::IndexMap;
 use miette::{Context, IntoDiagnostic, Result};
-use std::{collections::HashSet, io::Write};
+use std::{
+    collections::HashSet,
+    io::{Seek, Write},
+    path::Path,
+};
 use tracing::info;
 use xot::{Element, Node, SerializeOptions, Xot};
+use zip::{write::FileOptions, ZipWriter};
 
 use super::XotAttributeNameIDs;
 /// Save the pack core as xml pack using the given directory as pack root path.
@@ -22,21 +27,7 @@ pub(crate) fn save_pack_core_to_dir(
     all: bool,
 ) -> Result<()> {
     if cats || all {
-        // save categories
-        let mut tree = Xot::new();
-        let names = XotAttributeNameIDs::register_with_xot(&mut tree);
-        let od = tree.new_element(names.overlay_data);
-        let root_node = tree
-            .new_root(od)
-            .into_diagnostic()
-            .wrap_err("failed to create new root with overlay data node")?;
-        recursive_cat_serializer(&mut tree, &names, &pack_core.categories, od)
-            .wrap_err("failed to serialize cats")?;
-        let cats = tree
-            .with_serialize_options(SerializeOptions { pretty: true })
-            .to_string(root_node)
-            .into_diagnostic()
-            .wrap_err("failed to convert cats xot to string")?;
+        let cats = serialize_categories_to_xml(pack_core)?;
         dir.create("categories.xml")
             .into_diagnostic()
             .wrap_err("failed to create categories.xml")?
@@ -55,38 +46,7 @@ pub(crate) fn save_pack_core_to_dir(
                     );
                 }
             }
-            let mut tree = Xot::new();
-            let names = XotAttributeNameIDs::register_with_xot(&mut tree);
-            let od = tree.new_element(names.overlay_data);
-            let root_node: Node = tree
-                .new_root(od)
-                .into_diagnostic()
-                .wrap_err("failed to create root wiht overlay data for pois")?;
-            let pois = tree.new_element(names.pois);
-            tree.append(od, pois)
-                .into_diagnostic()
-                .wrap_err("faild to append pois to od node")?;
-            for marker in &map_data.markers {
-                let poi = tree.new_element(names.poi);
-                tree.append(pois, poi)
-                    .into_diagnostic()
-                    .wrap_err("failed to append poi (marker) to pois")?;
-                let ele = tree.element_mut(poi).unwrap();
-                serialize_marker_to_element(marker, ele, &names);
-            }
-            for trail in &map_data.trails {
-                let trail_node = tree.new_element(names.trail);
-                tree.append(pois, trail_node)
-                    .into_diagnostic()
-                    .wrap_err("failed to append a trail node to pois")?;
-                let ele = tree.element_mut(trail_node).unwrap();
-                serialize_trail_to_element(trail, ele, &names);
-            }
-            let map_xml = tree
-                .with_serialize_options(SerializeOptions { pretty: true })
-                .to_string(root_node)
-                .into_diagnostic()
-                .wrap_err("failed to serialize map data to string")?;
+            let map_xml = serialize_map_data_to_xml(map_data)?;
             dir.create(format!("{map_id}.xml"))
                 .into_diagnostic()
                 .wrap_err("failed to create map xml file")?
@@ -142,15 +102,7 @@ pub(crate) fn save_pack_core_to_dir(
                         miette::miette!("failed to create parent dir of tbin: {tbin_path}")
                     })?;
             }
-            let mut bytes: Vec<u8> = vec![];
-            bytes.reserve(8 + tbin.nodes.len() * 12);
-            bytes.extend_from_slice(&tbin.version.to_ne_bytes());
-            bytes.extend_from_slice(&tbin.map_id.to_ne_bytes());
-            for node in &tbin.nodes {
-                bytes.extend_from_slice(&node[0].to_ne_bytes());
-                bytes.extend_from_slice(&node[1].to_ne_bytes());
-                bytes.extend_from_slice(&node[2].to_ne_bytes());
-            }
+            let bytes = serialize_tbin(tbin);
             dir.create(tbin_path.as_str())
                 .into_diagnostic()
                 .wrap_err_with(|| miette::miette!("failed to create tbin file: {tbin_path}"))?
@@ -169,6 +121,130 @@ pub(crate) fn save_pack_core_to_dir(
     }
     Ok(())
 }
+/// Writes the pack core as a taco zip file at `out`. It has the same layout as the pack directory, so [super::get_pack_from_taco_zip_with_progress] can import it again.
+pub(crate) fn export_pack_to_taco_zip(pack_core: &PackCore, out: &Path) -> Result<()> {
+    let file = std::fs::File::create(out)
+        .into_diagnostic()
+        .wrap_err_with(|| miette::miette!("failed to create taco file: {out:?}"))?;
+    write_pack_core_to_zip(pack_core, file)?;
+    Ok(())
+}
+
+/// Writes the pack core into a zip archive and returns the writer back after finishing the archive.
+pub(crate) fn write_pack_core_to_zip<W: Write + Seek>(
+    pack_core: &PackCore,
+    writer: W,
+) -> Result<W> {
+    let mut zip = ZipWriter::new(writer);
+    let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    zip.start_file("categories.xml", options)
+        .into_diagnostic()
+        .wrap_err("failed to start categories.xml in zip")?;
+    zip.write_all(serialize_categories_to_xml(pack_core)?.as_bytes())
+        .into_diagnostic()
+        .wrap_err("failed to write categories.xml to zip")?;
+    for (map_id, map_data) in pack_core.maps.iter() {
+        if map_data.markers.is_empty() && map_data.trails.is_empty() {
+            continue;
+        }
+        zip.start_file(format!("{map_id}.xml"), options)
+            .into_diagnostic()
+            .wrap_err_with(|| miette::miette!("failed to start map xml in zip: {map_id}"))?;
+        zip.write_all(serialize_map_data_to_xml(map_data)?.as_bytes())
+            .into_diagnostic()
+            .wrap_err_with(|| miette::miette!("failed to write map xml to zip: {map_id}"))?;
+    }
+    for (img_path, img) in pack_core.textures.iter() {
+        // pngs are already compressed
+        zip.start_file(
+            img_path.as_str(),
+            options.compression_method(zip::CompressionMethod::Stored),
+        )
+        .into_diagnostic()
+        .wrap_err_with(|| miette::miette!("failed to start image in zip: {img_path}"))?;
+        zip.write_all(img)
+            .into_diagnostic()
+            .wrap_err_with(|| miette::miette!("failed to write image to zip: {img_path}"))?;
+    }
+    for (tbin_path, tbin) in pack_core.tbins.iter() {
+        zip.start_file(tbin_path.as_str(), options)
+            .into_diagnostic()
+            .wrap_err_with(|| miette::miette!("failed to start tbin in zip: {tbin_path}"))?;
+        zip.write_all(&serialize_tbin(tbin))
+            .into_diagnostic()
+            .wrap_err_with(|| miette::miette!("failed to write tbin to zip: {tbin_path}"))?;
+    }
+    zip.finish()
+        .into_diagnostic()
+        .wrap_err("failed to finish taco zip")
+}
+
+fn serialize_categories_to_xml(pack_core: &PackCore) -> Result<String> {
+    let mut tree = Xot::new();
+    let names = XotAttributeNameIDs::register_with_xot(&mut tree);
+    let od = tree.new_element(names.overlay_data);
+    let root_node = tree
+        .new_root(od)
+        .into_diagnostic()
+        .wrap_err("failed to create new root with overlay data node")?;
+    recursive_cat_serializer(&mut tree, &names, &pack_core.categories, od)
+        .wrap_err("failed to serialize cats")?;
+    tree.with_serialize_options(SerializeOptions { pretty: true })
+        .to_string(root_node)
+        .into_diagnostic()
+        .wrap_err("failed to convert cats xot to string")
+}
+
+fn serialize_map_data_to_xml(map_data: &MapData) -> Result<String> {
+    let mut tree = Xot::new();
+    let names = XotAttributeNameIDs::register_with_xot(&mut tree);
+    let od = tree.new_element(names.overlay_data);
+    let root_node: Node = tree
+        .new_root(od)
+        .into_diagnostic()
+        .wrap_err("failed to create root wiht overlay data for pois")?;
+    let pois = tree.new_element(names.pois);
+    tree.append(od, pois)
+        .into_diagnostic()
+        .wrap_err("faild to append pois to od node")?;
+    for marker in &map_data.markers {
+        let poi = tree.new_element(names.poi);
+        tree.append(pois, poi)
+            .into_diagnostic()
+            .wrap_err("failed to append poi (marker) to pois")?;
+        let ele = tree.element_mut(poi).unwrap();
+        serialize_marker_to_element(marker, ele, &names);
+        marker.attrs.serialize_extra_attributes(&mut tree, poi);
+    }
+    for trail in &map_data.trails {
+        let trail_node = tree.new_element(names.trail);
+        tree.append(pois, trail_node)
+            .into_diagnostic()
+            .wrap_err("failed to append a trail node to pois")?;
+        let ele = tree.element_mut(trail_node).unwrap();
+        serialize_trail_to_element(trail, ele, &names);
+        trail
+            .props
+            .serialize_extra_attributes(&mut tree, trail_node);
+    }
+    tree.with_serialize_options(SerializeOptions { pretty: true })
+        .to_string(root_node)
+        .into_diagnostic()
+        .wrap_err("failed to serialize map data to string")
+}
+
+fn serialize_tbin(tbin: &TBin) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(8 + tbin.nodes.len() * 12);
+    bytes.extend_from_slice(&tbin.version.to_ne_bytes());
+    bytes.extend_from_slice(&tbin.map_id.to_ne_bytes());
+    for node in &tbin.nodes {
+        bytes.extend_from_slice(&node[0].to_ne_bytes());
+        bytes.extend_from_slice(&node[1].to_ne_bytes());
+        bytes.extend_from_slice(&node[2].to_ne_bytes());
+    }
+    bytes
+}
+
 fn recursive_cat_serializer(
     tree: &mut Xot,
     names: &XotAttributeNameIDs,
@@ -192,6 +268,7 @@ fn recursive_cat_serializer(
             }
             cat.props.serialize_to_element(ele, names);
         }
+        cat.props.serialize_extra_attributes(tree, cat_node);
         recursive_cat_serializer(tree, names, &cat.children, cat_node)?;
     }
     Ok(())
@@ -212,3 +289,113 @@ fn serialize_marker_to_element(marker: &Marker, ele: &mut Element, names: &XotAt
     ele.set_attribute(names.category, &marker.category);
     marker.attrs.serialize_to_element(ele, names);
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        io::{get_pack_from_taco_zip_with_progress, ImportMode},
+        test_utils::zip_pack,
+    };
+
+    const TEST_CATEGORIES_XML: &str = r#"<OverlayData>
+    <MarkerCategory name="parent" DisplayName="Parent">
+        <MarkerCategory name="child" DisplayName="Child" toolVersion="2" />
+    </MarkerCategory>
+</OverlayData>"#;
+    const TEST_MAP_XML: &str = r#"<OverlayData>
+    <POIs>
+        <POI type="parent" MapID="15" xpos="1" ypos="2" zpos="3" iconFile="marker.png" GUID="AAAAAAAAAAAAAAAAAAAAAA==" tool-note="keep me" />
+        <POI type="parent.child" MapID="15" xpos="4" ypos="5" zpos="6" iconFile="marker.png" />
+        <Trail type="parent.child" texture="marker.png" trailData="basic.trl" />
+    </POIs>
+</OverlayData>"#;
+
+    fn test_zip() -> Vec<u8> {
+        let mut png = std::io::Cursor::new(vec![]);
+        image::RgbaImage::new(1, 1)
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        let mut tbin = vec![];
+        tbin.extend_from_slice(&0u32.to_ne_bytes());
+        tbin.extend_from_slice(&15u32.to_ne_bytes());
+        tbin.extend_from_slice(&[0u8; 12]);
+
+        zip_pack(&[
+            ("categories.xml", TEST_CATEGORIES_XML.as_bytes()),
+            ("15.xml", TEST_MAP_XML.as_bytes()),
+            ("marker.png", png.get_ref().as_slice()),
+            ("basic.trl", tbin.as_slice()),
+        ])
+    }
+
+    #[test]
+    fn taco_zip_round_trip() {
+        let (pack, _) = get_pack_from_taco_zip_with_progress(
+            &test_zip(),
+            &Default::default(),
+            ImportMode::Lenient,
+            |_, _, _| {},
+        )
+        .expect("failed to import test zip");
+        let exported = super::write_pack_core_to_zip(&pack, std::io::Cursor::new(vec![]))
+            .expect("failed to export pack")
+            .into_inner();
+        let (reimported, _) = get_pack_from_taco_zip_with_progress(
+            &exported,
+            &Default::default(),
+            ImportMode::Lenient,
+            |_, _, _| {},
+        )
+        .expect("failed to reimport pack");
+
+        let map = &pack.maps[&15];
+        let reimported_map = &reimported.maps[&15];
+        assert_eq!(map.markers.len(), 2);
+        assert_eq!(map.trails.len(), 1);
+        assert_eq!(reimported_map.markers.len(), map.markers.len());
+        assert_eq!(reimported_map.trails.len(), map.trails.len());
+        assert_eq!(reimported.categories.len(), pack.categories.len());
+        assert_eq!(
+            reimported.categories["parent"].children.len(),
+            pack.categories["parent"].children.len()
+        );
+        assert_eq!(reimported.textures, pack.textures);
+        assert_eq!(reimported.tbins.len(), pack.tbins.len());
+        // guids are base64 encoded on export, so they must survive the round trip
+        assert_eq!(reimported_map.markers[0].guid, map.markers[0].guid);
+        assert_eq!(reimported_map.markers[1].guid, map.markers[1].guid);
+    }
+
+    #[test]
+    fn unknown_attributes_survive_round_trip() {
+        let (pack, _) = get_pack_from_taco_zip_with_progress(
+            &test_zip(),
+            &Default::default(),
+            ImportMode::Lenient,
+            |_, _, _| {},
+        )
+        .expect("failed to import test zip");
+        let exported = super::write_pack_core_to_zip(&pack, std::io::Cursor::new(vec![]))
+            .expect("failed to export pack")
+            .into_inner();
+        let (reimported, _) = get_pack_from_taco_zip_with_progress(
+            &exported,
+            &Default::default(),
+            ImportMode::Lenient,
+            |_, _, _| {},
+        )
+        .expect("failed to reimport pack");
+
+        let markers = &reimported.maps[&15].markers;
+        let extra = markers[0]
+            .attrs
+            .get_extra()
+            .expect("missing extra attributes");
+        assert_eq!(extra.len(), 1);
+        assert_eq!(extra["tool-note"], "keep me");
+        // known attributes are not kept twice
+        assert!(markers[1].attrs.get_extra().is_none());
+        let child = &reimported.categories["parent"].children["child"];
+        assert_eq!(child.props.get_extra().unwrap()["toolVersion"], "2");
+    }
+}
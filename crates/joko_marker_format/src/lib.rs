@@ -5,8 +5,14 @@
 pub(crate) mod io;
 pub(crate) mod manager;
 pub(crate) mod pack;
-
-pub use manager::MarkerManager;
+#[cfg(test)]
+pub(crate) mod test_utils;
+
+pub use io::{
+    ImportMode, ImportPhase, ImportTelemetry, MissingAsset, MissingAssets, PackImportReport,
+};
+pub use manager::{ColorPalette, MarkerManager, NearbyMarker, RecordedTrail};
+pub use pack::{MergeStrategy, PackMergeReport, TrailParseConfig};
 // for compile time build info like pkg version or build timestamp or git hash etc..
 // shadow_rs::shadow!(build);
 
@@ -0,0 +1,134 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use jokoapi::{end_point::achievements::AccountAchievement, ApiClient};
+use miette::Result;
+use tracing::{error, info};
+
+use crate::pack::CommonAttributes;
+
+/// The result of an api request running in the background. None until the request is done
+pub(super) type PendingRequest<T> = Arc<Mutex<Option<Result<T>>>>;
+
+/// Caches the achievement progress of the account, to hide the markers whose `achievementId`/`achievementBit` is already done.
+/// The progress is fetched in the background every [Self::refresh_interval] seconds, instead of every frame.
+pub(crate) struct AchievementCache {
+    /// number of seconds between refreshes
+    pub refresh_interval: f64,
+    /// progress of the account by achievement id
+    achievements: HashMap<u32, AccountAchievement>,
+    /// the timestamp of the last refresh. None if we never refreshed
+    last_refresh: Option<f64>,
+    /// the result of a refresh which is in progress
+    pending: Option<PendingRequest<Vec<AccountAchievement>>>,
+    /// increments whenever the achievements change. packs use this to know when they need to filter the markers of the current map again
+    generation: u64,
+}
+impl Default for AchievementCache {
+    fn default() -> Self {
+        Self {
+            refresh_interval: 300.0,
+            achievements: Default::default(),
+            last_refresh: None,
+            pending: None,
+            generation: 0,
+        }
+    }
+}
+impl AchievementCache {
+    /// starts a refresh if the interval has passed and collects the result of the refresh in progress.
+    /// the api key of `client` needs the `progression` permission. if empty, we don't fetch anything and no marker is hidden
+    pub fn tick(&mut self, timestamp: f64, client: &Arc<ApiClient>) {
+        if let Some(pending) = self.pending.as_ref() {
+            let result = pending.lock().unwrap().take();
+            if let Some(result) = result {
+                self.pending = None;
+                match result {
+                    Ok(achievements) => {
+                        info!(count = achievements.len(), "refreshed account achievements");
+                        self.set_achievements(achievements);
+                    }
+                    Err(e) => {
+                        error!(?e, "failed to refresh account achievements");
+                    }
+                }
+            }
+        }
+        if client.api_key().is_empty() || self.pending.is_some() {
+            return;
+        }
+        if self
+            .last_refresh
+            .map(|last| timestamp - last < self.refresh_interval)
+            .unwrap_or_default()
+        {
+            return;
+        }
+        self.last_refresh = Some(timestamp);
+        let pending = Arc::new(Mutex::new(None));
+        self.pending = Some(pending.clone());
+        let client = client.clone();
+        rayon::spawn(move || {
+            let result = AccountAchievement::get(&client);
+            *pending.lock().unwrap() = Some(result);
+        });
+    }
+    /// refresh on the next tick. eg: after the api key changed
+    pub fn refresh_now(&mut self) {
+        self.last_refresh = None;
+    }
+    pub fn set_achievements(&mut self, achievements: Vec<AccountAchievement>) {
+        let achievements: HashMap<u32, AccountAchievement> = achievements
+            .into_iter()
+            .map(|achievement| (achievement.id, achievement))
+            .collect();
+        if achievements != self.achievements {
+            self.achievements = achievements;
+            self.generation += 1;
+        }
+    }
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+    /// whether the marker must be hidden, because its achievement (or achievement bit) is done.
+    /// markers without an achievement id are never hidden
+    pub fn is_hidden(&self, attrs: &CommonAttributes) -> bool {
+        let Some(id) = attrs.get_achievement_id() else {
+            return false;
+        };
+        self.achievements
+            .get(id)
+            .map(|achievement| achievement.is_done(attrs.get_achievement_bit().copied()))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marker_is_hidden_after_achievement_bit_is_done() {
+        let mut attrs = CommonAttributes::default();
+        attrs.set_achievement_id(Some(100));
+        attrs.set_achievement_bit(Some(2));
+        let mut cache = AchievementCache::default();
+        let response = |bits: &str| -> Vec<AccountAchievement> {
+            serde_json::from_str(&format!(
+                r#"[{{"id": 100, "bits": {bits}, "current": 1, "max": 4, "done": false}}]"#
+            ))
+            .unwrap()
+        };
+        assert!(!cache.is_hidden(&attrs));
+        cache.set_achievements(response("[0]"));
+        assert!(!cache.is_hidden(&attrs));
+        let generation = cache.generation();
+        cache.set_achievements(response("[0, 2]"));
+        assert!(cache.is_hidden(&attrs));
+        assert_ne!(cache.generation(), generation);
+        // markers without achievement attributes are unaffected
+        assert!(!cache.is_hidden(&CommonAttributes::default()));
+    }
+}
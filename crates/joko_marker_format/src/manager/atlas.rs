@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use egui::{Color32, ColorImage, TextureHandle};
+use glam::UVec2;
+use joko_render::atlas::{AtlasPacker, AtlasPlacement};
+
+use crate::pack::RelativePath;
+
+/// The small marker icons of the current map packed into a few textures. see [AtlasPacker]
+/// Bigger icons are not packed and stay standalone textures.
+#[derive(Default)]
+pub(crate) struct MarkerAtlas {
+    packer: AtlasPacker,
+    /// one texture per page of the packer
+    pages: Vec<TextureHandle>,
+    /// the icons which are already packed
+    icons: HashMap<RelativePath, AtlasPlacement>,
+}
+
+impl MarkerAtlas {
+    /// the page texture and placement of an icon which was packed by [Self::insert]
+    pub fn get(&self, path: &RelativePath) -> Option<(TextureHandle, AtlasPlacement)> {
+        let placement = self.icons.get(path)?;
+        Some((self.pages[placement.page].clone(), *placement))
+    }
+    /// packs the icon and uploads it into its page. None if the icon is too big for the atlas
+    pub fn insert(
+        &mut self,
+        etx: &egui::Context,
+        path: &RelativePath,
+        icon: ColorImage,
+    ) -> Option<(TextureHandle, AtlasPlacement)> {
+        let placement = self
+            .packer
+            .insert(UVec2::new(icon.width() as _, icon.height() as _))?;
+        if placement.page == self.pages.len() {
+            let size = self.packer.page_size() as usize;
+            self.pages.push(etx.load_texture(
+                format!("marker atlas {}", placement.page),
+                ColorImage::new([size, size], Color32::TRANSPARENT),
+                Default::default(),
+            ));
+        }
+        let page = &mut self.pages[placement.page];
+        page.set_partial(
+            [placement.pixel_pos.x as _, placement.pixel_pos.y as _],
+            icon,
+            Default::default(),
+        );
+        self.icons.insert(path.clone(), placement);
+        Some((page.clone(), placement))
+    }
+}
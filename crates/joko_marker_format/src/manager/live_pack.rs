@@ -1,6 +1,7 @@
 use std::{
-    collections::{HashMap, HashSet},
-    sync::Arc,
+    collections::{BTreeMap, HashMap, HashSet},
+    net::IpAddr,
+    sync::{mpsc::Sender, Arc},
 };
 
 use cap_std::fs_utf8::Dir;
@@ -8,18 +9,34 @@ use egui::{ColorImage, TextureHandle};
 use glam::{vec2, Vec2, Vec3};
 use image::EncodableLayout;
 use indexmap::IndexMap;
-use joko_render::billboard::{MarkerObject, MarkerVertex, TrailObject};
+use joko_render::{
+    atlas::AtlasPlacement,
+    billboard::{MarkerObject, MarkerVertex, TrailObject},
+};
 use tracing::{debug, error, info};
 use uuid::Uuid;
 
+use super::{
+    achievements::AchievementCache, atlas::MarkerAtlas, palette::ColorPalette,
+    professions::ProfessionCache, RecordedTrail,
+};
 use crate::{
     io::{load_pack_core_from_dir, save_pack_core_to_dir},
-    pack::{Category, CommonAttributes, PackCore, RelativePath},
+    pack::{
+        Behavior, Category, CommonAttributes, MapType, MergeStrategy, PackCore, PackMergeReport,
+        RelativePath, TBin, Trail, TrailParseConfig,
+    },
     INCHES_PER_METER,
 };
 use jokolink::MumbleLink;
 use miette::{bail, Context, IntoDiagnostic, Result};
 use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// taco triggers markers within 2 meters, if they don't have a `triggerRange`
+const DEFAULT_TRIGGER_RANGE: f32 = 2.0;
+/// taco lifts markers 1.5 meters off the ground, if they don't have a `heightOffset`
+const DEFAULT_HEIGHT_OFFSET: f32 = 1.5;
 
 pub(crate) struct LoadedPack {
     /// The directory inside which the pack data is stored
@@ -34,6 +51,28 @@ pub(crate) struct LoadedPack {
     dirty: Dirty,
     activation_data: ActivationData,
     current_map_data: CurrentMapData,
+    /// markers whose info text is being shown, because the player is within their info range
+    markers_in_info_range: HashSet<Uuid>,
+    /// markers with a `copy` attribute whose trigger range the player is in. their text is copied only once when the player enters the range
+    markers_in_copy_range: HashSet<Uuid>,
+    /// full names of the enabled categories and their attributes after inheriting from their parents.
+    /// computed when needed and cleared when the categories or their selection change.
+    enabled_categories: Option<HashMap<String, CommonAttributes>>,
+}
+
+/// Events about markers near the player. sent to the ui, which shows the info text of the markers. see [super::MarkerManager::gui]
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarkerEvent {
+    /// the player entered the info range of a marker with an `info` text
+    InfoShown { guid: Uuid, info: String },
+    /// the player left the info range of the marker or the marker is not active anymore
+    InfoHidden { guid: Uuid },
+    /// the player reached a marker with a `copy` attribute. the text should be copied to the clipboard and the message shown to the user
+    Copy {
+        guid: Uuid,
+        text: String,
+        message: Option<String>,
+    },
 }
 
 #[derive(Debug, Default, Clone)]
@@ -43,6 +82,8 @@ struct Dirty {
     cats: bool,
     /// whether cats selection needs to be saved
     cats_selection: bool,
+    /// whether activation data needs to be saved
+    activation_data: bool,
     /// Whether any mapdata needs saving
     map_dirty: HashSet<u32>,
     /// whether any texture needs saving
@@ -55,6 +96,7 @@ impl Dirty {
     fn is_dirty(&self) -> bool {
         self.cats
             || self.cats_selection
+            || self.activation_data
             || !self.map_dirty.is_empty()
             || !self.texture.is_empty()
             || !self.tbin.is_empty()
@@ -69,10 +111,249 @@ pub struct ActivationData {
     /// for markers which trigger once per character
     pub character: IndexMap<String, IndexMap<Uuid, ActivationType>>,
 }
+impl ActivationData {
+    /// removes the activation data of all markers for which `f` returns false
+    fn retain(&mut self, mut f: impl FnMut(&Uuid, &ActivationType) -> bool) {
+        self.global.retain(|k, v| f(k, v));
+        for char_data in self.character.values_mut() {
+            char_data.retain(|k, v| f(k, v));
+        }
+        self.character.retain(|_, char_data| !char_data.is_empty());
+    }
+}
+impl ActivationData {
+    /// The activation of this marker, if it is still active at `now` and hides the marker.
+    fn get_active(
+        &self,
+        guid: &Uuid,
+        behavior: Behavior,
+        character: &str,
+        server_address: &IpAddr,
+        now: OffsetDateTime,
+    ) -> Option<&ActivationType> {
+        let activation = if is_per_character(behavior) {
+            self.character.get(character)?.get(guid)?
+        } else {
+            self.global.get(guid)?
+        };
+        match activation {
+            ActivationType::ReappearOnMapChange | ActivationType::Permanent => true,
+            ActivationType::TimeStamp(ts) => *ts > now,
+            ActivationType::Instance(address) => address == server_address,
+        }
+        .then_some(activation)
+    }
+    /// records the activation of a marker at `now`, based on its behavior.
+    /// returns false if the behavior never hides the marker or its reset length/offset are invalid.
+    fn trigger(
+        &mut self,
+        guid: Uuid,
+        behavior: Behavior,
+        attrs: &CommonAttributes,
+        character: &str,
+        server_address: IpAddr,
+        now: OffsetDateTime,
+    ) -> bool {
+        let activation = match behavior {
+            Behavior::AlwaysVisible | Behavior::WvWObjective => return false,
+            Behavior::ReappearOnMapChange => ActivationType::ReappearOnMapChange,
+            Behavior::ReappearOnDailyReset | Behavior::DailyPerChar => {
+                ActivationType::TimeStamp(next_daily_reset(now))
+            }
+            Behavior::OnlyVisibleBeforeActivation => ActivationType::Permanent,
+            // the reset length/offset come from the pack, so they might be NaN or way out of range
+            Behavior::ReappearAfterTimer => {
+                let Some(ts) = time::Duration::checked_seconds_f32(
+                    attrs.get_reset_length().copied().unwrap_or_default(),
+                )
+                .and_then(|timer| now.checked_add(timer)) else {
+                    return false;
+                };
+                ActivationType::TimeStamp(ts)
+            }
+            Behavior::ReappearOnMapReset => {
+                let Some(ts) = next_map_reset(
+                    now,
+                    attrs.get_reset_length().copied().unwrap_or_default(),
+                    attrs.get_reset_offset().copied().unwrap_or_default(),
+                ) else {
+                    return false;
+                };
+                ActivationType::TimeStamp(ts)
+            }
+            Behavior::OncePerInstance | Behavior::OncePerInstancePerChar => {
+                ActivationType::Instance(server_address)
+            }
+            Behavior::WeeklyReset => ActivationType::TimeStamp(next_weekly_reset(now)),
+        };
+        if is_per_character(behavior) {
+            self.character
+                .entry(character.to_string())
+                .or_default()
+                .insert(guid, activation);
+        } else {
+            self.global.insert(guid, activation);
+        }
+        true
+    }
+    /// markers with [Behavior::ReappearOnMapChange] reappear when the player leaves the map
+    fn on_map_changed(&mut self) {
+        self.retain(|_, activation| !matches!(activation, ActivationType::ReappearOnMapChange));
+    }
+}
+fn is_per_character(behavior: Behavior) -> bool {
+    matches!(
+        behavior,
+        Behavior::DailyPerChar | Behavior::OncePerInstancePerChar
+    )
+}
+/// `markers` are the guid, position, info range and info text of markers.
+/// updates `in_range` with the markers whose info range contains the player, and returns the events for the markers which entered or left their range.
+/// markers without info text are ignored
+fn info_range_events<'a>(
+    in_range: &mut HashSet<Uuid>,
+    markers: impl Iterator<Item = (Uuid, Vec3, f32, &'a str)>,
+    player_pos: Vec3,
+) -> Vec<MarkerEvent> {
+    let mut events = vec![];
+    let mut now_in_range = HashSet::new();
+    for (guid, pos, range, info) in markers {
+        if info.is_empty() || pos.distance(player_pos) > range {
+            continue;
+        }
+        if !in_range.contains(&guid) {
+            events.push(MarkerEvent::InfoShown {
+                guid,
+                info: info.to_string(),
+            });
+        }
+        now_in_range.insert(guid);
+    }
+    for guid in in_range.difference(&now_in_range) {
+        events.push(MarkerEvent::InfoHidden { guid: *guid });
+    }
+    *in_range = now_in_range;
+    events
+}
+/// the attributes of a marker or trail, with the unset ones inherited from its category, which already inherited from its parent categories.
+/// returns None if the category is not enabled.
+fn inherited_attributes(
+    attrs: &CommonAttributes,
+    category: &str,
+    enabled_categories: &HashMap<String, CommonAttributes>,
+) -> Option<CommonAttributes> {
+    let category_attributes = enabled_categories.get(category)?;
+    let mut attrs = attrs.clone();
+    attrs.inherit_if_attr_none(category_attributes);
+    Some(attrs)
+}
+/// the position where the marker is drawn. the stored position is lifted along the up (Y) axis by the `heightOffset`
+fn marker_world_position(pos: Vec3, attrs: &CommonAttributes) -> Vec3 {
+    let height_offset = attrs
+        .get_height_offset()
+        .copied()
+        .unwrap_or(DEFAULT_HEIGHT_OFFSET);
+    pos + Vec3::Y * height_offset
+}
+/// markers with a `mapType` attribute are only shown on those types of maps. eg: only in WvW
+fn is_hidden_on_map_type(attrs: &CommonAttributes, map_type: MapType) -> bool {
+    attrs
+        .get_map_type()
+        .map(|map_types| !map_types.contains(map_type))
+        .unwrap_or_default()
+}
+/// The texture of a marker icon, and its placement if it was packed into the atlas of the current map.
+/// The icon is loaded on first use. small icons are packed into the atlas, the others are standalone textures
+fn load_icon(
+    etx: &egui::Context,
+    textures: &BTreeMap<RelativePath, Vec<u8>>,
+    map_data: &mut CurrentMapData,
+    path: &RelativePath,
+) -> Option<(TextureHandle, Option<AtlasPlacement>)> {
+    if let Some((th, placement)) = map_data.atlas.get(path) {
+        return Some((th, Some(placement)));
+    }
+    if let Some(th) = map_data.active_textures.get(path) {
+        return Some((th.clone(), None));
+    }
+    let Some(tex) = textures.get(path) else {
+        info!(%path, "failed to find this texture");
+        return None;
+    };
+    let img = match image::load_from_memory(tex) {
+        Ok(img) => img,
+        Err(e) => {
+            error!(?e, %path, "failed to decode texture");
+            return None;
+        }
+    };
+    let icon = ColorImage::from_rgba_unmultiplied(
+        [img.width() as _, img.height() as _],
+        img.into_rgba8().as_bytes(),
+    );
+    if let Some((th, placement)) = map_data.atlas.insert(etx, path, icon.clone()) {
+        return Some((th, Some(placement)));
+    }
+    let th = etx.load_texture(path.as_str(), icon, Default::default());
+    map_data.active_textures.insert(path.clone(), th.clone());
+    Some((th, None))
+}
+/// replaces the `color` tint with the color of the palette
+fn recolor(attrs: &mut CommonAttributes, palette: ColorPalette) {
+    if let Some(color) = attrs.get_color().copied() {
+        attrs.set_color(Some(palette.apply(color)));
+    }
+}
+/// gw2 daily reset is at 00:00 UTC
+fn next_daily_reset(now: OffsetDateTime) -> OffsetDateTime {
+    let now = now.to_offset(time::UtcOffset::UTC);
+    (now.date() + time::Duration::DAY).midnight().assume_utc()
+}
+/// gw2 weekly reset is on monday at 07:30 UTC
+fn next_weekly_reset(now: OffsetDateTime) -> OffsetDateTime {
+    let now = now.to_offset(time::UtcOffset::UTC);
+    let monday = now.date() - time::Duration::days(now.weekday().number_days_from_monday() as i64);
+    let reset = monday
+        .with_time(time::Time::from_hms(7, 30, 0).expect("valid time"))
+        .assume_utc();
+    if reset > now {
+        reset
+    } else {
+        reset + time::Duration::WEEK
+    }
+}
+/// map cycles are `reset_length` seconds long and start `reset_offset` seconds after the daily reset.
+/// without a length, the map resets with the daily reset.
+/// returns None if the length or offset is NaN, infinite or too large to be a timestamp.
+fn next_map_reset(
+    now: OffsetDateTime,
+    reset_length: f32,
+    reset_offset: f32,
+) -> Option<OffsetDateTime> {
+    if reset_length <= 0.0 {
+        return Some(next_daily_reset(now));
+    }
+    if !reset_length.is_finite() {
+        return None;
+    }
+    let now = now.to_offset(time::UtcOffset::UTC);
+    let first_cycle = now
+        .date()
+        .midnight()
+        .assume_utc()
+        .checked_add(time::Duration::checked_seconds_f32(reset_offset)?)?;
+    let elapsed = (now - first_cycle).as_seconds_f64();
+    let cycles = (elapsed / reset_length as f64).floor() + 1.0;
+    first_cycle.checked_add(time::Duration::checked_seconds_f64(
+        cycles * reset_length as f64,
+    )?)
+}
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum ActivationType {
     /// clean these up when the map is changed
     ReappearOnMapChange,
+    /// never reappears, unless the activation data is reset
+    Permanent,
     /// clean these up when the timestamp is reached
     TimeStamp(time::OffsetDateTime),
     Instance(std::net::IpAddr),
@@ -81,6 +362,8 @@ impl LoadedPack {
     const CORE_PACK_DIR_NAME: &str = "core";
     const CATEGORY_SELECTION_FILE_NAME: &str = "cats.json";
     const ACTIVATION_DATA_FILE_NAME: &str = "activation.json";
+    /// root category of the trails added by [Self::add_recorded_trail]
+    pub const RECORDED_TRAILS_CATEGORY: &str = "recorded_trails";
 
     pub fn new(core: PackCore, dir: Arc<Dir>) -> Self {
         let cats_selection = CategorySelection::default_from_pack_core(&core);
@@ -94,16 +377,164 @@ impl LoadedPack {
             current_map_data: Default::default(),
             dir,
             activation_data: Default::default(),
+            markers_in_info_range: Default::default(),
+            markers_in_copy_range: Default::default(),
+            enabled_categories: None,
         }
     }
     pub fn category_sub_menu(&mut self, ui: &mut egui::Ui) {
+        let mut reset_activation = None;
         CategorySelection::recursive_selection_ui(
             &mut self.cats_selection,
+            &self.core.categories,
             ui,
             &mut self.dirty.cats_selection,
+            "",
+            &mut reset_activation,
+        );
+        if let Some(category) = reset_activation {
+            self.reset_activation_data_of_category(&category);
+        }
+    }
+    /// shows the categories matching the query (see [PackCore::search_categories]) with checkboxes to toggle them
+    pub fn category_search_ui(&mut self, ui: &mut egui::Ui, query: &str) {
+        for full_name in self.core.search_categories(query) {
+            let Some(mut enabled) = CategorySelection::is_enabled(&self.cats_selection, &full_name)
+            else {
+                continue;
+            };
+            if ui.checkbox(&mut enabled, &full_name).changed() {
+                self.set_category_enabled(&full_name, enabled);
+            }
+        }
+    }
+    /// Enables or disables the category with this full name (like `a.b.c`) along with all of its sub categories.
+    /// The selection is saved to disk with the next [Self::save]. returns false if the category doesn't exist or is a separator.
+    pub fn set_category_enabled(&mut self, full_name: &str, enabled: bool) -> bool {
+        let found = CategorySelection::set_enabled(
+            &mut self.cats_selection,
+            &self.core.categories,
+            full_name,
+            enabled,
+        );
+        if found {
+            self.dirty.cats_selection = true;
+        }
+        found
+    }
+    /// merges `other` into this pack. see [PackCore::merge]. the new categories start with their default selection.
+    pub fn merge(&mut self, other: PackCore, strategy: MergeStrategy) -> PackMergeReport {
+        let report = self.core.merge(other, strategy);
+        CategorySelection::recursive_add_missing_category_selection(
+            &mut self.cats_selection,
+            &self.core.categories,
+        );
+        self.dirty.all = true;
+        self.dirty.cats_selection = true;
+        report
+    }
+    /// appends a node to the tbin of the trail with this guid. returns false if the trail or its tbin doesn't exist.
+    pub fn trail_push_node(&mut self, trail_guid: Uuid, node: Vec3) -> bool {
+        self.edit_trail_tbin(trail_guid, |tbin| {
+            tbin.push_node(node);
+            true
+        })
+        .unwrap_or_default()
+    }
+    /// inserts a node before `index` in the tbin of the trail with this guid.
+    /// returns false if the trail doesn't exist or the index is beyond the end of the trail.
+    pub fn trail_insert_node(&mut self, trail_guid: Uuid, index: usize, node: Vec3) -> bool {
+        self.edit_trail_tbin(trail_guid, |tbin| tbin.insert_node(index, node))
+            .unwrap_or_default()
+    }
+    /// removes the node at `index` from the tbin of the trail with this guid and returns it.
+    pub fn trail_remove_node(&mut self, trail_guid: Uuid, index: usize) -> Option<Vec3> {
+        self.edit_trail_tbin(trail_guid, |tbin| tbin.remove_node(index))
+            .flatten()
+    }
+    /// marks the edited tbin for saving, which also rebuilds the trails of the current map in the next [Self::tick]
+    fn edit_trail_tbin<T>(
+        &mut self,
+        trail_guid: Uuid,
+        edit: impl FnOnce(&mut TBin) -> T,
+    ) -> Option<T> {
+        let (path, tbin) = self.core.trail_tbin_mut(trail_guid)?;
+        let result = edit(tbin);
+        self.dirty.tbin.insert(path);
+        Some(result)
+    }
+    /// adds the trail with a new tbin to the pack and returns the guid of the trail.
+    /// Each recording gets an enabled category named after it, inside [Self::RECORDED_TRAILS_CATEGORY].
+    pub fn add_recorded_trail(&mut self, recorded: RecordedTrail) -> Uuid {
+        let guid = Uuid::new_v4();
+        // category names can't contain the `.` separator of full names
+        let mut name: String = recorded
+            .name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if name.is_empty() {
+            name = guid.simple().to_string();
+        }
+        let root = self
+            .core
+            .categories
+            .entry(Self::RECORDED_TRAILS_CATEGORY.to_string())
+            .or_insert_with(|| Category {
+                display_name: "Recorded Trails".to_string(),
+                separator: false,
+                default_enabled: true,
+                props: Default::default(),
+                children: Default::default(),
+            });
+        root.children
+            .entry(name.clone())
+            .or_insert_with(|| Category {
+                display_name: recorded.name.clone(),
+                separator: false,
+                default_enabled: true,
+                props: Default::default(),
+                children: Default::default(),
+            });
+        CategorySelection::recursive_add_missing_category_selection(
+            &mut self.cats_selection,
+            &self.core.categories,
         );
+
+        let path: RelativePath =
+            format!("{}/{}.trl", Self::RECORDED_TRAILS_CATEGORY, guid.simple())
+                .parse()
+                .unwrap();
+        let mut props = CommonAttributes::default();
+        props.set_trail_data(Some(path.clone()));
+        self.core
+            .maps
+            .entry(recorded.map_id)
+            .or_default()
+            .trails
+            .push(Trail {
+                guid,
+                map_id: recorded.map_id,
+                category: format!("{}.{name}", Self::RECORDED_TRAILS_CATEGORY),
+                props,
+            });
+        self.core
+            .tbins
+            .insert(path.clone(), TBin::new(recorded.map_id, 0, recorded.nodes));
+
+        self.dirty.cats = true;
+        self.dirty.cats_selection = true;
+        self.dirty.map_dirty.insert(recorded.map_id);
+        self.dirty.tbin.insert(path);
+        guid
     }
-    pub fn load_from_dir(dir: Arc<Dir>) -> Result<Self> {
+    pub fn load_from_dir(dir: Arc<Dir>, trail_config: &TrailParseConfig) -> Result<Self> {
         if !dir
             .try_exists(Self::CORE_PACK_DIR_NAME)
             .into_diagnostic()
@@ -115,7 +546,8 @@ impl LoadedPack {
             .open_dir(Self::CORE_PACK_DIR_NAME)
             .into_diagnostic()
             .wrap_err("failed to open core pack directory")?;
-        let core = load_pack_core_from_dir(&core_dir).wrap_err("failed to load pack from dir")?;
+        let core = load_pack_core_from_dir(&core_dir, trail_config)
+            .wrap_err("failed to load pack from dir")?;
 
         let cats_selection = (if dir.exists(Self::ACTIVATION_DATA_FILE_NAME) {
             match dir.read_to_string(Self::CATEGORY_SELECTION_FILE_NAME) {
@@ -178,17 +610,30 @@ impl LoadedPack {
             dirty: Default::default(),
             current_map_data: Default::default(),
             activation_data,
+            markers_in_info_range: Default::default(),
+            markers_in_copy_range: Default::default(),
+            enabled_categories: None,
         })
     }
-    pub fn tick(
+    /// saves the pack if needed and refreshes the markers/trails of the current map.
+    /// the result can be rendered with [Self::current_render_objects]
+    #[allow(clippy::too_many_arguments)]
+    pub fn update(
         &mut self,
         etx: &egui::Context,
-        _timestamp: f64,
-        joko_renderer: &mut joko_render::JokoRenderer,
         link: &Option<Arc<MumbleLink>>,
         default_tex_id: &TextureHandle,
+        map_id: u32,
+        events: &Sender<MarkerEvent>,
+        achievements: &AchievementCache,
+        professions: &ProfessionCache,
+        palette: ColorPalette,
     ) {
-        let categories_changed = self.dirty.cats_selection;
+        if self.dirty.cats_selection || self.dirty.cats || self.dirty.all {
+            self.enabled_categories = None;
+        }
+        let categories_changed = self.dirty.cats_selection || self.dirty.activation_data;
+        let tbins_changed = !self.dirty.tbin.is_empty();
         if self.dirty.is_dirty() {
             match self.save() {
                 Ok(_) => {}
@@ -202,145 +647,188 @@ impl LoadedPack {
             None => return,
         };
 
-        if self.current_map_data.map_id != link.map_id || categories_changed {
-            self.on_map_changed(etx, link, default_tex_id);
-        }
-        let z_near = joko_renderer.get_z_near();
-        for marker in self.current_map_data.active_markers.values() {
-            if let Some(mo) = marker.get_vertices_and_texture(link, z_near) {
-                joko_renderer.add_billboard(mo);
-            }
+        let now = OffsetDateTime::now_utc();
+        let reappeared = self
+            .current_map_data
+            .next_reappear
+            .map(|ts| ts <= now)
+            .unwrap_or_default();
+        let achievements_changed =
+            self.current_map_data.achievements_generation != achievements.generation();
+        let profession_changed =
+            self.current_map_data.profession_generation != professions.generation();
+        if self.current_map_data.map_id != map_id
+            || self.current_map_data.map_type != link.map_type
+            || categories_changed
+            || tbins_changed
+            || reappeared
+            || achievements_changed
+            || profession_changed
+            || self.current_map_data.palette != palette
+        {
+            self.on_map_changed(
+                etx,
+                link,
+                default_tex_id,
+                map_id,
+                now,
+                achievements,
+                professions,
+                palette,
+            );
         }
-        for trail in self.current_map_data.active_trails.values() {
-            joko_renderer.add_trail(TrailObject {
+        self.trigger_markers(link, now);
+        self.send_info_events(link, events);
+        self.send_copy_events(link, events);
+    }
+    /// The markers and trails of the current map which would be handed over to the renderer for this link at `time`
+    pub fn current_render_objects(
+        &self,
+        link: &MumbleLink,
+        z_near: f32,
+        size_scale: f32,
+        time: f64,
+    ) -> (Vec<MarkerObject>, Vec<TrailObject>) {
+        let markers = self
+            .current_map_data
+            .active_markers
+            .values()
+            .filter_map(|marker| marker.get_vertices_and_texture(link, z_near, size_scale, time))
+            .collect();
+        let trails = self
+            .current_map_data
+            .active_trails
+            .values()
+            .map(|trail| TrailObject {
                 vertices: trail.trail_object.vertices.clone(),
                 texture: trail.trail_object.texture,
-            });
-        }
+                map_id: trail.trail_object.map_id,
+                texture_speed: trail.trail_object.texture_speed,
+            })
+            .collect();
+        (markers, trails)
     }
+    #[allow(clippy::too_many_arguments)]
     fn on_map_changed(
         &mut self,
         etx: &egui::Context,
         link: &MumbleLink,
         default_tex_id: &TextureHandle,
+        map_id: u32,
+        now: OffsetDateTime,
+        achievements: &AchievementCache,
+        professions: &ProfessionCache,
+        palette: ColorPalette,
     ) {
         info!(
             self.current_map_data.map_id,
-            link.map_id, "current map data is updated."
+            map_id, "current map data is updated."
         );
+        if self.current_map_data.map_id != map_id {
+            self.activation_data.on_map_changed();
+        }
         self.current_map_data = Default::default();
-        if link.map_id == 0 {
+        self.current_map_data.achievements_generation = achievements.generation();
+        self.current_map_data.profession_generation = professions.generation();
+        self.current_map_data.map_type = link.map_type;
+        self.current_map_data.palette = palette;
+        let map_type = MapType::from_u32(link.map_type);
+        if map_id == 0 {
             return;
         }
-        self.current_map_data.map_id = link.map_id;
-        let mut enabled_cats_list = Default::default();
-        CategorySelection::recursive_get_full_names(
-            &self.cats_selection,
-            &self.core.categories,
-            &mut enabled_cats_list,
-            "",
-            &Default::default(),
-        );
+        self.current_map_data.map_id = map_id;
+        let enabled_cats_list = self.enabled_categories.take().unwrap_or_else(|| {
+            let mut list = Default::default();
+            CategorySelection::recursive_get_full_names(
+                &self.cats_selection,
+                &self.core.categories,
+                &mut list,
+                "",
+                &Default::default(),
+            );
+            list
+        });
         for (index, marker) in self
             .core
             .maps
-            .get(&link.map_id)
+            .get(&map_id)
             .unwrap_or(&Default::default())
             .markers
             .iter()
             .enumerate()
         {
-            if let Some(category_attributes) = enabled_cats_list.get(&marker.category) {
-                let mut attrs = marker.attrs.clone();
-                attrs.inherit_if_attr_none(category_attributes);
+            if let Some(mut attrs) =
+                inherited_attributes(&marker.attrs, &marker.category, &enabled_cats_list)
+            {
+                if achievements.is_hidden(&attrs)
+                    || professions.is_hidden(&attrs)
+                    || is_hidden_on_map_type(&attrs, map_type)
+                {
+                    continue;
+                }
+                recolor(&mut attrs, palette);
                 let key = &marker.guid;
-                if let Some(behavior) = attrs.get_behavior() {
-                    use crate::pack::Behavior;
-                    if match behavior {
-                        Behavior::AlwaysVisible => false,
-                        Behavior::ReappearOnMapChange
-                        | Behavior::ReappearOnDailyReset
-                        | Behavior::OnlyVisibleBeforeActivation
-                        | Behavior::ReappearAfterTimer
-                        | Behavior::ReappearOnMapReset
-                        | Behavior::WeeklyReset => self.activation_data.global.contains_key(key),
-                        Behavior::OncePerInstance => self
-                            .activation_data
-                            .global
-                            .get(key)
-                            .map(|a| match a {
-                                ActivationType::Instance(a) => a == &link.server_address,
-                                _ => false,
-                            })
-                            .unwrap_or_default(),
-                        Behavior::DailyPerChar => self
-                            .activation_data
-                            .character
-                            .get(&link.name)
-                            .map(|a| a.contains_key(key))
-                            .unwrap_or_default(),
-                        Behavior::OncePerInstancePerChar => self
-                            .activation_data
-                            .character
-                            .get(&link.name)
-                            .map(|a| {
-                                a.get(key)
-                                    .map(|a| match a {
-                                        ActivationType::Instance(a) => a == &link.server_address,
-                                        _ => false,
-                                    })
-                                    .unwrap_or_default()
-                            })
-                            .unwrap_or_default(),
-                        Behavior::WvWObjective => {
-                            false // ???
+                if let Some(behavior) = attrs.get_behavior().copied() {
+                    if let Some(activation) = self.activation_data.get_active(
+                        key,
+                        behavior,
+                        &link.name,
+                        &link.server_address,
+                        now,
+                    ) {
+                        if let ActivationType::TimeStamp(ts) = activation {
+                            let next = self.current_map_data.next_reappear.get_or_insert(*ts);
+                            *next = (*next).min(*ts);
                         }
-                    } {
+                        self.current_map_data.hidden_markers.push(*key);
                         continue;
                     }
                 }
-                if let Some(tex_path) = attrs.get_icon_file() {
-                    if !self.current_map_data.active_textures.contains_key(tex_path) {
-                        if let Some(tex) = self.core.textures.get(tex_path) {
-                            let img = image::load_from_memory(tex).unwrap();
-                            self.current_map_data.active_textures.insert(
-                                tex_path.clone(),
-                                etx.load_texture(
-                                    tex_path.as_str(),
-                                    ColorImage::from_rgba_unmultiplied(
-                                        [img.width() as _, img.height() as _],
-                                        img.into_rgba8().as_bytes(),
-                                    ),
-                                    Default::default(),
-                                ),
-                            );
-                        } else {
-                            info!(%tex_path, ?self.core.textures, "failed to find this texture");
+                // the frames of an animated icon replace the icon file. frames which fail to load are skipped
+                let mut frames: Vec<IconFrame> = attrs
+                    .get_icon_frames()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|path| {
+                        load_icon(etx, &self.core.textures, &mut self.current_map_data, path)
+                    })
+                    .filter_map(IconFrame::new)
+                    .collect();
+                if frames.is_empty() {
+                    let icon = match attrs.get_icon_file() {
+                        Some(tex_path) => load_icon(
+                            etx,
+                            &self.core.textures,
+                            &mut self.current_map_data,
+                            tex_path,
+                        ),
+                        None => {
+                            info!("no texture attribute on this marker");
+                            None
                         }
-                    }
-                } else {
-                    info!("no texture attribute on this marker");
+                    };
+                    frames.extend(IconFrame::new(
+                        icon.unwrap_or_else(|| (default_tex_id.clone(), None)),
+                    ));
                 }
-                let th = attrs
-                    .get_icon_file()
-                    .and_then(|path| self.current_map_data.active_textures.get(path))
-                    .unwrap_or(default_tex_id);
-                let texture_id = match th.id() {
-                    egui::TextureId::Managed(i) => i,
-                    egui::TextureId::User(_) => todo!(),
-                };
+                if frames.is_empty() {
+                    continue;
+                }
+                let frame_rate = attrs.get_icon_frame_rate().copied().unwrap_or_default();
 
                 let max_pixel_size = attrs.get_max_size().copied().unwrap_or(2048.0); // default taco max size
                 let min_pixel_size = attrs.get_min_size().copied().unwrap_or(5.0); // default taco min size
                 self.current_map_data.active_markers.insert(
                     index,
                     ActiveMarker {
-                        texture_id,
-                        _texture: th.clone(),
+                        frames,
+                        frame_rate,
                         attrs,
                         pos: marker.position,
+                        guid: marker.guid,
                         max_pixel_size,
                         min_pixel_size,
+                        map_id,
                     },
                 );
             }
@@ -349,15 +837,22 @@ impl LoadedPack {
         for (index, trail) in self
             .core
             .maps
-            .get(&link.map_id)
+            .get(&map_id)
             .unwrap_or(&Default::default())
             .trails
             .iter()
             .enumerate()
         {
-            if let Some(category_attributes) = enabled_cats_list.get(&trail.category) {
-                let mut common_attributes = trail.props.clone();
-                common_attributes.inherit_if_attr_none(category_attributes);
+            if let Some(mut common_attributes) =
+                inherited_attributes(&trail.props, &trail.category, &enabled_cats_list)
+            {
+                if achievements.is_hidden(&common_attributes)
+                    || professions.is_hidden(&common_attributes)
+                    || is_hidden_on_map_type(&common_attributes, map_type)
+                {
+                    continue;
+                }
+                recolor(&mut common_attributes, palette);
                 if let Some(tex_path) = common_attributes.get_texture() {
                     if !self.current_map_data.active_textures.contains_key(tex_path) {
                         if let Some(tex) = self.core.textures.get(tex_path) {
@@ -399,8 +894,9 @@ impl LoadedPack {
                 };
                 if let Some(active_trail) = ActiveTrail::get_vertices_and_texture(
                     &common_attributes,
-                    &tbin.nodes,
+                    &tbin.render_nodes(&tbin.config),
                     th.clone(),
+                    map_id,
                 ) {
                     self.current_map_data
                         .active_trails
@@ -408,6 +904,132 @@ impl LoadedPack {
                 }
             }
         }
+        self.enabled_categories = Some(enabled_cats_list);
+    }
+    /// The guids of markers on the current map which are hidden, because they were triggered and their behavior hides them for now
+    pub fn hidden_markers(&self) -> &[Uuid] {
+        &self.current_map_data.hidden_markers
+    }
+    /// records the activation of markers whose trigger range the player is in, and hides them if their behavior says so.
+    fn trigger_markers(&mut self, link: &MumbleLink, now: OffsetDateTime) {
+        let mut triggered = vec![];
+        for (index, marker) in self.current_map_data.active_markers.iter() {
+            let behavior = match marker.attrs.get_behavior() {
+                Some(behavior) => *behavior,
+                None => continue,
+            };
+            let range = marker
+                .attrs
+                .get_trigger_range()
+                .copied()
+                .unwrap_or(DEFAULT_TRIGGER_RANGE);
+            if marker.pos.distance(link.player_pos) > range {
+                continue;
+            }
+            if self.activation_data.trigger(
+                marker.guid,
+                behavior,
+                &marker.attrs,
+                &link.name,
+                link.server_address,
+                now,
+            ) {
+                triggered.push(*index);
+            }
+        }
+        for index in triggered {
+            if let Some(marker) = self.current_map_data.active_markers.shift_remove(&index) {
+                self.current_map_data.hidden_markers.push(marker.guid);
+            }
+            self.dirty.activation_data = true;
+        }
+    }
+    /// sends the events for active markers whose info range the player entered or left since the last tick
+    fn send_info_events(&mut self, link: &MumbleLink, events: &Sender<MarkerEvent>) {
+        let markers = self
+            .current_map_data
+            .active_markers
+            .values()
+            .filter_map(|marker| {
+                let info = marker.attrs.get_info()?;
+                let range = marker
+                    .attrs
+                    .get_info_range()
+                    .or(marker.attrs.get_trigger_range())
+                    .copied()
+                    .unwrap_or(DEFAULT_TRIGGER_RANGE);
+                Some((marker.guid, marker.pos, range, info.as_str()))
+            });
+        for event in info_range_events(&mut self.markers_in_info_range, markers, link.player_pos) {
+            if events.send(event).is_err() {
+                debug!("marker event receiver is dropped");
+            }
+        }
+    }
+    /// sends the copy events for active markers with a `copy` attribute whose trigger range the player entered since the last tick
+    fn send_copy_events(&mut self, link: &MumbleLink, events: &Sender<MarkerEvent>) {
+        let mut now_in_range = HashSet::new();
+        for marker in self.current_map_data.active_markers.values() {
+            let text = match marker.attrs.get_copy() {
+                Some(text) if !text.is_empty() => text,
+                _ => continue,
+            };
+            let range = marker
+                .attrs
+                .get_trigger_range()
+                .copied()
+                .unwrap_or(DEFAULT_TRIGGER_RANGE);
+            if marker.pos.distance(link.player_pos) > range {
+                continue;
+            }
+            if !self.markers_in_copy_range.contains(&marker.guid) {
+                let event = MarkerEvent::Copy {
+                    guid: marker.guid,
+                    text: text.to_string(),
+                    message: marker.attrs.get_copy_message().map(|m| m.to_string()),
+                };
+                if events.send(event).is_err() {
+                    debug!("marker event receiver is dropped");
+                }
+            }
+            now_in_range.insert(marker.guid);
+        }
+        self.markers_in_copy_range = now_in_range;
+    }
+    /// Clears all the activation data, so that every marker which was hidden due to its behavior will be shown again
+    pub fn reset_activation_data(&mut self) {
+        self.activation_data = Default::default();
+        self.dirty.activation_data = true;
+    }
+    /// Clears the activation data of markers which belong to `category` (full name like `a.b.c`) or any of its sub categories
+    pub fn reset_activation_data_of_category(&mut self, category: &str) {
+        let sub_category_prefix = format!("{category}.");
+        let guids: HashSet<Uuid> = self
+            .core
+            .maps
+            .values()
+            .flat_map(|map| map.markers.iter())
+            .filter(|marker| {
+                marker.category == category || marker.category.starts_with(&sub_category_prefix)
+            })
+            .map(|marker| marker.guid)
+            .collect();
+        self.activation_data.retain(|guid, _| !guids.contains(guid));
+        self.dirty.activation_data = true;
+    }
+    /// Clears the activation data with a timestamp which is already in the past
+    pub fn clear_expired_activation_data(&mut self) {
+        let now = time::OffsetDateTime::now_utc();
+        self.activation_data
+            .retain(|_, activation| match activation {
+                ActivationType::TimeStamp(ts) => *ts > now,
+                _ => true,
+            });
+        self.dirty.activation_data = true;
+    }
+    /// whether there are any changes which are not yet saved to disk
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.is_dirty()
     }
     pub fn save_all(&mut self) -> Result<()> {
         self.dirty.all = true;
@@ -430,6 +1052,21 @@ impl LoadedPack {
                 }
             }
         }
+        if std::mem::take(&mut self.dirty.activation_data) || self.dirty.all {
+            match serde_json::to_string_pretty(&self.activation_data) {
+                Ok(ad_json) => match self.dir.write(Self::ACTIVATION_DATA_FILE_NAME, ad_json) {
+                    Ok(_) => {
+                        debug!("wrote activation data to disk");
+                    }
+                    Err(e) => {
+                        error!(?e, "failed to write activation data to disk");
+                    }
+                },
+                Err(e) => {
+                    error!(?e, "failed to serialize activation data");
+                }
+            }
+        }
         self.dir
             .create_dir_all(Self::CORE_PACK_DIR_NAME)
             .into_diagnostic()
@@ -456,13 +1093,27 @@ impl LoadedPack {
 pub(crate) struct CurrentMapData {
     /// the map to which the current map data belongs to
     pub map_id: u32,
+    /// the raw `map_type` of MumbleLink which was used to filter the markers
+    pub map_type: u32,
     /// The textures that are being used by the markers, so must be kept alive by this hashmap
     pub active_textures: HashMap<RelativePath, TextureHandle>,
+    /// the small icons of the active markers. see [load_icon]
+    pub atlas: MarkerAtlas,
     /// The key is the index of the marker in the map markers
     /// Their position in the map markers serves as their "id" as uuids can be duplicates.
     pub active_markers: IndexMap<usize, ActiveMarker>,
     /// The key is the position/index of this trail in the map trails. same as markers
     pub active_trails: IndexMap<usize, ActiveTrail>,
+    /// markers of this map which are hidden by their behavior
+    pub hidden_markers: Vec<Uuid>,
+    /// the earliest time when one of the hidden markers reappears
+    pub next_reappear: Option<OffsetDateTime>,
+    /// the [AchievementCache::generation] which was used to filter the markers
+    pub achievements_generation: u64,
+    /// the [ProfessionCache::generation] which was used to filter the markers
+    pub profession_generation: u64,
+    /// the palette which was used for the colors of the active markers and trails
+    pub palette: ColorPalette,
 }
 
 /*
@@ -472,21 +1123,26 @@ pub(crate) struct CurrentMapData {
 */
 pub struct ActiveTrail {
     pub trail_object: TrailObject,
+    /// never read, but the renderer only gets the texture id, so this keeps the texture alive as long as the trail
+    #[allow(dead_code)]
     pub texture_handle: TextureHandle,
 }
 /// This is an active marker.
 /// It stores all the info that we need to scan every frame
 pub(crate) struct ActiveMarker {
-    /// texture id from managed textures
-    pub texture_id: u64,
-    /// owned texture handle to keep it alive
-    pub _texture: TextureHandle,
+    /// the textures of the icon. A static icon has a single frame. never empty
+    pub frames: Vec<IconFrame>,
+    /// frames per second of an animated icon. see [joko_render::billboard::animation_frame]
+    pub frame_rate: f32,
     /// position
     pub pos: Vec3,
+    pub guid: Uuid,
     /// billboard must not be bigger than this size in pixels
     pub max_pixel_size: f32,
     /// billboard must not be smaller than this size in pixels
     pub min_pixel_size: f32,
+    /// the map which this marker belongs to
+    pub map_id: u32,
     pub attrs: CommonAttributes,
 }
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -532,6 +1188,49 @@ impl CategorySelection {
             }
         }
     }
+    fn set_selected_recursive(&mut self, selected: bool) {
+        self.selected = selected;
+        for child in self.children.values_mut() {
+            child.set_selected_recursive(selected);
+        }
+    }
+    /// whether the category with this full name is selected. None if it doesn't exist
+    fn is_enabled(selection: &HashMap<String, CategorySelection>, full_name: &str) -> Option<bool> {
+        let mut selection = selection;
+        let mut selected = None;
+        for name in full_name.split('.') {
+            let selected_cat = selection.get(name)?;
+            selected = Some(selected_cat.selected);
+            selection = &selected_cat.children;
+        }
+        selected
+    }
+    /// see [LoadedPack::set_category_enabled]
+    fn set_enabled(
+        selection: &mut HashMap<String, CategorySelection>,
+        cats: &IndexMap<String, Category>,
+        full_name: &str,
+        enabled: bool,
+    ) -> bool {
+        let mut selection = selection;
+        let mut cats = cats;
+        let mut names = full_name.split('.').peekable();
+        while let Some(name) = names.next() {
+            let (Some(selected_cat), Some(cat)) = (selection.get_mut(name), cats.get(name)) else {
+                return false;
+            };
+            if names.peek().is_none() {
+                if cat.separator {
+                    return false;
+                }
+                selected_cat.set_selected_recursive(enabled);
+                return true;
+            }
+            selection = &mut selected_cat.children;
+            cats = &cat.children;
+        }
+        false
+    }
     fn recursive_create_category_selection(
         selection: &mut HashMap<String, CategorySelection>,
         cats: &IndexMap<String, Category>,
@@ -543,24 +1242,70 @@ impl CategorySelection {
             Self::recursive_create_category_selection(&mut s.children, &cat.children);
         }
     }
+    /// unlike [Self::recursive_create_category_selection], this keeps the selection of existing categories
+    fn recursive_add_missing_category_selection(
+        selection: &mut HashMap<String, CategorySelection>,
+        cats: &IndexMap<String, Category>,
+    ) {
+        for (cat_name, cat) in cats.iter() {
+            let s = selection
+                .entry(cat_name.clone())
+                .or_insert_with(|| CategorySelection {
+                    selected: cat.default_enabled,
+                    display_name: cat.display_name.clone(),
+                    children: Default::default(),
+                });
+            Self::recursive_add_missing_category_selection(&mut s.children, &cat.children);
+        }
+    }
+    /// `reset_activation` is set to the full name of a category if user wants to reset its activation data
+    /// separators can't be toggled, so they are just labels
     fn recursive_selection_ui(
         selection: &mut HashMap<String, CategorySelection>,
+        cats: &IndexMap<String, Category>,
         ui: &mut egui::Ui,
         changed: &mut bool,
+        parent_name: &str,
+        reset_activation: &mut Option<String>,
     ) {
         egui::ScrollArea::vertical().show(ui, |ui| {
-            for cat in selection.values_mut() {
+            for (name, cat) in selection.iter_mut() {
+                let full_name = if parent_name.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{parent_name}.{name}")
+                };
+                let core_cat = cats.get(name);
+                if core_cat.map(|c| c.separator).unwrap_or_default() {
+                    ui.label(egui::RichText::new(&cat.display_name).strong());
+                    continue;
+                }
                 ui.horizontal(|ui| {
                     if ui.checkbox(&mut cat.selected, "").changed() {
+                        cat.set_selected_recursive(cat.selected);
                         *changed = true;
                     }
-                    if !cat.children.is_empty() {
+                    let response = if !cat.children.is_empty() {
                         ui.menu_button(&cat.display_name, |ui: &mut egui::Ui| {
-                            Self::recursive_selection_ui(&mut cat.children, ui, changed);
-                        });
+                            Self::recursive_selection_ui(
+                                &mut cat.children,
+                                core_cat.map(|c| &c.children).unwrap_or(&Default::default()),
+                                ui,
+                                changed,
+                                &full_name,
+                                reset_activation,
+                            );
+                        })
+                        .response
                     } else {
-                        ui.label(&cat.display_name);
-                    }
+                        ui.label(&cat.display_name)
+                    };
+                    response.context_menu(|ui| {
+                        if ui.button("reset activations").clicked() {
+                            *reset_activation = Some(full_name.clone());
+                            ui.close_menu();
+                        }
+                    });
                 });
             }
         });
@@ -569,20 +1314,63 @@ impl CategorySelection {
 
 pub const _BILLBOARD_MAX_VISIBILITY_DISTANCE: f32 = 10000.0;
 
+/// A texture of an [ActiveMarker]
+pub(crate) struct IconFrame {
+    /// texture id from managed textures
+    pub texture_id: u64,
+    /// owned texture handle to keep it alive
+    pub _texture: TextureHandle,
+    /// where the icon is inside [Self::_texture], if it is an atlas page
+    pub atlas_placement: Option<AtlasPlacement>,
+}
+impl IconFrame {
+    /// takes the result of [load_icon]. None if the texture is not managed by egui, as the renderer only draws managed textures
+    fn new((texture, atlas_placement): (TextureHandle, Option<AtlasPlacement>)) -> Option<Self> {
+        let texture_id = match texture.id() {
+            egui::TextureId::Managed(i) => i,
+            egui::TextureId::User(id) => {
+                error!(id, "skipping marker icon with a user texture id");
+                return None;
+            }
+        };
+        Some(Self {
+            texture_id,
+            _texture: texture,
+            atlas_placement,
+        })
+    }
+}
+
 impl ActiveMarker {
-    pub fn get_vertices_and_texture(&self, link: &MumbleLink, z_near: f32) -> Option<MarkerObject> {
+    /// `size_scale` is [joko_render::JokoRenderer::get_billboard_size_scale].
+    /// `time` (seconds) picks the frame of animated icons
+    pub fn get_vertices_and_texture(
+        &self,
+        link: &MumbleLink,
+        z_near: f32,
+        size_scale: f32,
+        time: f64,
+    ) -> Option<MarkerObject> {
         let Self {
-            texture_id,
+            frames,
+            frame_rate,
             pos,
             attrs,
-            _texture,
             max_pixel_size,
             min_pixel_size,
+            map_id,
             ..
         } = self;
+        let frame =
+            &frames[joko_render::billboard::animation_frame(time, *frame_rate, frames.len())];
+        let uv = |uv: Vec2| {
+            frame
+                .atlas_placement
+                .map_or(uv, |placement| placement.remap_uv(uv))
+        };
         // let width = *width;
         // let height = *height;
-        let texture_id = *texture_id;
+        let texture_id = frame.texture_id;
         let pos = *pos;
         // filters
         if let Some(mounts) = attrs.get_mount() {
@@ -594,7 +1382,6 @@ impl ActiveMarker {
                 return None;
             }
         }
-        let height_offset = attrs.get_height_offset().copied().unwrap_or(1.5); // default taco height offset
         let fade_near = attrs.get_fade_near().copied().unwrap_or(-1.0) / INCHES_PER_METER;
         let fade_far = attrs.get_fade_far().copied().unwrap_or(-1.0) / INCHES_PER_METER;
         let icon_size = attrs.get_icon_size().copied().unwrap_or(1.0);
@@ -623,37 +1410,26 @@ impl ActiveMarker {
         mount
         specialization
         */
-        if fade_far > 0.0 && player_distance > fade_far {
+        if joko_render::billboard::fade_alpha(player_distance, fade_near_far) <= 0.0 {
             return None;
         }
         // markers are 1 meter in width/height by default
-        let mut pos = pos;
-        pos.y += height_offset;
+        let pos = marker_world_position(pos, attrs);
         let direction_to_marker = link.cam_pos - pos;
         let direction_to_side = direction_to_marker.normalize().cross(Vec3::Y);
 
         let far_offset = {
-            let dpi = if link.dpi_scaling <= 0 {
-                96.0
-            } else {
-                link.dpi as f32
-            } / 96.0;
-            let gw2_width = link.client_size.as_vec2().x / dpi;
-
-            // offset (half width i.e. distance from center of the marker to the side of the marker)
-            const SIDE_OFFSET_FAR: f32 = 1.0;
-            // the size of the projected on to the near plane
-            let near_offset = SIDE_OFFSET_FAR * icon_size * (z_near / camera_distance);
-            // convert the near_plane width offset into pixels by multiplying the near_ffset with gw2 window width
-            let near_offset_in_pixels = near_offset * gw2_width;
-
-            // we will clamp the texture width between min and max widths, and make sure that it is less than gw2 window width
-            let near_offset_in_pixels = near_offset_in_pixels
-                .clamp(*min_pixel_size, *max_pixel_size)
-                .min(gw2_width / 2.0);
-
-            let near_offset_of_marker = near_offset_in_pixels / gw2_width;
-            near_offset_of_marker * camera_distance / z_near
+            // scaling down the physical width is the same as scaling up the min/max pixel sizes
+            let gw2_width = link.client_size.as_vec2().x / size_scale;
+
+            joko_render::billboard::clamped_side_offset(
+                icon_size,
+                camera_distance,
+                z_near,
+                gw2_width,
+                *min_pixel_size,
+                *max_pixel_size,
+            )
         };
         // let pixel_ratio = width as f32 * (distance / z_near);// (near width / far width) = near_z / far_z;
         // we want to map 100 pixels to one meter in game
@@ -663,7 +1439,7 @@ impl ActiveMarker {
         let y_offset = x_offset; // seems all markers are squares
         let bottom_left = MarkerVertex {
             position: (pos - (direction_to_side * x_offset) - (Vec3::Y * y_offset)),
-            texture_coordinates: vec2(0.0, 1.0),
+            texture_coordinates: uv(vec2(0.0, 1.0)),
             alpha,
             color,
             fade_near_far,
@@ -671,21 +1447,21 @@ impl ActiveMarker {
 
         let top_left = MarkerVertex {
             position: (pos - (direction_to_side * x_offset) + (Vec3::Y * y_offset)),
-            texture_coordinates: vec2(0.0, 0.0),
+            texture_coordinates: uv(vec2(0.0, 0.0)),
             alpha,
             color,
             fade_near_far,
         };
         let top_right = MarkerVertex {
             position: (pos + (direction_to_side * x_offset) + (Vec3::Y * y_offset)),
-            texture_coordinates: vec2(1.0, 0.0),
+            texture_coordinates: uv(vec2(1.0, 0.0)),
             alpha,
             color,
             fade_near_far,
         };
         let bottom_right = MarkerVertex {
             position: (pos + (direction_to_side * x_offset) - (Vec3::Y * y_offset)),
-            texture_coordinates: vec2(1.0, 1.0),
+            texture_coordinates: uv(vec2(1.0, 1.0)),
             alpha,
             color,
             fade_near_far,
@@ -702,6 +1478,9 @@ impl ActiveMarker {
             vertices,
             texture: texture_id,
             distance: player_distance,
+            occlude: attrs.get_occlude().unwrap_or_default(),
+            map_id: *map_id,
+            space: Default::default(),
         })
     }
 }
@@ -711,6 +1490,7 @@ impl ActiveTrail {
         attrs: &CommonAttributes,
         positions: &[Vec3],
         texture: TextureHandle,
+        map_id: u32,
     ) -> Option<Self> {
         // can't have a trail without atleast two nodes
         if positions.len() < 2 {
@@ -739,7 +1519,7 @@ impl ActiveTrail {
                 // right side of the vector from first to second
                 let right_side = (second - first).normalize().cross(Vec3::Y).normalize();
 
-                let new_offset = (-1.0 * (first.distance(second) / height)) + y_offset;
+                let new_offset = -(first.distance(second) / height) + y_offset;
                 let first_left = MarkerVertex {
                     position: first - (right_side * horizontal_offset),
                     texture_coordinates: vec2(0.0, y_offset),
@@ -791,8 +1571,429 @@ impl ActiveTrail {
                     egui::TextureId::Managed(i) => i,
                     egui::TextureId::User(_) => todo!(),
                 },
+                map_id,
+                texture_speed: attrs.get_anim_speed().copied().unwrap_or_default(),
             },
             texture_handle: texture,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::temp_jokolay_dir;
+    use cap_std::fs_utf8::camino::Utf8PathBuf;
+
+    #[test]
+    fn markers_are_drawn_above_their_position() {
+        let pos = Vec3::new(1.0, 2.0, 3.0);
+        let mut attrs = CommonAttributes::default();
+        assert_eq!(marker_world_position(pos, &attrs), Vec3::new(1.0, 3.5, 3.0));
+        attrs.set_height_offset(Some(-2.0));
+        assert_eq!(marker_world_position(pos, &attrs), Vec3::new(1.0, 0.0, 3.0));
+    }
+
+    #[test]
+    fn markers_are_filtered_by_map_type() {
+        let mut wvw_only = CommonAttributes::default();
+        wvw_only.set_map_type(Some(
+            MapType::EternalBattlegrounds | MapType::BlueBorderlands | MapType::GreenBorderlands,
+        ));
+        // open world
+        assert!(is_hidden_on_map_type(&wvw_only, MapType::from_u32(5)));
+        // pvp
+        assert!(is_hidden_on_map_type(&wvw_only, MapType::from_u32(2)));
+        // eternal battlegrounds
+        assert!(!is_hidden_on_map_type(&wvw_only, MapType::from_u32(9)));
+        assert!(!is_hidden_on_map_type(&wvw_only, MapType::from_u32(11)));
+        // markers without the attribute show everywhere
+        for map_type in 0..20 {
+            assert!(!is_hidden_on_map_type(
+                &CommonAttributes::default(),
+                MapType::from_u32(map_type)
+            ));
+        }
+    }
+
+    /// 2023-01-01 00:00:00 UTC, a sunday
+    const NEW_YEAR: i64 = 1_672_531_200;
+    const HOUR: i64 = 60 * 60;
+
+    fn at(unix_timestamp: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(unix_timestamp).unwrap()
+    }
+
+    fn trigger(data: &mut ActivationData, guid: Uuid, behavior: Behavior, now: OffsetDateTime) {
+        assert!(data.trigger(
+            guid,
+            behavior,
+            &CommonAttributes::default(),
+            "character",
+            IpAddr::from([127, 0, 0, 1]),
+            now,
+        ));
+    }
+
+    fn is_hidden(
+        data: &ActivationData,
+        guid: &Uuid,
+        behavior: Behavior,
+        now: OffsetDateTime,
+    ) -> bool {
+        data.get_active(
+            guid,
+            behavior,
+            "character",
+            &IpAddr::from([127, 0, 0, 1]),
+            now,
+        )
+        .is_some()
+    }
+
+    #[test]
+    fn daily_reset_behavior_reappears_after_reset() {
+        let mut data = ActivationData::default();
+        let guid = Uuid::new_v4();
+        let behavior = Behavior::ReappearOnDailyReset;
+        assert!(!is_hidden(&data, &guid, behavior, at(NEW_YEAR + 23 * HOUR)));
+        trigger(&mut data, guid, behavior, at(NEW_YEAR + 23 * HOUR));
+        assert!(is_hidden(
+            &data,
+            &guid,
+            behavior,
+            at(NEW_YEAR + 23 * HOUR + 59 * 60)
+        ));
+        assert!(!is_hidden(&data, &guid, behavior, at(NEW_YEAR + 24 * HOUR)));
+        // per character dailies don't hide the marker for other characters
+        let behavior = Behavior::DailyPerChar;
+        trigger(&mut data, guid, behavior, at(NEW_YEAR));
+        assert!(is_hidden(&data, &guid, behavior, at(NEW_YEAR + HOUR)));
+        assert!(data
+            .get_active(
+                &guid,
+                behavior,
+                "other character",
+                &IpAddr::from([127, 0, 0, 1]),
+                at(NEW_YEAR + HOUR)
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn once_per_map_behavior_reappears_on_map_change() {
+        let mut data = ActivationData::default();
+        let guid = Uuid::new_v4();
+        let behavior = Behavior::ReappearOnMapChange;
+        trigger(&mut data, guid, behavior, at(NEW_YEAR));
+        // no matter how long we stay on the map
+        assert!(is_hidden(&data, &guid, behavior, at(NEW_YEAR + 48 * HOUR)));
+        data.on_map_changed();
+        assert!(!is_hidden(&data, &guid, behavior, at(NEW_YEAR)));
+    }
+
+    #[test]
+    fn info_event_fires_once_on_entry() {
+        let guid = Uuid::new_v4();
+        let mut in_range = HashSet::new();
+        let mut events_at = |x: f32| {
+            info_range_events(
+                &mut in_range,
+                [(guid, Vec3::ZERO, 5.0, "hello")].into_iter(),
+                Vec3::new(x, 0.0, 0.0),
+            )
+        };
+        assert!(events_at(10.0).is_empty());
+        assert_eq!(
+            events_at(4.0),
+            vec![MarkerEvent::InfoShown {
+                guid,
+                info: "hello".to_string()
+            }]
+        );
+        // still inside
+        assert!(events_at(1.0).is_empty());
+        assert_eq!(events_at(6.0), vec![MarkerEvent::InfoHidden { guid }]);
+        assert!(events_at(6.0).is_empty());
+    }
+
+    fn category(separator: bool, children: &[(&str, Category)]) -> Category {
+        Category {
+            display_name: Default::default(),
+            separator,
+            default_enabled: true,
+            props: Default::default(),
+            children: children
+                .iter()
+                .cloned()
+                .map(|(n, c)| (n.to_string(), c))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn markers_inherit_from_their_grandparent_category() {
+        let mut grandparent = category(
+            false,
+            &[(
+                "parent",
+                category(false, &[("child", category(false, &[]))]),
+            )],
+        );
+        let texture: RelativePath = "grandparent.png".parse().unwrap();
+        grandparent.props.set_texture(Some(texture.clone()));
+        grandparent.props.set_alpha(Some(0.5));
+        let mut pack = PackCore::default();
+        pack.categories
+            .insert("grandparent".to_string(), grandparent);
+        let selection = CategorySelection::default_from_pack_core(&pack);
+        let mut list = HashMap::new();
+        CategorySelection::recursive_get_full_names(
+            &selection,
+            &pack.categories,
+            &mut list,
+            "",
+            &Default::default(),
+        );
+
+        let mut marker_attrs = CommonAttributes::default();
+        marker_attrs.set_alpha(Some(1.0));
+        let attrs = inherited_attributes(&marker_attrs, "grandparent.parent.child", &list).unwrap();
+        assert_eq!(attrs.get_texture(), Some(&texture));
+        // attributes of the marker are kept
+        assert_eq!(attrs.get_alpha(), Some(&1.0));
+        assert!(inherited_attributes(&marker_attrs, "missing", &list).is_none());
+    }
+
+    #[test]
+    fn disabling_parent_category_filters_descendants() {
+        let mut pack = PackCore::default();
+        pack.categories.insert(
+            "parent".to_string(),
+            category(
+                false,
+                &[
+                    (
+                        "child",
+                        category(false, &[("grandchild", category(false, &[]))]),
+                    ),
+                    ("separator", category(true, &[])),
+                ],
+            ),
+        );
+        pack.categories
+            .insert("other".to_string(), category(false, &[]));
+        let mut selection = CategorySelection::default_from_pack_core(&pack);
+        let enabled = |selection: &HashMap<String, CategorySelection>| {
+            let mut list = HashMap::new();
+            CategorySelection::recursive_get_full_names(
+                selection,
+                &pack.categories,
+                &mut list,
+                "",
+                &Default::default(),
+            );
+            list
+        };
+        assert_eq!(enabled(&selection).len(), 5);
+
+        assert!(CategorySelection::set_enabled(
+            &mut selection,
+            &pack.categories,
+            "parent",
+            false
+        ));
+        let list = enabled(&selection);
+        assert_eq!(list.len(), 1);
+        assert!(list.contains_key("other"));
+        // children are disabled too, not just hidden by their parent
+        assert!(!selection["parent"].children["child"].children["grandchild"].selected);
+
+        assert!(CategorySelection::set_enabled(
+            &mut selection,
+            &pack.categories,
+            "parent",
+            true
+        ));
+        assert!(CategorySelection::set_enabled(
+            &mut selection,
+            &pack.categories,
+            "parent.child",
+            false
+        ));
+        let list = enabled(&selection);
+        assert!(list.contains_key("parent"));
+        assert!(!list.contains_key("parent.child.grandchild"));
+
+        assert!(!CategorySelection::set_enabled(
+            &mut selection,
+            &pack.categories,
+            "parent.separator",
+            false
+        ));
+        assert!(!CategorySelection::set_enabled(
+            &mut selection,
+            &pack.categories,
+            "missing",
+            false
+        ));
+    }
+
+    #[test]
+    fn reset_schedules() {
+        assert_eq!(next_daily_reset(at(NEW_YEAR)), at(NEW_YEAR + 24 * HOUR));
+        // monday 07:30
+        assert_eq!(
+            next_weekly_reset(at(NEW_YEAR)),
+            at(NEW_YEAR + 24 * HOUR + 7 * HOUR + 30 * 60)
+        );
+        assert_eq!(
+            next_weekly_reset(at(NEW_YEAR + 24 * HOUR + 8 * HOUR)),
+            at(NEW_YEAR + 8 * 24 * HOUR + 7 * HOUR + 30 * 60)
+        );
+        // two hour cycles starting at 00:30
+        assert_eq!(
+            next_map_reset(at(NEW_YEAR + HOUR), 7200.0, 1800.0),
+            Some(at(NEW_YEAR + 2 * HOUR + 30 * 60))
+        );
+        assert_eq!(
+            next_map_reset(at(NEW_YEAR + 10 * 60), 7200.0, 1800.0),
+            Some(at(NEW_YEAR + 30 * 60))
+        );
+    }
+
+    fn trigger_with_reset(
+        data: &mut ActivationData,
+        behavior: Behavior,
+        reset_length: &str,
+        reset_offset: &str,
+    ) -> bool {
+        let mut attrs = CommonAttributes::default();
+        attrs.set_reset_length(Some(reset_length.parse().unwrap()));
+        attrs.set_reset_offset(Some(reset_offset.parse().unwrap()));
+        data.trigger(
+            Uuid::new_v4(),
+            behavior,
+            &attrs,
+            "character",
+            IpAddr::from([127, 0, 0, 1]),
+            at(NEW_YEAR),
+        )
+    }
+
+    #[test]
+    fn invalid_reset_length_skips_activation() {
+        let mut data = ActivationData::default();
+        for behavior in [Behavior::ReappearAfterTimer, Behavior::ReappearOnMapReset] {
+            for length in ["NaN", "inf", "1e38"] {
+                assert!(
+                    !trigger_with_reset(&mut data, behavior, length, "0"),
+                    "{behavior:?} with resetLength={length}"
+                );
+            }
+        }
+        assert!(!trigger_with_reset(
+            &mut data,
+            Behavior::ReappearOnMapReset,
+            "60",
+            "NaN"
+        ));
+        assert!(data.global.is_empty());
+        // valid values still work
+        assert!(trigger_with_reset(
+            &mut data,
+            Behavior::ReappearAfterTimer,
+            "60",
+            "0"
+        ));
+        assert_eq!(data.global.len(), 1);
+    }
+
+    /// a pack on map 15 with a marker per `(guid, category)`. its dir is a new directory in the temp dir, returns the path to remove once the test is done
+    fn pack_with_markers(test_name: &str, markers: &[(u128, &str)]) -> (LoadedPack, Utf8PathBuf) {
+        let mut core = PackCore::default();
+        core.maps
+            .entry(15)
+            .or_default()
+            .markers
+            .extend(markers.iter().map(|(guid, category)| crate::pack::Marker {
+                guid: Uuid::from_u128(*guid),
+                position: Vec3::ZERO,
+                map_id: 15,
+                category: category.to_string(),
+                attrs: Default::default(),
+            }));
+        let (dir, path) = temp_jokolay_dir(test_name);
+        let mut pack = LoadedPack::new(core, Arc::new(dir));
+        pack.dirty = Default::default();
+        (pack, path)
+    }
+
+    fn activate(pack: &mut LoadedPack, guid: u128, character: Option<&str>) {
+        let activation = ActivationType::ReappearOnMapChange;
+        match character {
+            Some(character) => pack
+                .activation_data
+                .character
+                .entry(character.to_string())
+                .or_default()
+                .insert(Uuid::from_u128(guid), activation),
+            None => pack
+                .activation_data
+                .global
+                .insert(Uuid::from_u128(guid), activation),
+        };
+    }
+
+    fn active_guids(pack: &LoadedPack) -> Vec<u128> {
+        let mut guids: Vec<u128> = pack
+            .activation_data
+            .global
+            .keys()
+            .chain(
+                pack.activation_data
+                    .character
+                    .values()
+                    .flat_map(|c| c.keys()),
+            )
+            .map(|guid| guid.as_u128())
+            .collect();
+        guids.sort();
+        guids
+    }
+
+    #[test]
+    fn reset_activation_data_of_one_category() {
+        let (mut pack, path) = pack_with_markers(
+            "reset_activation_data_of_one_category",
+            &[(1, "a"), (2, "a.sub"), (3, "ab"), (4, "b")],
+        );
+        for guid in 1..=4 {
+            activate(&mut pack, guid, None);
+        }
+        // per character activations are cleared too
+        activate(&mut pack, 2, Some("character"));
+        assert_eq!(active_guids(&pack), [1, 2, 2, 3, 4]);
+
+        pack.reset_activation_data_of_category("a");
+        // `ab` is not a sub category of `a`
+        assert_eq!(active_guids(&pack), [3, 4]);
+        assert!(pack.activation_data.character.is_empty());
+        assert!(pack.dirty.activation_data);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn reset_all_activation_data() {
+        let (mut pack, path) =
+            pack_with_markers("reset_all_activation_data", &[(1, "a"), (2, "b")]);
+        activate(&mut pack, 1, None);
+        activate(&mut pack, 2, Some("character"));
+        assert!(!pack.dirty.is_dirty());
+
+        pack.reset_activation_data();
+        assert!(active_guids(&pack).is_empty());
+        assert!(pack.dirty.is_dirty());
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -15,30 +15,55 @@ editing a category's name/path means that you have to load all the maps that ref
 We will make not having a valid category/texture/tbin path as allowed. So, users can deal with the headache themselves.
 
 */
+mod achievements;
+mod atlas;
 mod live_pack;
+mod palette;
+mod professions;
+mod trail_recorder;
 use std::{
     collections::BTreeMap,
     io::Read,
-    sync::{Arc, Mutex},
+    sync::{
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
 };
 
 use cap_std::fs_utf8::Dir;
 use egui::{CollapsingHeader, ColorImage, TextureHandle, Window};
+use glam::Vec3;
 use image::EncodableLayout;
+use indexmap::IndexMap;
+use uuid::Uuid;
 
 use tracing::{error, info, info_span};
 
+use joko_render::billboard::{MarkerObject, TrailObject};
+use jokoapi::ApiClient;
 use jokolink::MumbleLink;
 use miette::{Context, IntoDiagnostic, Result};
 
-use self::live_pack::LoadedPack;
+use self::{
+    achievements::AchievementCache,
+    live_pack::{LoadedPack, MarkerEvent},
+    professions::ProfessionCache,
+    trail_recorder::TrailRecorder,
+};
+pub use palette::ColorPalette;
+pub use trail_recorder::RecordedTrail;
 
-use super::pack::PackCore;
+use super::{
+    io::{ImportMode, ImportPhase, PackImportReport},
+    pack::{MergeStrategy, PackCore, PackMergeReport, TrailParseConfig},
+};
 
 // pub const PACK_LIST_URL: &str = "https://packlist.jokolay.com/packlist.json";
 
 pub const MARKER_MANAGER_DIRECTORY_NAME: &str = "marker_manager";
 pub const MARKER_PACKS_DIRECTORY_NAME: &str = "packs";
+/// number of seconds for which we show the notification after copying the text of a marker
+const COPY_NOTIFICATION_DURATION: f64 = 3.0;
 // pub const MARKER_MANAGER_CONFIG_NAME: &str = "marker_manager_config.json";
 
 /// It manage everything that has to do with marker packs.
@@ -66,29 +91,129 @@ pub struct MarkerManager {
     /// This is the interval in number of seconds when we check if any of the packs need to be saved due to changes.
     /// This allows us to avoid saving the pack too often.
     pub save_interval: f64,
+    /// The map whose markers are shown. only changes after the map id from mumble link is stable for a while
+    map_debounce: MapDebounce,
+    /// packs send the events about markers near the player to this channel during [Self::tick]
+    event_sender: Sender<MarkerEvent>,
+    /// the ui receives the events in [Self::gui]
+    event_receiver: Receiver<MarkerEvent>,
+    /// hides the markers of completed achievements
+    achievements: AchievementCache,
+    /// hides the markers of other professions than the current character's
+    professions: ProfessionCache,
+    /// api key with `progression` and `characters` permissions. edited in the gui
+    api_key: String,
+    /// shared by the background api requests, so that they use the same cache and rate limit. recreated when the api key changes
+    api_client: Arc<ApiClient>,
+    /// remaps the colors of markers and trails for color vision deficiencies
+    pub color_palette: ColorPalette,
+    /// used when parsing the trails of packs. changes only apply to packs imported or loaded afterwards.
+    pub trail_parse_config: TrailParseConfig,
+    /// whether importing a pack fails when its markers reference missing categories
+    pub import_mode: ImportMode,
+    /// records the player positions into a trail. see [Self::start_recording]
+    trail_recorder: Option<TrailRecorder>,
+    /// while recording a trail, positions closer than this (in meters) to the previous node are skipped
+    pub recording_min_distance: f32,
+}
+
+/// A marker returned by [MarkerManager::markers_near]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NearbyMarker {
+    /// name of the pack which contains this marker
+    pub pack: String,
+    pub guid: Uuid,
+    pub position: Vec3,
+    /// full name of the category of this marker
+    pub category: String,
+    /// distance from the center of the query in meters
+    pub distance: f32,
+}
+
+/// During loading screens, gw2 briefly reports the destination map id and then some transient zero/invalid states.
+/// To avoid markers flashing, we only switch to a new map id after it is reported continuously for [Self::interval] seconds.
+#[derive(Debug)]
+pub struct MapDebounce {
+    /// map id which is currently active
+    pub stable_map_id: u32,
+    /// map id which is waiting to become stable and the timestamp when we first saw it
+    pending: Option<(u32, f64)>,
+    /// number of seconds for which a new map id must be stable
+    pub interval: f64,
+}
+impl Default for MapDebounce {
+    fn default() -> Self {
+        Self {
+            stable_map_id: 0,
+            pending: None,
+            interval: 0.5,
+        }
+    }
+}
+impl MapDebounce {
+    /// Takes the latest map id from mumble link and returns the map id which should be active.
+    /// zero map id is ignored and doesn't reset a pending map id.
+    pub fn update(&mut self, map_id: u32, timestamp: f64) -> u32 {
+        if map_id == 0 {
+            return self.stable_map_id;
+        }
+        if map_id == self.stable_map_id {
+            self.pending = None;
+            return self.stable_map_id;
+        }
+        match self.pending {
+            Some((pending_id, since)) if pending_id == map_id => {
+                if timestamp - since >= self.interval {
+                    self.stable_map_id = map_id;
+                    self.pending = None;
+                }
+            }
+            _ => {
+                self.pending = Some((map_id, timestamp));
+            }
+        }
+        self.stable_map_id
+    }
 }
 
 #[derive(Debug, Default)]
+// PackDone is much bigger than the other variants, but there's only ever one status, behind a mutex
+#[allow(clippy::large_enum_variant)]
 pub(crate) enum ImportStatus {
     #[default]
     UnInitialized,
     WaitingForFileChooser,
-    LoadingPack(std::path::PathBuf),
-    PackDone(String, PackCore, bool),
+    /// the file path and the latest progress of the import
+    LoadingPack(std::path::PathBuf, Option<(ImportPhase, usize, usize)>),
+    PackDone(String, PackCore, PackImportReport, bool),
     PackError(miette::Report),
 }
 #[derive(Debug, Default)]
 pub(crate) struct MarkerManagerUI {
     // tf is this type supposed to be? maybe we should have used a ECS for this reason.
     pub import_status: Option<Arc<Mutex<ImportStatus>>>,
+    /// map id from the latest link. used to show which packs are doing anything on the current map
+    pub current_map_id: u32,
+    /// info texts of the markers near the player. updated from [MarkerEvent]s
+    pub marker_infos: IndexMap<Uuid, String>,
+    /// the notification about the text copied from a marker and the time until which it is shown
+    pub copy_notification: Option<(String, f64)>,
+    /// filters the categories shown in the markers menu
+    pub category_search: String,
+    /// name of the next recorded trail
+    pub recording_name: String,
+    /// the pack to which the recorded trail is added
+    pub recording_pack: String,
 }
 
 #[derive(Debug, Default)]
+#[allow(dead_code)]
 pub struct PackList {
     pub packs: BTreeMap<String, PackEntry>,
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub struct PackEntry {
     pub url: url::Url,
     pub description: String,
@@ -119,6 +244,8 @@ impl MarkerManager {
             .into_diagnostic()
             .wrap_err("failed to open marker packs dir")?;
         let mut packs: BTreeMap<String, LoadedPack> = Default::default();
+        let (event_sender, event_receiver) = std::sync::mpsc::channel();
+        let trail_parse_config = TrailParseConfig::default();
 
         for entry in marker_packs_dir
             .entries()
@@ -136,7 +263,7 @@ impl MarkerManager {
                     .wrap_err("failed to open pack entry as directory")?;
                 {
                     let span_guard = info_span!("loading pack from dir", name).entered();
-                    match LoadedPack::load_from_dir(pack_dir.into()) {
+                    match LoadedPack::load_from_dir(pack_dir.into(), &trail_parse_config) {
                         Ok(lp) => {
                             packs.insert(name, lp);
                         }
@@ -156,10 +283,26 @@ impl MarkerManager {
             ui_data: Default::default(),
             save_interval: 0.0,
             missing_texture: None,
+            map_debounce: Default::default(),
+            event_sender,
+            event_receiver,
+            achievements: Default::default(),
+            professions: Default::default(),
+            api_key: Default::default(),
+            api_client: Arc::new(ApiClient::new("")),
+            color_palette: Default::default(),
+            import_mode: Default::default(),
+            trail_parse_config,
+            trail_recorder: None,
+            recording_min_distance: 1.0,
         })
     }
 
-    fn pack_importer(import_status: Arc<Mutex<ImportStatus>>) {
+    fn pack_importer(
+        import_status: Arc<Mutex<ImportStatus>>,
+        trail_config: TrailParseConfig,
+        mode: ImportMode,
+    ) {
         rayon::spawn(move || {
             *import_status.lock().unwrap() = ImportStatus::WaitingForFileChooser;
 
@@ -167,12 +310,23 @@ impl MarkerManager {
                 .add_filter("taco", &["zip", "taco"])
                 .pick_file()
             {
-                *import_status.lock().unwrap() = ImportStatus::LoadingPack(file_path.clone());
+                *import_status.lock().unwrap() = ImportStatus::LoadingPack(file_path.clone(), None);
 
-                let result = import_pack_from_zip_file_path(file_path);
+                let result = import_pack_from_zip_file_path(
+                    file_path.clone(),
+                    &trail_config,
+                    mode,
+                    |phase, done, total| {
+                        *import_status.lock().unwrap() = ImportStatus::LoadingPack(
+                            file_path.clone(),
+                            Some((phase, done, total)),
+                        );
+                    },
+                );
                 match result {
-                    Ok((name, pack)) => {
-                        *import_status.lock().unwrap() = ImportStatus::PackDone(name, pack, false);
+                    Ok((name, pack, report)) => {
+                        *import_status.lock().unwrap() =
+                            ImportStatus::PackDone(name, pack, report, false);
                     }
                     Err(e) => {
                         *import_status.lock().unwrap() = ImportStatus::PackError(e);
@@ -184,6 +338,24 @@ impl MarkerManager {
             }
         });
     }
+    fn pack_exporter(name: String, core: PackCore) {
+        rayon::spawn(move || {
+            if let Some(file_path) = rfd::FileDialog::new()
+                .add_filter("taco", &["taco", "zip"])
+                .set_file_name(format!("{name}.taco"))
+                .save_file()
+            {
+                match crate::io::export_pack_to_taco_zip(&core, &file_path) {
+                    Ok(_) => {
+                        info!(name, ?file_path, "exported marker pack");
+                    }
+                    Err(e) => {
+                        error!(?e, name, "failed to export marker pack");
+                    }
+                }
+            }
+        });
+    }
     pub fn tick(
         &mut self,
         etx: &egui::Context,
@@ -191,6 +363,24 @@ impl MarkerManager {
         joko_renderer: &mut joko_render::JokoRenderer,
         link: &Option<Arc<MumbleLink>>,
     ) {
+        self.update(etx, timestamp, link);
+        if let Some(link) = link.as_ref() {
+            let (markers, trails) = self.current_render_objects(
+                link,
+                joko_renderer.get_z_near(),
+                joko_renderer.get_billboard_size_scale(),
+                timestamp,
+            );
+            for mo in markers {
+                joko_renderer.add_billboard(mo);
+            }
+            for trail in trails {
+                joko_renderer.add_trail(trail);
+            }
+        }
+    }
+    /// Everything that [Self::tick] does except handing the markers/trails to the renderer
+    pub fn update(&mut self, etx: &egui::Context, timestamp: f64, link: &Option<Arc<MumbleLink>>) {
         if self.missing_texture.is_none() {
             let img = image::load_from_memory(include_bytes!("../pack/marker.png")).unwrap();
             let size = [img.width() as _, img.height() as _];
@@ -204,33 +394,279 @@ impl MarkerManager {
             ));
         }
 
+        if let Some(link) = link.as_ref() {
+            self.ui_data.current_map_id = link.map_id;
+        }
+        let map_id = self.map_debounce.update(
+            link.as_ref().map(|link| link.map_id).unwrap_or_default(),
+            timestamp,
+        );
+        self.achievements.tick(timestamp, &self.api_client);
+        if let Some(link) = link.as_ref() {
+            self.professions.tick(&self.api_client, &link.name);
+        }
+        if let (Some(recorder), Some(link)) = (self.trail_recorder.as_mut(), link.as_ref()) {
+            recorder.record(link.map_id, link.player_pos);
+        }
         for pack in self.packs.values_mut() {
-            pack.tick(
+            pack.update(
                 etx,
-                timestamp,
-                joko_renderer,
                 link,
                 self.missing_texture.as_ref().unwrap(),
+                map_id,
+                &self.event_sender,
+                &self.achievements,
+                &self.professions,
+                self.color_palette,
             );
         }
     }
+    /// Saves any pending changes of the packs. Packs are usually saved during [Self::tick],
+    /// but changes from the gui of the last frame would be lost if we exit without calling this.
+    pub fn shutdown(&mut self) {
+        for (name, pack) in self.packs.iter_mut() {
+            if pack.is_dirty() {
+                if let Err(e) = pack.save() {
+                    error!(?e, name, "failed to save marker pack during shutdown");
+                }
+            }
+        }
+    }
+    /// Returns exactly what would be handed over to the renderer by all the packs for the current map and category selection.
+    /// `z_near` and `size_scale` should be the same as [joko_render::JokoRenderer::get_z_near] and [joko_render::JokoRenderer::get_billboard_size_scale].
+    /// `time` (seconds) picks the frames of animated icons
+    pub fn current_render_objects(
+        &self,
+        link: &MumbleLink,
+        z_near: f32,
+        size_scale: f32,
+        time: f64,
+    ) -> (Vec<MarkerObject>, Vec<TrailObject>) {
+        let mut markers = vec![];
+        let mut trails = vec![];
+        for pack in self.packs.values() {
+            let (pack_markers, pack_trails) =
+                pack.current_render_objects(link, z_near, size_scale, time);
+            markers.extend(pack_markers);
+            trails.extend(pack_trails);
+        }
+        (markers, trails)
+    }
+    /// The markers of all packs on `map_id` which are within `radius` (in meters) of `center`, sorted by distance.
+    /// This ignores the category selection and behaviors, so it also returns markers that are not being drawn.
+    pub fn markers_near(&self, map_id: u32, center: Vec3, radius: f32) -> Vec<NearbyMarker> {
+        let mut markers = vec![];
+        for (pack_name, pack) in self.packs.iter() {
+            if let Some(map) = pack.core.maps.get(&map_id) {
+                markers.extend(map.markers_near(center, radius).into_iter().map(
+                    |(distance, marker)| NearbyMarker {
+                        pack: pack_name.clone(),
+                        guid: marker.guid,
+                        position: marker.position,
+                        category: marker.category.clone(),
+                        distance,
+                    },
+                ));
+            }
+        }
+        markers.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        markers
+    }
+    /// saves the category `full_name` of the pack `pack_name` with its sub categories and their markers as a new taco pack.
+    /// returns false if the pack or category doesn't exist. see [PackCore::extract_subtree]
+    pub fn export_category(&self, pack_name: &str, full_name: &str) -> bool {
+        let Some(core) = self
+            .packs
+            .get(pack_name)
+            .and_then(|pack| pack.core.extract_subtree(full_name))
+        else {
+            return false;
+        };
+        Self::pack_exporter(format!("{pack_name}_{full_name}"), core);
+        true
+    }
+    /// merges a copy of the pack `from` into the pack `into`. `from` is left as it is, so that users can delete it if they want to.
+    /// returns None if either of them doesn't exist.
+    pub fn merge_packs(
+        &mut self,
+        into: &str,
+        from: &str,
+        strategy: MergeStrategy,
+    ) -> Option<PackMergeReport> {
+        if into == from {
+            return None;
+        }
+        let other = self.packs.get(from)?.core.clone();
+        self.packs
+            .get_mut(into)
+            .map(|pack| pack.merge(other, strategy))
+    }
+    /// starts recording the player positions on `map_id` into a trail called `name`. discards any recording in progress.
+    pub fn start_recording(&mut self, name: impl Into<String>, map_id: u32) {
+        self.trail_recorder = Some(TrailRecorder::new(
+            name.into(),
+            map_id,
+            self.recording_min_distance,
+        ));
+    }
+    pub fn is_recording(&self) -> bool {
+        self.trail_recorder.is_some()
+    }
+    /// returns the recorded trail or None if we were not recording
+    pub fn stop_recording(&mut self) -> Option<RecordedTrail> {
+        self.trail_recorder.take().map(TrailRecorder::finish)
+    }
+    /// adds the recorded trail to the pack `pack_name`, which saves it in the next [Self::tick].
+    /// returns the guid of the new trail or None if the pack doesn't exist.
+    pub fn save_recorded_trail(&mut self, pack_name: &str, trail: RecordedTrail) -> Option<Uuid> {
+        self.packs
+            .get_mut(pack_name)
+            .map(|pack| pack.add_recorded_trail(trail))
+    }
+    /// appends a node to the trail with this guid in the pack `pack_name`. see [LoadedPack::trail_push_node]
+    pub fn trail_push_node(&mut self, pack_name: &str, trail_guid: Uuid, node: Vec3) -> bool {
+        self.packs
+            .get_mut(pack_name)
+            .map(|pack| pack.trail_push_node(trail_guid, node))
+            .unwrap_or_default()
+    }
+    /// inserts a node before `index` in the trail with this guid in the pack `pack_name`. see [LoadedPack::trail_insert_node]
+    pub fn trail_insert_node(
+        &mut self,
+        pack_name: &str,
+        trail_guid: Uuid,
+        index: usize,
+        node: Vec3,
+    ) -> bool {
+        self.packs
+            .get_mut(pack_name)
+            .map(|pack| pack.trail_insert_node(trail_guid, index, node))
+            .unwrap_or_default()
+    }
+    /// removes the node at `index` from the trail with this guid in the pack `pack_name`. see [LoadedPack::trail_remove_node]
+    pub fn trail_remove_node(
+        &mut self,
+        pack_name: &str,
+        trail_guid: Uuid,
+        index: usize,
+    ) -> Option<Vec3> {
+        self.packs
+            .get_mut(pack_name)
+            .and_then(|pack| pack.trail_remove_node(trail_guid, index))
+    }
+    /// Enables or disables a category (by its full name like `a.b.c`) and all of its sub categories in the pack `pack_name`.
+    /// returns false if the pack or category doesn't exist, or if the category is a separator.
+    pub fn set_category_enabled(
+        &mut self,
+        pack_name: &str,
+        full_name: &str,
+        enabled: bool,
+    ) -> bool {
+        self.packs
+            .get_mut(pack_name)
+            .map(|pack| pack.set_category_enabled(full_name, enabled))
+            .unwrap_or_default()
+    }
+    /// Returns the name of each loaded pack which has markers on the given map, along with the number of markers it contributes.
+    /// Packs without any markers on this map are skipped.
+    pub fn packs_active_on_map(&self, map_id: u32) -> Vec<(String, usize)> {
+        self.packs
+            .iter()
+            .filter_map(|(name, pack)| {
+                let count = pack
+                    .core
+                    .maps
+                    .get(&map_id)
+                    .map(|map| map.markers.len())
+                    .unwrap_or_default();
+                (count != 0).then(|| (name.clone(), count))
+            })
+            .collect()
+    }
     pub fn menu_ui(&mut self, ui: &mut egui::Ui) {
         ui.menu_button("Markers", |ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.ui_data.category_search)
+                    .hint_text("search categories"),
+            );
+            let query = self.ui_data.category_search.trim();
             for pack in self.packs.values_mut() {
-                pack.category_sub_menu(ui);
+                if query.is_empty() {
+                    pack.category_sub_menu(ui);
+                } else {
+                    pack.category_search_ui(ui, query);
+                }
             }
         });
     }
     pub fn gui(&mut self, etx: &egui::Context, open: &mut bool) {
+        for event in self.event_receiver.try_iter() {
+            match event {
+                MarkerEvent::InfoShown { guid, info } => {
+                    self.ui_data.marker_infos.insert(guid, info);
+                }
+                MarkerEvent::InfoHidden { guid } => {
+                    self.ui_data.marker_infos.shift_remove(&guid);
+                }
+                MarkerEvent::Copy { text, message, .. } => {
+                    let notification = message.unwrap_or_else(|| format!("copied {text}"));
+                    etx.output_mut(|o| o.copied_text = text);
+                    let until = etx.input(|i| i.time) + COPY_NOTIFICATION_DURATION;
+                    self.ui_data.copy_notification = Some((notification, until));
+                }
+            }
+        }
+        if let Some((notification, until)) = self.ui_data.copy_notification.as_ref() {
+            if etx.input(|i| i.time) > *until {
+                self.ui_data.copy_notification = None;
+            } else {
+                egui::Area::new("marker copy notification")
+                    .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -150.0])
+                    .interactable(false)
+                    .show(etx, |ui| {
+                        ui.label(
+                            egui::RichText::new(notification)
+                                .heading()
+                                .color(egui::Color32::WHITE),
+                        );
+                    });
+            }
+        }
+        if !self.ui_data.marker_infos.is_empty() {
+            egui::Area::new("marker infos")
+                .anchor(egui::Align2::CENTER_TOP, [0.0, 100.0])
+                .interactable(false)
+                .show(etx, |ui| {
+                    for info in self.ui_data.marker_infos.values() {
+                        ui.label(
+                            egui::RichText::new(info)
+                                .heading()
+                                .color(egui::Color32::WHITE),
+                        );
+                    }
+                });
+        }
         Window::new("Marker Manager").open(open).show(etx, |ui| -> Result<()> {
             CollapsingHeader::new("Loaded Packs").show(ui, |ui| {
                 egui::Grid::new("packs").striped(true).show(ui, |ui| {
                     let mut delete = vec![];
-                for pack in self.packs.keys() {
-                    ui.label(pack);
+                for (pack_name, pack) in self.packs.iter_mut() {
+                    ui.label(pack_name);
+                    ui.label(format!("{} categories", pack.core.iter_categories().count()));
+                    ui.label(format!("{} hidden", pack.hidden_markers().len())).on_hover_text("markers on the current map, which are hidden due to their behavior");
                     if ui.button("delete").clicked() {
-                        delete.push(pack.clone());
+                        delete.push(pack_name.clone());
+                    }
+                    if ui.button("export").on_hover_text("save this pack as a taco file").clicked() {
+                        Self::pack_exporter(pack_name.clone(), pack.core.clone());
                     }
+                    if ui.button("reset activations").on_hover_text("show all the markers which were hidden due to their behavior").clicked() {
+                        pack.reset_activation_data();
+                    }
+                    if ui.button("clear expired").on_hover_text("show the markers whose activation timer has run out").clicked() {
+                        pack.clear_expired_activation_data();
+                    }
+                    ui.end_row();
                 }
                 for pack_name in delete {
                     self.packs.remove(&pack_name);
@@ -242,16 +678,107 @@ impl MarkerManager {
                 }
             });
             });
+            CollapsingHeader::new("Achievements").show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("api key: ");
+                    if ui
+                        .add(egui::TextEdit::singleline(&mut self.api_key).password(true))
+                        .on_hover_text("api key with progression and characters permissions. markers of completed achievements and other professions are hidden")
+                        .lost_focus()
+                    {
+                        if self.api_client.api_key() != self.api_key {
+                            self.api_client = Arc::new(ApiClient::new(self.api_key.clone()));
+                        }
+                        self.achievements.refresh_now();
+                        self.professions.refresh_now();
+                    }
+                });
+                if ui.button("refresh").clicked() {
+                    // otherwise, we would get the cached progress back
+                    self.api_client.clear_cache();
+                    self.achievements.refresh_now();
+                    self.professions.refresh_now();
+                }
+            });
+            CollapsingHeader::new("Colors").show(ui, |ui| {
+                egui::ComboBox::from_label("color palette")
+                    .selected_text(self.color_palette.name())
+                    .show_ui(ui, |ui| {
+                        for palette in ColorPalette::ALL {
+                            ui.selectable_value(&mut self.color_palette, palette, palette.name());
+                        }
+                    })
+                    .response
+                    .on_hover_text("for color vision deficiencies");
+            });
+            CollapsingHeader::new("Active On Current Map").show(ui, |ui| {
+                let map_id = self.ui_data.current_map_id;
+                let active_packs = self.packs_active_on_map(map_id);
+                if active_packs.is_empty() {
+                    ui.label(format!("no pack has markers on map {map_id}"));
+                } else {
+                    egui::Grid::new("active packs").striped(true).show(ui, |ui| {
+                        for (pack_name, count) in active_packs {
+                            ui.label(pack_name);
+                            ui.label(format!("{count} markers"));
+                            ui.end_row();
+                        }
+                    });
+                }
+            });
+            CollapsingHeader::new("Record Trail").show(ui, |ui| {
+                if self.is_recording() {
+                    ui.label(format!("recording {} into {}", self.ui_data.recording_name, self.ui_data.recording_pack));
+                    ui.horizontal(|ui| {
+                        if ui.button("stop").on_hover_text("add the recorded trail to the pack").clicked() {
+                            if let Some(trail) = self.stop_recording() {
+                                let pack_name = self.ui_data.recording_pack.clone();
+                                if self.save_recorded_trail(&pack_name, trail).is_none() {
+                                    error!(pack_name, "failed to add recorded trail, as the pack doesn't exist");
+                                }
+                            }
+                        }
+                        if ui.button("discard").clicked() {
+                            self.stop_recording();
+                        }
+                    });
+                } else {
+                    ui.horizontal(|ui| {
+                        ui.label("name: ");
+                        ui.text_edit_singleline(&mut self.ui_data.recording_name);
+                    });
+                    egui::ComboBox::from_label("pack")
+                        .selected_text(self.ui_data.recording_pack.as_str())
+                        .show_ui(ui, |ui| {
+                            for pack_name in self.packs.keys() {
+                                ui.selectable_value(&mut self.ui_data.recording_pack, pack_name.clone(), pack_name);
+                            }
+                        });
+                    let map_id = self.ui_data.current_map_id;
+                    let can_start = map_id != 0 && self.packs.contains_key(&self.ui_data.recording_pack);
+                    if ui.add_enabled(can_start, egui::Button::new("record")).on_hover_text("records your positions on the current map into a trail").clicked() {
+                        self.start_recording(self.ui_data.recording_name.clone(), map_id);
+                    }
+                }
+            });
 
             if self.ui_data.import_status.is_some() {
                 if ui.button("clear").on_hover_text(
                     "This will cancel any pack import in progress. If import is already finished, then it wil simply clear the import status").clicked() {
                     self.ui_data.import_status = None;
                 }
-            } else if ui.button("import pack").on_hover_text("select a taco/zip file to import the marker pack from").clicked() {
-                let import_status = Arc::new(Mutex::default());
-                self.ui_data.import_status = Some(import_status.clone());
-                Self::pack_importer(import_status);
+            } else {
+                ui.horizontal(|ui| {
+                    if ui.button("import pack").on_hover_text("select a taco/zip file to import the marker pack from").clicked() {
+                        let import_status = Arc::new(Mutex::default());
+                        self.ui_data.import_status = Some(import_status.clone());
+                        Self::pack_importer(import_status, self.trail_parse_config, self.import_mode);
+                    }
+                    ui.selectable_value(&mut self.import_mode, ImportMode::Lenient, "lenient")
+                        .on_hover_text("import markers with missing categories anyway");
+                    ui.selectable_value(&mut self.import_mode, ImportMode::Strict, "strict")
+                        .on_hover_text("fail the import if any marker references a missing category");
+                });
             }
             if let Some(import_status) = self.ui_data.import_status.as_ref() {
                 if let Ok(mut status) = import_status.lock() {
@@ -264,16 +791,54 @@ impl MarkerManager {
                                 "wailting for the file dialog. choose a taco/zip file to import",
                             );
                         }
-                        ImportStatus::LoadingPack(p) => {
+                        ImportStatus::LoadingPack(p, progress) => {
                             ui.label(format!("pack is being imported from {p:?}"));
+                            if let Some((phase, done, total)) = progress {
+                                ui.add(
+                                    egui::ProgressBar::new(*done as f32 / (*total).max(1) as f32)
+                                        .text(format!("{}: {done}/{total}", phase.name())),
+                                );
+                            }
                         }
-                        ImportStatus::PackDone(name, pack, saved) => {
+                        ImportStatus::PackDone(name, pack, report, saved) => {
+                            let missing = report.missing_assets();
+                            if !missing.is_empty() {
+                                ui.colored_label(
+                                    egui::Color32::YELLOW,
+                                    format!(
+                                        "{} missing textures and {} missing trails",
+                                        missing.textures.len(),
+                                        missing.trails.len()
+                                    ),
+                                )
+                                .on_hover_ui(|ui| {
+                                    for asset in missing.textures.iter().chain(missing.trails.iter()) {
+                                        ui.label(format!(
+                                            "{} referenced by {} ({}) in {}",
+                                            asset.path, asset.category, asset.guid, asset.source_file
+                                        ));
+                                    }
+                                });
+                            }
+
+                            let telemetry = report.telemetry();
+                            ui.label(format!("imported in {:.2?}", telemetry.total))
+                                .on_hover_ui(|ui| {
+                                    ui.label(format!("textures: {:.2?}", telemetry.texture_loading));
+                                    ui.label(format!("trails: {:.2?}", telemetry.tbin_loading));
+                                    ui.label(format!("xml and categories: {:.2?}", telemetry.xml_parsing));
+                                    ui.label(format!("markers and trails: {:.2?}", telemetry.elements_registering));
+                                });
 
                             if !*saved {
                                 ui.horizontal(|ui| {
                                     ui.label("choose a pack name: ");    
                                     ui.text_edit_singleline(name);
                                 });
+                                if ui.button("deduplicate textures").on_hover_text("removes the textures with the same bytes as another texture of the pack").clicked() {
+                                    let aliases = pack.dedup_textures();
+                                    info!(removed = aliases.len(), "deduplicated textures");
+                                }
                                 let name = name.as_str();
                                 if ui.button("save").clicked() {
 
@@ -324,7 +889,12 @@ impl MarkerManager {
     }
 }
 
-fn import_pack_from_zip_file_path(file_path: std::path::PathBuf) -> Result<(String, PackCore)> {
+fn import_pack_from_zip_file_path(
+    file_path: std::path::PathBuf,
+    trail_config: &TrailParseConfig,
+    mode: ImportMode,
+    progress: impl FnMut(ImportPhase, usize, usize),
+) -> Result<(String, PackCore, PackImportReport)> {
     let mut taco_zip = vec![];
     std::fs::File::open(&file_path)
         .into_diagnostic()?
@@ -332,13 +902,230 @@ fn import_pack_from_zip_file_path(file_path: std::path::PathBuf) -> Result<(Stri
         .into_diagnostic()?;
 
     info!("starting to get pack from taco");
-    crate::io::get_pack_from_taco_zip(&taco_zip).map(|pack| {
-        (
-            file_path
-                .file_name()
-                .map(|ostr| ostr.to_string_lossy().to_string())
-                .unwrap_or_default(),
-            pack,
+    crate::io::get_pack_from_taco_zip_with_progress(&taco_zip, trail_config, mode, progress).map(
+        |(pack, report)| {
+            (
+                file_path
+                    .file_name()
+                    .map(|ostr| ostr.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                pack,
+                report,
+            )
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use cap_std::fs_utf8::camino::Utf8PathBuf;
+
+    use super::*;
+    use crate::{
+        pack::Marker,
+        test_utils::{temp_jokolay_dir, zip_pack},
+    };
+
+    /// a manager inside a new directory in the temp dir. returns the path to remove once the test is done
+    fn test_manager(test_name: &str) -> (MarkerManager, Utf8PathBuf) {
+        let (jdir, path) = temp_jokolay_dir(test_name);
+        (MarkerManager::new(&jdir).unwrap(), path)
+    }
+
+    fn add_pack(manager: &mut MarkerManager, name: &str, core: PackCore) {
+        manager.marker_packs_dir.create_dir_all(name).unwrap();
+        let dir = manager.marker_packs_dir.open_dir(name).unwrap();
+        manager
+            .packs
+            .insert(name.to_string(), LoadedPack::new(core, dir.into()));
+    }
+
+    fn pack_with_markers(maps: &[(u32, usize)]) -> PackCore {
+        let mut core = PackCore::default();
+        for &(map_id, count) in maps {
+            let markers = &mut core.maps.entry(map_id).or_default().markers;
+            for _ in 0..count {
+                markers.push(Marker {
+                    guid: Uuid::new_v4(),
+                    position: Vec3::ZERO,
+                    map_id,
+                    category: "cat".to_string(),
+                    attrs: Default::default(),
+                });
+            }
+        }
+        core
+    }
+
+    #[test]
+    fn map_debounce_ignores_loading_screen_glitches() {
+        let mut debounce = MapDebounce::default();
+        const A: u32 = 15;
+        const B: u32 = 50;
+        assert_eq!(debounce.update(A, 0.0), 0);
+        assert_eq!(debounce.update(A, 0.5), A);
+        // B is reported briefly, then the transient zero and A again
+        assert_eq!(debounce.update(B, 1.0), A);
+        assert_eq!(debounce.update(0, 1.1), A);
+        assert_eq!(debounce.update(A, 1.2), A);
+        // the glitch is forgotten, so B has to be stable for the whole interval again
+        assert_eq!(debounce.update(B, 1.6), A);
+        assert_eq!(debounce.update(B, 2.0), A);
+        assert_eq!(debounce.update(0, 2.05), A);
+        assert_eq!(debounce.update(B, 2.1), B);
+        assert_eq!(debounce.stable_map_id, B);
+    }
+
+    #[test]
+    fn packs_active_on_map_counts_markers() {
+        let (mut manager, path) = test_manager("packs_active_on_map");
+        add_pack(
+            &mut manager,
+            "first",
+            pack_with_markers(&[(15, 2), (50, 1)]),
+        );
+        add_pack(
+            &mut manager,
+            "second",
+            pack_with_markers(&[(15, 3), (24, 4)]),
+        );
+        // the map exists, but without any markers
+        add_pack(&mut manager, "empty", pack_with_markers(&[(15, 0)]));
+
+        assert_eq!(
+            manager.packs_active_on_map(15),
+            [("first".to_string(), 2), ("second".to_string(), 3)]
+        );
+        assert_eq!(manager.packs_active_on_map(50), [("first".to_string(), 1)]);
+        assert_eq!(manager.packs_active_on_map(24), [("second".to_string(), 4)]);
+        assert!(manager.packs_active_on_map(1).is_empty());
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn recorded_trail_is_saved_into_the_pack() {
+        let (mut manager, path) = test_manager("recorded_trail");
+        add_pack(&mut manager, "routes", PackCore::default());
+        let etx = egui::Context::default();
+        manager.start_recording("My Route", 15);
+        for step in 0..5 {
+            let link = MumbleLink {
+                ui_tick: 1,
+                map_id: 15,
+                player_pos: Vec3::new(0.0, 0.0, step as f32 * 2.0),
+                ..Default::default()
+            };
+            manager.update(&etx, step as f64, &Some(Arc::new(link)));
+        }
+        let trail = manager.stop_recording().unwrap();
+        assert_eq!(trail.nodes.len(), 5);
+        assert!(manager
+            .save_recorded_trail("missing", trail.clone())
+            .is_none());
+        let guid = manager.save_recorded_trail("routes", trail).unwrap();
+        assert!(manager.packs["routes"].is_dirty());
+        // the pack is saved in the next update
+        manager.update(&etx, 5.0, &None);
+        assert!(!manager.packs["routes"].is_dirty());
+
+        let dir = manager.marker_packs_dir.open_dir("routes").unwrap();
+        let pack = LoadedPack::load_from_dir(dir.into(), &Default::default()).unwrap();
+        let category = pack.core.category("recorded_trails.my_route").unwrap();
+        assert_eq!(category.display_name, "My Route");
+        let trails = &pack.core.maps[&15].trails;
+        assert_eq!(trails.len(), 1);
+        assert_eq!(trails[0].guid, guid);
+        assert_eq!(trails[0].category, "recorded_trails.my_route");
+        let tbin = &pack.core.tbins[trails[0].props.get_trail_data().unwrap()];
+        assert_eq!(tbin.map_id, 15);
+        assert_eq!(tbin.nodes.len(), 5);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    /// a taco pack with two markers and a trail on map 15 and another marker on map 50
+    fn fixture_pack() -> PackCore {
+        // version and map id
+        let mut tbin = vec![];
+        tbin.extend_from_slice(&0u32.to_ne_bytes());
+        tbin.extend_from_slice(&15u32.to_ne_bytes());
+        for node in [[0.0f32, 0.0, 5.0], [0.0, 0.0, 15.0], [5.0, 0.0, 15.0]] {
+            for f in node {
+                tbin.extend_from_slice(&f.to_le_bytes());
+            }
+        }
+        let zip = zip_pack(&[
+            (
+                "pack.xml",
+                br#"<OverlayData>
+    <MarkerCategory name="cat" DisplayName="Cat" />
+    <POIs>
+        <POI type="cat" MapID="15" xpos="0" ypos="0" zpos="10" />
+        <POI type="cat" MapID="15" xpos="5" ypos="0" zpos="10" />
+        <POI type="cat" MapID="50" xpos="0" ypos="0" zpos="10" />
+        <Trail type="cat" trailData="trail.trl" />
+    </POIs>
+</OverlayData>"#,
+            ),
+            ("trail.trl", &tbin),
+        ]);
+        crate::io::get_pack_from_taco_zip_with_progress(
+            &zip,
+            &Default::default(),
+            ImportMode::Lenient,
+            |_, _, _| {},
         )
-    })
+        .unwrap()
+        .0
+    }
+
+    #[test]
+    fn render_objects_of_the_current_map() {
+        let (mut manager, path) = test_manager("current_render_objects");
+        add_pack(&mut manager, "fixture", fixture_pack());
+        let link = Arc::new(MumbleLink {
+            ui_tick: 1,
+            map_id: 15,
+            player_pos: Vec3::ZERO,
+            cam_pos: Vec3::new(0.0, 2.0, -5.0),
+            client_size: glam::IVec2::new(1920, 1080),
+            ..Default::default()
+        });
+        let etx = egui::Context::default();
+        // the map id becomes stable after the debounce interval
+        manager.update(&etx, 0.0, &Some(link.clone()));
+        manager.update(&etx, 1.0, &Some(link.clone()));
+
+        let (markers, trails) = manager.current_render_objects(&link, 1.0, 1.0, 1.0);
+        assert_eq!(markers.len(), 2);
+        // the quads are centered on the markers, which are lifted by the default height offset
+        let mut centers: Vec<Vec3> = markers
+            .iter()
+            .map(|marker| (marker.vertices[0].position + marker.vertices[2].position) / 2.0)
+            .collect();
+        centers.sort_by(|a, b| a.x.total_cmp(&b.x));
+        for (center, expected) in centers
+            .iter()
+            .zip([Vec3::new(0.0, 1.5, 10.0), Vec3::new(5.0, 1.5, 10.0)])
+        {
+            assert!(center.distance(expected) < 0.001, "{center} != {expected}");
+        }
+        assert!(markers.iter().all(|marker| marker.map_id == 15));
+
+        assert_eq!(trails.len(), 1);
+        let trail = &trails[0];
+        assert_eq!(trail.map_id, 15);
+        // two segments with two triangles each
+        assert_eq!(trail.vertices.len(), 12);
+        // the second and third vertex are the left/right side of the first node of each segment
+        let segment_starts: Vec<Vec3> = trail
+            .vertices
+            .chunks(6)
+            .map(|quad| (quad[1].position + quad[2].position) / 2.0)
+            .collect();
+        assert_eq!(
+            segment_starts,
+            [Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, 15.0)]
+        );
+        std::fs::remove_dir_all(&path).unwrap();
+    }
 }
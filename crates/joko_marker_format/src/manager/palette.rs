@@ -0,0 +1,74 @@
+/// Remaps the `color` tint of markers and trails, so that users with color vision deficiency can tell them apart.
+/// The deficiency presets use daltonization: the colors which the user can't distinguish are shifted towards the ones they can.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorPalette {
+    /// the colors of the pack
+    #[default]
+    Original,
+    /// red blindness
+    Protanopia,
+    /// green blindness
+    Deuteranopia,
+}
+/// simulations of protanopia and deuteranopia by Machado et al. (2009) with severity 1.0
+const PROTANOPIA: [[f32; 3]; 3] = [
+    [0.152286, 1.052583, -0.204868],
+    [0.114503, 0.786281, 0.099216],
+    [-0.003882, -0.048116, 1.051998],
+];
+const DEUTERANOPIA: [[f32; 3]; 3] = [
+    [0.367322, 0.860646, -0.227968],
+    [0.280085, 0.672501, 0.047413],
+    [-0.011820, 0.042940, 0.968881],
+];
+/// moves the lost red/green difference into green and blue channels, where it is still visible
+const ERROR_SHIFT: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]];
+
+impl ColorPalette {
+    pub const ALL: [ColorPalette; 3] = [
+        ColorPalette::Original,
+        ColorPalette::Protanopia,
+        ColorPalette::Deuteranopia,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorPalette::Original => "original",
+            ColorPalette::Protanopia => "protanopia",
+            ColorPalette::Deuteranopia => "deuteranopia",
+        }
+    }
+    /// remaps an srgba8 color. alpha is unchanged, and so are grays
+    pub fn apply(self, color: [u8; 4]) -> [u8; 4] {
+        let simulation = match self {
+            ColorPalette::Original => return color,
+            ColorPalette::Protanopia => &PROTANOPIA,
+            ColorPalette::Deuteranopia => &DEUTERANOPIA,
+        };
+        let rgb = [color[0], color[1], color[2]].map(|c| c as f32 / 255.0);
+        let simulated = multiply(simulation, rgb);
+        let error = [0, 1, 2].map(|i| rgb[i] - simulated[i]);
+        let shift = multiply(&ERROR_SHIFT, error);
+        let [r, g, b] =
+            [0, 1, 2].map(|i| ((rgb[i] + shift[i]).clamp(0.0, 1.0) * 255.0).round() as u8);
+        [r, g, b, color[3]]
+    }
+}
+fn multiply(matrix: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    matrix.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_remap_red() {
+        let red = [255, 0, 0, 200];
+        assert_eq!(ColorPalette::Original.apply(red), red);
+        assert_eq!(ColorPalette::Protanopia.apply(red), [255, 122, 152, 200]);
+        assert_eq!(ColorPalette::Deuteranopia.apply(red), [255, 42, 116, 200]);
+        for palette in ColorPalette::ALL {
+            assert_eq!(palette.apply([128, 128, 128, 255]), [128, 128, 128, 255]);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use jokoapi::{end_point::characters::CharacterCore, ApiClient};
+use tracing::{error, info};
+
+use super::achievements::PendingRequest;
+use crate::pack::{CommonAttributes, Profession};
+
+/// MumbleLink only has the name of the character, so we fetch the profession of each character once from the api.
+/// Markers whose `profession` attribute doesn't contain the profession of the current character are hidden.
+#[derive(Default)]
+pub(crate) struct ProfessionCache {
+    /// profession by character name
+    professions: HashMap<String, Profession>,
+    /// characters whose profession couldn't be fetched. we don't retry them until [Self::refresh_now]
+    failed: HashSet<String>,
+    /// the character name and the result of a fetch which is in progress
+    pending: Option<(String, PendingRequest<CharacterCore>)>,
+    /// profession of the current character. None if we don't know it yet
+    current: Option<Profession>,
+    /// increments whenever the current profession changes. packs use this to know when they need to filter the markers of the current map again
+    generation: u64,
+}
+impl ProfessionCache {
+    /// collects the result of the fetch in progress and starts fetching the profession of `character` if we don't know it yet.
+    /// the api key of `client` needs the `characters` permission. if empty, we don't fetch anything
+    pub fn tick(&mut self, client: &Arc<ApiClient>, character: &str) {
+        if let Some((name, pending)) = self.pending.as_ref() {
+            let result = pending.lock().unwrap().take();
+            if let Some(result) = result {
+                let name = name.clone();
+                self.pending = None;
+                match result.and_then(|core| {
+                    core.profession
+                        .to_lowercase()
+                        .parse::<Profession>()
+                        .map_err(|e| miette::miette!("{e}: {}", core.profession))
+                }) {
+                    Ok(profession) => {
+                        info!(name, ?profession, "fetched profession of character");
+                        self.professions.insert(name, profession);
+                    }
+                    Err(e) => {
+                        error!(?e, name, "failed to fetch profession of character");
+                        self.failed.insert(name);
+                    }
+                }
+            }
+        }
+        if !client.api_key().is_empty()
+            && !character.is_empty()
+            && self.pending.is_none()
+            && !self.professions.contains_key(character)
+            && !self.failed.contains(character)
+        {
+            let pending = Arc::new(Mutex::new(None));
+            self.pending = Some((character.to_string(), pending.clone()));
+            let client = client.clone();
+            let name = character.to_string();
+            rayon::spawn(move || {
+                let result = CharacterCore::get(&client, &name);
+                *pending.lock().unwrap() = Some(result);
+            });
+        }
+        let current = self.professions.get(character).copied();
+        if current != self.current {
+            self.current = current;
+            self.generation += 1;
+        }
+    }
+    /// retry the characters whose fetch failed. eg: after the api key changed
+    pub fn refresh_now(&mut self) {
+        self.failed.clear();
+    }
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+    /// whether the marker must be hidden, because it is not meant for the profession of the current character.
+    /// markers without a profession attribute are never hidden, and nothing is hidden while we don't know the profession
+    pub fn is_hidden(&self, attrs: &CommonAttributes) -> bool {
+        let (Some(professions), Some(current)) = (attrs.get_profession(), self.current) else {
+            return false;
+        };
+        !professions.contains(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::Ordering, time::Duration};
+
+    use jokoapi::mock::mock_server;
+
+    use super::*;
+
+    /// ticks until the fetch of the profession of `character` is done
+    fn tick_until_fetched(cache: &mut ProfessionCache, client: &Arc<ApiClient>, character: &str) {
+        for _ in 0..500 {
+            cache.tick(client, character);
+            if cache.professions.contains_key(character) || cache.failed.contains(character) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("fetching the profession of {character} timed out");
+    }
+
+    #[test]
+    fn marker_is_hidden_for_other_professions() {
+        let (base_url, requests) = mock_server(|path| {
+            let profession = if path.contains("Guardian") {
+                "Guardian"
+            } else {
+                "Warrior"
+            };
+            format!(
+                r#"{{"name":"","race":"Human","gender":"Male","profession":"{profession}","level":80}}"#
+            )
+        });
+        let mut guardian_only = CommonAttributes::default();
+        guardian_only.set_profession(Some(Profession::Guardian.into()));
+        let mut cache = ProfessionCache::default();
+        // without api key, nothing is fetched and we don't know the profession
+        cache.tick(&Arc::new(ApiClient::new("")), "Warrior Character");
+        assert!(!cache.is_hidden(&guardian_only));
+
+        let client = Arc::new(ApiClient::with_base_url(base_url, "key"));
+        let generation = cache.generation();
+        tick_until_fetched(&mut cache, &client, "Warrior Character");
+        assert_ne!(cache.generation(), generation);
+        assert!(cache.is_hidden(&guardian_only));
+        // markers without profession attribute are unaffected
+        assert!(!cache.is_hidden(&CommonAttributes::default()));
+
+        tick_until_fetched(&mut cache, &client, "Guardian Character");
+        assert!(!cache.is_hidden(&guardian_only));
+        // switching back doesn't fetch the known profession again
+        for _ in 0..10 {
+            cache.tick(&client, "Warrior Character");
+        }
+        assert!(cache.is_hidden(&guardian_only));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+}
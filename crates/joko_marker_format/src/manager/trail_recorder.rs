@@ -0,0 +1,85 @@
+use glam::Vec3;
+
+/// Records the positions of the player into a trail, like the trail editor of taco.
+#[derive(Debug, Clone)]
+pub(crate) struct TrailRecorder {
+    name: String,
+    /// samples from other maps are ignored, so that we don't record loading screens or a map change
+    map_id: u32,
+    /// a sample is only added if it is atleast this far (in meters) from the previous one
+    min_distance: f32,
+    nodes: Vec<Vec3>,
+}
+
+/// A trail recorded by [TrailRecorder]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedTrail {
+    pub name: String,
+    pub map_id: u32,
+    pub nodes: Vec<Vec3>,
+}
+
+impl TrailRecorder {
+    pub fn new(name: String, map_id: u32, min_distance: f32) -> Self {
+        Self {
+            name,
+            map_id,
+            min_distance,
+            nodes: vec![],
+        }
+    }
+    /// adds the position if it is on the recording map and far enough from the previous node. returns true if it was added.
+    pub fn record(&mut self, map_id: u32, pos: Vec3) -> bool {
+        if map_id != self.map_id {
+            return false;
+        }
+        if let Some(previous) = self.nodes.last() {
+            if previous.distance(pos) < self.min_distance {
+                return false;
+            }
+        }
+        self.nodes.push(pos);
+        true
+    }
+    pub fn finish(self) -> RecordedTrail {
+        RecordedTrail {
+            name: self.name,
+            map_id: self.map_id,
+            nodes: self.nodes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standing_still_doesnt_add_nodes() {
+        let mut recorder = TrailRecorder::new("route".to_string(), 15, 1.0);
+        // walk 10 meters in steps of 10cm, standing still for a while in the middle
+        for step in 0..=100 {
+            let pos = Vec3::new(0.0, 0.0, step as f32 * 0.1);
+            recorder.record(15, pos);
+            if step == 50 {
+                for _ in 0..100 {
+                    recorder.record(15, pos);
+                }
+            }
+        }
+        // samples from other maps are ignored
+        assert!(!recorder.record(50, Vec3::new(100.0, 0.0, 0.0)));
+        let trail = recorder.finish();
+        assert_eq!(trail.name, "route");
+        assert_eq!(trail.map_id, 15);
+        // roughly one node per meter, as a step may land slightly short of a meter
+        assert!(
+            (10..=11).contains(&trail.nodes.len()),
+            "{}",
+            trail.nodes.len()
+        );
+        for pair in trail.nodes.windows(2) {
+            assert!(pair[0].distance(pair[1]) >= 1.0);
+        }
+    }
+}
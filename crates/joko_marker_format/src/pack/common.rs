@@ -1,10 +1,10 @@
-use std::str::FromStr;
+use std::{collections::BTreeMap, str::FromStr};
 
 use enumflags2::{bitflags, BitFlags};
 use glam::Vec3;
 use itertools::Itertools;
-use tracing::info;
-use xot::Element;
+use tracing::{info, trace};
+use xot::{Element, Node, Xot};
 
 use crate::io::XotAttributeNameIDs;
 
@@ -71,15 +71,37 @@ macro_rules! common_attributes_struct_macro {
 /// - ele: &[xot::Element] (xot Element to which we are serializing our fields to)
 /// - names: &[XotAttributeNameIDs] (which contains the name ids of our fields)
 /// - [f1, f2, f3...]: an array of field identifiers which will be serialized.
-/// ```rust
-/// set_attribute_to_ele!(ca, ele, names, [field1, field2, field3]);
 /// ```
-///
-/// The expansion for each field is like this
-/// ```rust
+/// # use enumflags2::{bitflags, BitFlags};
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy)]
+/// # #[allow(non_camel_case_types)]
+/// # enum ActiveAttributes {
+/// #     field1 = 1,
+/// # }
+/// # struct Ca {
+/// #     active_attributes: BitFlags<ActiveAttributes>,
+/// #     field1: f32,
+/// # }
+/// # struct Names {
+/// #     field1: xot::NameId,
+/// # }
+/// # let mut xot = xot::Xot::new();
+/// # let names = Names {
+/// #     field1: xot.add_name("field1"),
+/// # };
+/// # let node = xot.new_element(names.field1);
+/// # let ele = xot.element_mut(node).unwrap();
+/// # let ca = Ca {
+/// #     active_attributes: ActiveAttributes::field1.into(),
+/// #     field1: 1.5,
+/// # };
+/// // set_attribute_to_ele!(ca, ele, names, [field1]) expands to
 /// if ca.active_attributes.contains(ActiveAttributes::field1) {
 ///     ele.set_attribute(names.field1, ca.field1.to_string());
 /// }
+/// # assert_eq!(ele.get_attribute(names.field1), Some("1.5"));
 /// ```
 macro_rules! set_attribute_to_ele {
     ($ca: ident, $ele: ident,$names: ident, [$($field: ident),+]) => {
@@ -95,22 +117,51 @@ macro_rules! set_attribute_to_ele {
 /// - ele: &[xot::Element] (xot Element to which we are serializing our fields to)
 /// - names: &[XotAttributeNameIDs] (which contains the name ids of our fields)
 /// - [f1, f2, f3...]: an array of field identifiers which will be serialized.
-/// ```rust
-/// set_attribute_bool_to_ele!(ca, ele, names, [field1, field2, field3]);
 /// ```
-///
-/// The expansion for each field is like this
-/// ```rust
+/// # use enumflags2::{bitflags, BitFlags};
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy)]
+/// # #[allow(non_camel_case_types)]
+/// # enum ActiveAttributes {
+/// #     field1 = 1,
+/// # }
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy)]
+/// # #[allow(non_camel_case_types)]
+/// # enum BoolAttributes {
+/// #     field1 = 1,
+/// # }
+/// # struct Ca {
+/// #     active_attributes: BitFlags<ActiveAttributes>,
+/// #     bool_attributes: BitFlags<BoolAttributes>,
+/// # }
+/// # struct Names {
+/// #     field1: xot::NameId,
+/// # }
+/// # let mut xot = xot::Xot::new();
+/// # let names = Names {
+/// #     field1: xot.add_name("field1"),
+/// # };
+/// # let node = xot.new_element(names.field1);
+/// # let ele = xot.element_mut(node).unwrap();
+/// # let ca = Ca {
+/// #     active_attributes: ActiveAttributes::field1.into(),
+/// #     bool_attributes: BoolAttributes::field1.into(),
+/// # };
+/// // set_attribute_bool_to_ele!(ca, ele, names, [field1]) expands to
 /// if ca.active_attributes.contains(ActiveAttributes::field1) {
-///     ele.set_attribute(names.field1,
-///         ca
-///             .bool_attributes
+///     ele.set_attribute(
+///         names.field1,
+///         ca.bool_attributes
 ///             .contains(BoolAttributes::field1)
 ///             .then_some(1)
 ///             .unwrap_or(0u8)
-///             .to_string()
+///             .to_string(),
 ///     );
 /// }
+/// # assert_eq!(ele.get_attribute(names.field1), Some("1"));
 /// ```
 macro_rules! set_attribute_bool_to_ele {
     ($ca: ident, $ele: ident,$names: ident, [$($field: ident),+]) => {
@@ -133,18 +184,63 @@ macro_rules! set_attribute_bool_to_ele {
 /// - ele: &[xot::Element] (xot Element to which we are serializing our fields to)
 /// - names: &[XotAttributeNameIDs] (which contains the name ids of our fields)
 /// - [f1, f2, f3...]: an array of field identifiers which will be serialized.
-/// ```rust
-/// set_attribute_bitflags_as_array_to_ele!(ca, ele, names, [field1, field2, field3]);
 /// ```
-///
-/// The expansion for each field is like this
-/// ```rust
+/// # use itertools::Itertools;
+/// # use enumflags2::{bitflags, BitFlags};
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy)]
+/// # #[allow(non_camel_case_types)]
+/// # enum ActiveAttributes {
+/// #     field1 = 1,
+/// # }
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy, Debug, PartialEq)]
+/// # enum Mount {
+/// #     Raptor = 1,
+/// #     Griffon = 2,
+/// # }
+/// # impl std::fmt::Display for Mount {
+/// #     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// #         write!(f, "{self:?}")
+/// #     }
+/// # }
+/// # impl std::str::FromStr for Mount {
+/// #     type Err = &'static str;
+/// #     fn from_str(s: &str) -> Result<Self, Self::Err> {
+/// #         match s {
+/// #             "Raptor" => Ok(Self::Raptor),
+/// #             "Griffon" => Ok(Self::Griffon),
+/// #             _ => Err("unknown mount"),
+/// #         }
+/// #     }
+/// # }
+/// # struct Ca {
+/// #     active_attributes: BitFlags<ActiveAttributes>,
+/// #     field1: BitFlags<Mount>,
+/// # }
+/// # struct Names {
+/// #     field1: xot::NameId,
+/// # }
+/// # let mut xot = xot::Xot::new();
+/// # let names = Names {
+/// #     field1: xot.add_name("field1"),
+/// # };
+/// # let node = xot.new_element(names.field1);
+/// # let ele = xot.element_mut(node).unwrap();
+/// # let ca = Ca {
+/// #     active_attributes: ActiveAttributes::field1.into(),
+/// #     field1: Mount::Raptor | Mount::Griffon,
+/// # };
+/// // set_attribute_bitflags_as_array_to_ele!(ca, ele, names, [field1]) expands to
 /// if ca.active_attributes.contains(ActiveAttributes::field1) {
 ///     ele.set_attribute(
 ///         names.field1,
-///         ca.field1.iter().map(|s| s.as_ref()).join(","),
+///         ca.field1.iter().map(|s| s.to_string()).join(","),
 ///     );
 /// }
+/// # assert_eq!(ele.get_attribute(names.field1), Some("Raptor,Griffon"));
 /// ```
 macro_rules! set_attribute_bitflags_as_array_to_ele {
     ($ca: ident, $ele: ident,$names: ident, [$($field: ident),+]) => {
@@ -163,18 +259,38 @@ macro_rules! set_attribute_bitflags_as_array_to_ele {
 /// - ele: &[xot::Element] (xot Element to which we are serializing our fields to)
 /// - names: &[XotAttributeNameIDs] (which contains the name ids of our fields)
 /// - [f1, f2, f3...]: an array of field identifiers which will be serialized.
-/// ```rust
-/// update_attribute_from_ele!(ca, ele, names, [field1, field2, field3]);
 /// ```
-///
-/// The expansion for each field is like this
-/// ```rust
+/// # use enumflags2::{bitflags, BitFlags};
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy)]
+/// # #[allow(non_camel_case_types)]
+/// # enum ActiveAttributes {
+/// #     field1 = 1,
+/// # }
+/// # struct Ca {
+/// #     active_attributes: BitFlags<ActiveAttributes>,
+/// #     field1: f32,
+/// # }
+/// # struct Names {
+/// #     field1: xot::NameId,
+/// # }
+/// # let mut xot = xot::Xot::new();
+/// # let names = Names {
+/// #     field1: xot.add_name("field1"),
+/// # };
+/// # let node = xot.new_element(names.field1);
+/// # xot.element_mut(node).unwrap().set_attribute(names.field1, " 1.5 ");
+/// # let ele = xot.element(node).unwrap();
+/// # let mut ca = Ca {
+/// #     active_attributes: BitFlags::empty(),
+/// #     field1: 0.0,
+/// # };
+/// // update_attribute_from_ele!(ca, ele, names, [field1]) expands to
 /// if let Some(value) = ele.get_attribute(names.field1) {
 ///     match value.trim().parse() {
 ///         Ok(value) => {
-///             ca
-///                 .active_attributes
-///                 .insert(ActiveAttributes::fiel1);
+///             ca.active_attributes.insert(ActiveAttributes::field1);
 ///             ca.field1 = value;
 ///         }
 ///         Err(e) => {
@@ -182,6 +298,8 @@ macro_rules! set_attribute_bitflags_as_array_to_ele {
 ///         }
 ///     }
 /// }
+/// # assert!(ca.active_attributes.contains(ActiveAttributes::field1));
+/// # assert_eq!(ca.field1, 1.5);
 /// ```
 macro_rules! update_attribute_from_ele {
     ($ca: ident, $ele: ident,$names: ident, [$($field: ident),+]) => {
@@ -209,35 +327,59 @@ macro_rules! update_attribute_from_ele {
 /// - ele: &[xot::Element] (xot Element to which we are serializing our fields to)
 /// - names: &[XotAttributeNameIDs] (which contains the name ids of our fields)
 /// - [f1, f2, f3...]: an array of field identifiers which will be serialized.
-/// ```rust
-/// update_attribute_bool_from_ele!(ca, ele, names, [field1, field2, field3]);
 /// ```
-///
-/// The expansion for each field is like this
-/// ```rust
+/// # use enumflags2::{bitflags, BitFlags};
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy)]
+/// # #[allow(non_camel_case_types)]
+/// # enum ActiveAttributes {
+/// #     field1 = 1,
+/// # }
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy)]
+/// # #[allow(non_camel_case_types)]
+/// # enum BoolAttributes {
+/// #     field1 = 1,
+/// # }
+/// # struct Ca {
+/// #     active_attributes: BitFlags<ActiveAttributes>,
+/// #     bool_attributes: BitFlags<BoolAttributes>,
+/// # }
+/// # struct Names {
+/// #     field1: xot::NameId,
+/// # }
+/// # let mut xot = xot::Xot::new();
+/// # let names = Names {
+/// #     field1: xot.add_name("field1"),
+/// # };
+/// # let node = xot.new_element(names.field1);
+/// # xot.element_mut(node).unwrap().set_attribute(names.field1, "1");
+/// # let ele = xot.element(node).unwrap();
+/// # let mut ca = Ca {
+/// #     active_attributes: BitFlags::empty(),
+/// #     bool_attributes: BitFlags::empty(),
+/// # };
+/// // update_attribute_bool_from_ele!(ca, ele, names, [field1]) expands to
 /// if let Some(value) = ele.get_attribute(names.field1) {
 ///     match value.trim().parse::<i8>() {
-///         Ok(value) => {
-///             match value {
-///                 0 | 1 => {
-///                     ca
-///                     .active_attributes
-///                     .insert(ActiveAttributes::field1);
-///                     ca.bool_attributes.set(
-///                         BoolAttributes::field1,
-///                         if value == 0 { false } else { true },
-///                     );
-///                 }
-///                 _ => {
-///                     info!(value, "failed to parse {}", "field1");
-///                 }
+///         Ok(value) => match value {
+///             0 | 1 => {
+///                 ca.active_attributes.insert(ActiveAttributes::field1);
+///                 ca.bool_attributes
+///                     .set(BoolAttributes::field1, if value == 0 { false } else { true });
 ///             }
-///         }
+///             _ => {
+///                 tracing::info!(value, "failed to parse {}", "field1");
+///             }
+///         },
 ///         Err(e) => {
 ///             tracing::info!(?e, value, "failed to parse {}", "field1");
 ///         }
 ///     }
 /// }
+/// # assert!(ca.bool_attributes.contains(BoolAttributes::field1));
 /// ```
 macro_rules! update_attribute_bool_from_ele {
     ($common_attributes: ident, $ele: ident,$names: ident, [$($field: ident),+]) => {
@@ -274,27 +416,70 @@ macro_rules! update_attribute_bool_from_ele {
 /// - ele: &[xot::Element] (xot Element to which we are serializing our fields to)
 /// - names: &[XotAttributeNameIDs] (which contains the name ids of our fields)
 /// - [f1,t1; f2,t2;...]: an array of field identifiers which will be serialized and their enum type.
-/// ```rust
-/// update_attribute_bitflags_array_from_ele!(ca, ele, names, [f1, t1; f2, t2]);
 /// ```
-///
-/// The expansion for each field is like this
-/// ```rust
-/// if let Some(field1_str) = ele.get_attribute(names.field1) {
-///     for value in field1_str.split(',') {
-///         match value.trim().parse::<t1>() {
+/// # use enumflags2::{bitflags, BitFlags};
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy)]
+/// # #[allow(non_camel_case_types)]
+/// # enum ActiveAttributes {
+/// #     field1 = 1,
+/// # }
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy, Debug, PartialEq)]
+/// # enum Mount {
+/// #     Raptor = 1,
+/// #     Griffon = 2,
+/// # }
+/// # impl std::fmt::Display for Mount {
+/// #     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// #         write!(f, "{self:?}")
+/// #     }
+/// # }
+/// # impl std::str::FromStr for Mount {
+/// #     type Err = &'static str;
+/// #     fn from_str(s: &str) -> Result<Self, Self::Err> {
+/// #         match s {
+/// #             "Raptor" => Ok(Self::Raptor),
+/// #             "Griffon" => Ok(Self::Griffon),
+/// #             _ => Err("unknown mount"),
+/// #         }
+/// #     }
+/// # }
+/// # struct Ca {
+/// #     active_attributes: BitFlags<ActiveAttributes>,
+/// #     field1: BitFlags<Mount>,
+/// # }
+/// # struct Names {
+/// #     field1: xot::NameId,
+/// # }
+/// # let mut xot = xot::Xot::new();
+/// # let names = Names {
+/// #     field1: xot.add_name("field1"),
+/// # };
+/// # let node = xot.new_element(names.field1);
+/// # xot.element_mut(node).unwrap().set_attribute(names.field1, "Raptor, Griffon,unknown");
+/// # let ele = xot.element(node).unwrap();
+/// # let mut ca = Ca {
+/// #     active_attributes: BitFlags::empty(),
+/// #     field1: BitFlags::empty(),
+/// # };
+/// // update_attribute_bitflags_array_from_ele!(ca, ele, names, [field1, Mount]) expands to
+/// if let Some(value) = ele.get_attribute(names.field1) {
+///     for item in value.trim().split(',') {
+///         match item.trim().parse::<Mount>() {
 ///             Ok(flag) => {
-///                 ca
-///                 .active_attribus
-///                 .insert(ActiveAttributes::field1);
-///                 ca.field1.set(flag);
+///                 ca.active_attributes.insert(ActiveAttributes::field1);
+///                 ca.field1.insert(flag);
 ///             }
 ///             Err(e) => {
-///                 tracing::info!(value, e);
+///                 tracing::info!(item, e);
 ///             }
 ///         }
 ///     }
 /// }
+/// # assert_eq!(ca.field1, Mount::Raptor | Mount::Griffon);
 /// ```
 macro_rules! update_attribute_bitflags_array_from_ele {
     ($ca: ident, $ele: ident,$xot_names: ident, [$($field: ident, $ty: ty);+]) => {
@@ -314,8 +499,39 @@ macro_rules! update_attribute_bitflags_array_from_ele {
     };
 }
 /// generates getters for bool attributes
-/// ```rust
-/// getters_for_bool_attributes!([field1, field2, field3]);
+/// ```
+/// # use enumflags2::{bitflags, BitFlags};
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy)]
+/// # #[allow(non_camel_case_types)]
+/// # enum ActiveAttributes {
+/// #     field1 = 1,
+/// # }
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy)]
+/// # #[allow(non_camel_case_types)]
+/// # enum BoolAttributes {
+/// #     field1 = 1,
+/// # }
+/// # struct Ca {
+/// #     active_attributes: BitFlags<ActiveAttributes>,
+/// #     bool_attributes: BitFlags<BoolAttributes>,
+/// # }
+/// // getters_for_bool_attributes!([field1]) inside `impl Ca` expands to
+/// impl Ca {
+///     pub(crate) fn get_field1(&self) -> Option<bool> {
+///         self.active_attributes
+///             .contains(ActiveAttributes::field1)
+///             .then_some(self.bool_attributes.contains(BoolAttributes::field1))
+///     }
+/// }
+/// # let ca = Ca {
+/// #     active_attributes: ActiveAttributes::field1.into(),
+/// #     bool_attributes: BitFlags::empty(),
+/// # };
+/// # assert_eq!(ca.get_field1(), Some(false));
 /// ```
 ///
 /// This generates a `fn get_field1(&self) -> Option<bool>`
@@ -327,7 +543,7 @@ macro_rules! getters_for_bool_attributes {
             /// If the attribute is not set, then we return None.
             /// Otherwise, we return the boolean value of the attribute.
             #[allow(unused)]
-            fn [<get_ $field>](&self) -> Option<bool> {
+            pub(crate) fn [<get_ $field>](&self) -> Option<bool> {
                 self.active_attributes.contains(ActiveAttributes::$field).then_some(
                     self.bool_attributes.contains(BoolAttributes::$field)
                 )
@@ -337,8 +553,45 @@ macro_rules! getters_for_bool_attributes {
     };
 }
 /// generates setters for bool attributes
-/// ```rust
-/// setters_for_bool_attributes!([field1, field2, field3]);
+/// ```
+/// # use enumflags2::{bitflags, BitFlags};
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy)]
+/// # #[allow(non_camel_case_types)]
+/// # enum ActiveAttributes {
+/// #     field1 = 1,
+/// # }
+/// # #[bitflags]
+/// # #[repr(u8)]
+/// # #[derive(Clone, Copy)]
+/// # #[allow(non_camel_case_types)]
+/// # enum BoolAttributes {
+/// #     field1 = 1,
+/// # }
+/// # struct Ca {
+/// #     active_attributes: BitFlags<ActiveAttributes>,
+/// #     bool_attributes: BitFlags<BoolAttributes>,
+/// # }
+/// // setters_for_bool_attributes!([field1]) inside `impl Ca` expands to
+/// impl Ca {
+///     pub(crate) fn set_field1(&mut self, value: Option<bool>) {
+///         if let Some(value) = value {
+///             self.active_attributes.insert(ActiveAttributes::field1);
+///             self.bool_attributes.set(BoolAttributes::field1, value);
+///         } else {
+///             self.active_attributes.remove(ActiveAttributes::field1);
+///         }
+///     }
+/// }
+/// # let mut ca = Ca {
+/// #     active_attributes: BitFlags::empty(),
+/// #     bool_attributes: BitFlags::empty(),
+/// # };
+/// # ca.set_field1(Some(true));
+/// # assert!(ca.bool_attributes.contains(BoolAttributes::field1));
+/// # ca.set_field1(None);
+/// # assert!(ca.active_attributes.is_empty());
 /// ```
 ///
 /// This generates a `fn set_field1(&mut self, value: Option<bool>)`
@@ -350,7 +603,7 @@ macro_rules! setters_for_bool_attributes {
             /// If the attribute is not set, then we return None.
             /// Otherwise, we return the boolean value of the attribute.
             #[allow(unused)]
-            fn [<set_ $field>](&mut self, value: Option<bool>) {
+            pub(crate) fn [<set_ $field>](&mut self, value: Option<bool>) {
                 if let Some(value) = value {
                     self.active_attributes.insert(ActiveAttributes::$field);
                     self.bool_attributes.set(BoolAttributes::$field, value);
@@ -384,6 +637,9 @@ common_attributes_struct_macro!(
         copy: SmolStr,
         copy_message: SmolStr,
         cull: Cull,
+        /// the attributes which jokolay doesn't know, like the ones used by other tools. the key is the attribute name.
+        /// They are kept as they are, so that exporting the pack doesn't lose them
+        extra: BTreeMap<String, String>,
         /// Determines how far the marker will completely disappear. If below 0, the marker won't disappear at any distance. Default is -1. FadeFar needs to be higher than fadeNear for sane results. This value is in game units (inches).
         // #[serde(rename = "fadeFar")]
         fade_far: f32,
@@ -396,6 +652,10 @@ common_attributes_struct_macro!(
         hide: SmolStr,
         /// The icon to be displayed for the marker. If not given, this defaults to the image shown at the start of this article. This should point to a .png file. The overlay looks for the image files both starting from the root directory and the POIs directory for convenience. Make sure you don't use too high resolution (above 128x128) images because the texture atlas used for these is limited in size and it's a needless waste of resources to fill it quickly.Default value: 20
         icon_file: RelativePath,
+        /// The frames of an animated icon. a comma separated list of .png files, which replaces the iconFile. see `icon_frame_rate`
+        icon_frames: Vec<RelativePath>,
+        /// how many frames of `icon_frames` are shown per second. zero or less shows only the first frame
+        icon_frame_rate: f32,
         /// The size of the icon in the game world. Default is 1.0 if this is not defined. Note that the "screen edges herd icons" option will limit the size of the displayed images for technical reasons.
         icon_size: f32,
         /// his can be a multiline string, it will show up on screen as a text when the player is inside of infoRange of the marker
@@ -455,6 +715,7 @@ impl CommonAttributes {
         keep_on_map_edge,
         map_visibility,
         mini_map_visibility,
+        occlude,
         scale_on_map_with_zoom
     ]);
     setters_for_bool_attributes!([
@@ -467,13 +728,28 @@ impl CommonAttributes {
         keep_on_map_edge,
         map_visibility,
         mini_map_visibility,
+        occlude,
         scale_on_map_with_zoom
     ]);
+    /// `tree` is only used to get the names of the unknown attributes
     pub(crate) fn update_common_attributes_from_element(
         &mut self,
+        tree: &Xot,
         ele: &Element,
         names: &XotAttributeNameIDs,
     ) {
+        for (name, value) in ele.attributes().iter() {
+            if names.known.contains(name) {
+                continue;
+            }
+            let (name, namespace) = tree.name_ns_str(*name);
+            if !namespace.is_empty() {
+                info!(name, namespace, "ignoring attribute with a namespace");
+                continue;
+            }
+            self.active_attributes.insert(ActiveAttributes::extra);
+            self.extra.insert(name.to_string(), value.clone());
+        }
         if let Some(input_str) = ele.get_attribute(names.color) {
             use data_encoding::HEXLOWER_PERMISSIVE;
             let mut output = [0u8; 4];
@@ -547,14 +823,33 @@ impl CommonAttributes {
                 }
             }
         }
+        if let Some(frames) = ele.get_attribute(names.icon_frames) {
+            for frame in frames.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+                match frame.parse() {
+                    Ok(path) => {
+                        self.active_attributes.insert(ActiveAttributes::icon_frames);
+                        self.icon_frames.push(path);
+                    }
+                    Err(e) => {
+                        info!(?e, frames, frame, "failed to parse icon frame");
+                    }
+                }
+            }
+        }
         // bitflags with multiple elements
         update_attribute_bitflags_array_from_ele!(self, ele, names, [
             festival, Festival;
             map_type, MapType;
             mount, Mount;
-            profession, Profession;
             race, Race
         ]);
+        if let Some(value) = ele.get_attribute(names.profession) {
+            let professions = Profession::parse_list(value);
+            if !professions.is_empty() {
+                self.active_attributes.insert(ActiveAttributes::profession);
+                self.profession.insert(professions);
+            }
+        }
 
         // bools
         update_attribute_bool_from_ele!(
@@ -571,6 +866,7 @@ impl CommonAttributes {
                 keep_on_map_edge,
                 map_visibility,
                 mini_map_visibility,
+                occlude,
                 scale_on_map_with_zoom
             ]
         );
@@ -603,6 +899,7 @@ impl CommonAttributes {
                 fade_near,
                 fade_far,
                 height_offset,
+                icon_frame_rate,
                 icon_size,
                 info_range,
                 map_display_size,
@@ -622,6 +919,18 @@ impl CommonAttributes {
         );
     }
 
+    /// the unknown attributes need new names in the tree, so they can't be serialized by [Self::serialize_to_element] which only borrows the element.
+    pub(crate) fn serialize_extra_attributes(&self, tree: &mut Xot, node: Node) {
+        let Some(extra) = self.get_extra() else {
+            return;
+        };
+        for (name, value) in extra {
+            let name = tree.add_name(name);
+            if let Some(ele) = tree.element_mut(node) {
+                ele.set_attribute(name, value);
+            }
+        }
+    }
     pub(crate) fn serialize_to_element(&self, ele: &mut Element, names: &XotAttributeNameIDs) {
         // color arrays
         if self.active_attributes.contains(ActiveAttributes::color) {
@@ -657,13 +966,25 @@ impl CommonAttributes {
                     .join(","),
             );
         }
+        if self
+            .active_attributes
+            .contains(ActiveAttributes::icon_frames)
+        {
+            ele.set_attribute(names.icon_frames, self.icon_frames.iter().join(","));
+        }
         // bitflags arrays
         set_attribute_bitflags_as_array_to_ele!(
             self,
             ele,
             names,
-            [festival, map_type, mount, profession, race]
+            [festival, map_type, mount, race]
         );
+        if self
+            .active_attributes
+            .contains(ActiveAttributes::profession)
+        {
+            ele.set_attribute(names.profession, Profession::format_list(self.profession));
+        }
         // bools
         set_attribute_bool_to_ele!(
             self,
@@ -679,6 +1000,7 @@ impl CommonAttributes {
                 keep_on_map_edge,
                 map_visibility,
                 mini_map_visibility,
+                occlude,
                 scale_on_map_with_zoom
             ]
         );
@@ -712,6 +1034,7 @@ impl CommonAttributes {
                 fade_near,
                 fade_far,
                 height_offset,
+                icon_frame_rate,
                 icon_size,
                 info_range,
                 map_display_size,
@@ -766,6 +1089,8 @@ pub enum BoolAttributes {
     mini_map_visibility = 1 << 8,
     /// scaling of marker on 2d map (or minimap)
     scale_on_map_with_zoom = 1 << 9,
+    /// whether the marker is hidden behind other (occluding) markers/trails. If not set, the marker is drawn on top of everything.
+    occlude = 1 << 10,
 }
 #[allow(non_camel_case_types)]
 #[bitflags]
@@ -830,6 +1155,10 @@ pub enum ActiveAttributes {
     trail_data = 1 << 55,
     trail_scale = 1 << 56,
     trigger_range = 1 << 57,
+    occlude = 1 << 58,
+    icon_frames = 1 << 59,
+    icon_frame_rate = 1 << 60,
+    extra = 1 << 61,
 }
 #[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum Behavior {
@@ -878,7 +1207,7 @@ impl FromStr for Behavior {
 /// Filter which professions the marker should be active for. if its null, its available for all professions
 #[bitflags]
 #[repr(u16)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Profession {
     Elementalist = 1 << 0,
     Engineer = 1 << 1,
@@ -923,9 +1252,31 @@ impl AsRef<str> for Profession {
         }
     }
 }
-impl ToString for Profession {
-    fn to_string(&self) -> String {
-        self.as_ref().to_string()
+impl Profession {
+    /// parses a comma separated list of professions like `guardian, Warrior`. unknown professions are skipped
+    pub fn parse_list(s: &str) -> BitFlags<Profession> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|profession| !profession.is_empty())
+            .filter_map(
+                |profession| match profession.to_lowercase().parse::<Profession>() {
+                    Ok(profession) => Some(profession),
+                    Err(e) => {
+                        trace!(profession, e, "skipping unknown profession");
+                        None
+                    }
+                },
+            )
+            .collect()
+    }
+    /// the inverse of [Self::parse_list]
+    pub fn format_list(professions: BitFlags<Profession>) -> String {
+        professions.iter().map(|p| p.as_ref().to_string()).join(",")
+    }
+}
+impl std::fmt::Display for Profession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
     }
 }
 #[derive(Debug, Clone, Copy, Default)]
@@ -958,9 +1309,9 @@ impl AsRef<str> for Cull {
         }
     }
 }
-impl ToString for Cull {
-    fn to_string(&self) -> String {
-        self.as_ref().to_string()
+impl std::fmt::Display for Cull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
     }
 }
 /// Filter for which festivals will the marker be active for
@@ -1003,9 +1354,9 @@ impl AsRef<str> for Festival {
         }
     }
 }
-impl ToString for Festival {
-    fn to_string(&self) -> String {
-        self.as_ref().to_string()
+impl std::fmt::Display for Festival {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
     }
 }
 /// Filter for which specializations (the third traitline) will the marker be active for
@@ -1246,15 +1597,15 @@ impl AsRef<str> for Specialization {
     }
 }
 
-impl ToString for Specialization {
-    fn to_string(&self) -> String {
-        self.as_ref().to_string()
+impl std::fmt::Display for Specialization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
     }
 }
 /// Most of this data is stolen from BlishHUD.
 #[bitflags]
 #[repr(u32)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MapType {
     Unknown = 1 << 0,
     /// Redirect map type, e.g. when logging in while in a PvP match.
@@ -1295,20 +1646,87 @@ pub enum MapType {
     /// WvW lounge map type, e.g. Armistice Bastion.    
     WvwLounge = 1 << 18,
 }
+impl MapType {
+    /// converts the `map_type` of MumbleLink. unrecognized values become [MapType::Unknown]
+    pub fn from_u32(map_type: u32) -> Self {
+        match map_type {
+            0 => MapType::Redirect,
+            1 => MapType::CharacterCreate,
+            2 => MapType::PvP,
+            3 => MapType::GvG,
+            4 => MapType::Instance,
+            5 => MapType::Public,
+            6 => MapType::Tournament,
+            7 => MapType::Tutorial,
+            8 => MapType::UserTournament,
+            9 => MapType::EternalBattlegrounds,
+            10 => MapType::BlueBorderlands,
+            11 => MapType::GreenBorderlands,
+            12 => MapType::RedBorderlands,
+            13 => MapType::FortunesVale,
+            14 => MapType::ObsidianSanctum,
+            15 => MapType::EdgeOfTheMists,
+            16 => MapType::PublicMini,
+            18 => MapType::WvwLounge,
+            _ => MapType::Unknown,
+        }
+    }
+}
 impl FromStr for MapType {
     type Err = &'static str;
-    fn from_str(_s: &str) -> Result<Self, Self::Err> {
-        unimplemented!("needs research to verify the map type values")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "unknown" => MapType::Unknown,
+            "redirect" => MapType::Redirect,
+            "charactercreate" => MapType::CharacterCreate,
+            "pvp" => MapType::PvP,
+            "gvg" => MapType::GvG,
+            "instance" => MapType::Instance,
+            "public" => MapType::Public,
+            "tournament" => MapType::Tournament,
+            "tutorial" => MapType::Tutorial,
+            "usertournament" => MapType::UserTournament,
+            "eternalbattlegrounds" => MapType::EternalBattlegrounds,
+            "blueborderlands" => MapType::BlueBorderlands,
+            "greenborderlands" => MapType::GreenBorderlands,
+            "redborderlands" => MapType::RedBorderlands,
+            "fortunesvale" => MapType::FortunesVale,
+            "obsidiansanctum" => MapType::ObsidianSanctum,
+            "edgeofthemists" => MapType::EdgeOfTheMists,
+            "publicmini" => MapType::PublicMini,
+            "wvwlounge" => MapType::WvwLounge,
+            _ => return Err("unrecognized map type"),
+        })
     }
 }
 impl AsRef<str> for MapType {
-    fn as_ref(&self) -> &str {
-        unimplemented!("needs research to verify the maptype values")
+    fn as_ref(&self) -> &'static str {
+        match self {
+            MapType::Unknown => "unknown",
+            MapType::Redirect => "redirect",
+            MapType::CharacterCreate => "charactercreate",
+            MapType::PvP => "pvp",
+            MapType::GvG => "gvg",
+            MapType::Instance => "instance",
+            MapType::Public => "public",
+            MapType::Tournament => "tournament",
+            MapType::Tutorial => "tutorial",
+            MapType::UserTournament => "usertournament",
+            MapType::EternalBattlegrounds => "eternalbattlegrounds",
+            MapType::BlueBorderlands => "blueborderlands",
+            MapType::GreenBorderlands => "greenborderlands",
+            MapType::RedBorderlands => "redborderlands",
+            MapType::FortunesVale => "fortunesvale",
+            MapType::ObsidianSanctum => "obsidiansanctum",
+            MapType::EdgeOfTheMists => "edgeofthemists",
+            MapType::PublicMini => "publicmini",
+            MapType::WvwLounge => "wvwlounge",
+        }
     }
 }
-impl ToString for MapType {
-    fn to_string(&self) -> String {
-        self.as_ref().to_string()
+impl std::fmt::Display for MapType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
     }
 }
 /// made it using multi cursor (ctrl + shift + L) by copy-pasting json from api
@@ -2259,3 +2677,44 @@ pub static MAP_ID_TO_NAME: phf::OrderedMap<u16, &'static str> = phf::phf_ordered
     1499u16 => "Forward",
     1500u16 => "Fractals of the Mists",
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profession_lists_round_trip() {
+        let professions = Profession::parse_list(" guardian,Warrior , ,necromancer,druid");
+        assert_eq!(
+            professions,
+            Profession::Guardian | Profession::Warrior | Profession::Necromancer
+        );
+        let formatted = Profession::format_list(professions);
+        assert_eq!(formatted, "guardian,necromancer,warrior");
+        assert_eq!(Profession::parse_list(&formatted), professions);
+
+        assert!(Profession::parse_list("").is_empty());
+        assert!(Profession::parse_list("druid, scourge").is_empty());
+        assert_eq!(Profession::format_list(BitFlags::empty()), "");
+    }
+
+    #[test]
+    fn map_types_from_mumble_link() {
+        assert_eq!(MapType::from_u32(0), MapType::Redirect);
+        assert_eq!(MapType::from_u32(2), MapType::PvP);
+        assert_eq!(MapType::from_u32(4), MapType::Instance);
+        assert_eq!(MapType::from_u32(5), MapType::Public);
+        assert_eq!(MapType::from_u32(9), MapType::EternalBattlegrounds);
+        assert_eq!(MapType::from_u32(15), MapType::EdgeOfTheMists);
+        assert_eq!(MapType::from_u32(16), MapType::PublicMini);
+        assert_eq!(MapType::from_u32(18), MapType::WvwLounge);
+        assert_eq!(MapType::from_u32(17), MapType::Unknown);
+        assert_eq!(MapType::from_u32(1000), MapType::Unknown);
+
+        for map_type in BitFlags::<MapType>::all() {
+            assert_eq!(map_type.as_ref().parse::<MapType>(), Ok(map_type));
+        }
+        assert_eq!("PvP".parse::<MapType>(), Ok(MapType::PvP));
+        assert!("sPvP".parse::<MapType>().is_err());
+    }
+}
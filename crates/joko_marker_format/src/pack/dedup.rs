@@ -0,0 +1,141 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use indexmap::IndexMap;
+
+use super::{Category, CommonAttributes, PackCore, RelativePath};
+
+impl PackCore {
+    /// Removes the textures whose bytes are the same as another texture and makes the markers, trails and categories
+    /// refer to the texture which is kept instead. The first path (in the order of [Self::textures]) is kept.
+    /// returns the removed paths with the path that replaced them.
+    pub fn dedup_textures(&mut self) -> BTreeMap<RelativePath, RelativePath> {
+        let mut aliases = BTreeMap::new();
+        // hash of the bytes -> the kept paths with that hash
+        let mut kept: HashMap<u64, Vec<RelativePath>> = HashMap::new();
+        for (path, bytes) in self.textures.iter() {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            bytes.hash(&mut hasher);
+            let same_hash = kept.entry(hasher.finish()).or_default();
+            // the hash may collide, so we compare the bytes too
+            match same_hash
+                .iter()
+                .find(|kept_path| self.textures[*kept_path] == *bytes)
+            {
+                Some(kept_path) => {
+                    aliases.insert(path.clone(), kept_path.clone());
+                }
+                None => same_hash.push(path.clone()),
+            }
+        }
+        if aliases.is_empty() {
+            return aliases;
+        }
+        for path in aliases.keys() {
+            self.textures.remove(path);
+        }
+        recursive_replace_aliases(&mut self.categories, &aliases);
+        for map in self.maps.values_mut() {
+            for marker in map.markers.iter_mut() {
+                replace_aliases(&mut marker.attrs, &aliases);
+            }
+            for trail in map.trails.iter_mut() {
+                replace_aliases(&mut trail.props, &aliases);
+            }
+        }
+        aliases
+    }
+}
+
+fn recursive_replace_aliases(
+    cats: &mut IndexMap<String, Category>,
+    aliases: &BTreeMap<RelativePath, RelativePath>,
+) {
+    for cat in cats.values_mut() {
+        replace_aliases(&mut cat.props, aliases);
+        recursive_replace_aliases(&mut cat.children, aliases);
+    }
+}
+
+fn replace_aliases(attrs: &mut CommonAttributes, aliases: &BTreeMap<RelativePath, RelativePath>) {
+    if let Some(kept) = attrs.get_icon_file().and_then(|path| aliases.get(path)) {
+        attrs.set_icon_file(Some(kept.clone()));
+    }
+    if let Some(kept) = attrs.get_texture().and_then(|path| aliases.get(path)) {
+        attrs.set_texture(Some(kept.clone()));
+    }
+    if let Some(frames) = attrs.get_icon_frames() {
+        if frames.iter().any(|frame| aliases.contains_key(frame)) {
+            let frames = frames
+                .iter()
+                .map(|frame| aliases.get(frame).unwrap_or(frame).clone())
+                .collect();
+            attrs.set_icon_frames(Some(frames));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::pack::Marker;
+
+    fn path(path: &str) -> RelativePath {
+        path.parse().unwrap()
+    }
+    fn marker(guid: u128, texture: &str) -> Marker {
+        let mut attrs = CommonAttributes::default();
+        attrs.set_icon_file(Some(path(texture)));
+        Marker {
+            guid: Uuid::from_u128(guid),
+            position: Vec3::ZERO,
+            map_id: 15,
+            category: "cat".to_string(),
+            attrs,
+        }
+    }
+
+    #[test]
+    fn identical_textures_share_one_buffer() {
+        let mut pack = PackCore::default();
+        pack.textures.insert(path("a.png"), b"same".to_vec());
+        pack.textures.insert(path("icons/b.png"), b"same".to_vec());
+        pack.textures.insert(path("c.png"), b"other".to_vec());
+        let map = pack.maps.entry(15).or_default();
+        map.markers.extend([
+            marker(1, "a.png"),
+            marker(2, "icons/b.png"),
+            marker(3, "c.png"),
+        ]);
+        let mut frames = marker(4, "c.png");
+        frames
+            .attrs
+            .set_icon_frames(Some(vec![path("icons/b.png"), path("c.png")]));
+        map.markers.push(frames);
+
+        let aliases = pack.dedup_textures();
+        assert_eq!(aliases.len(), 1);
+        assert_eq!(aliases[&path("icons/b.png")], path("a.png"));
+        assert_eq!(
+            pack.textures.keys().collect::<Vec<_>>(),
+            [&path("a.png"), &path("c.png")]
+        );
+        let markers = &pack.maps[&15].markers;
+        let icons: Vec<&str> = markers
+            .iter()
+            .map(|m| m.attrs.get_icon_file().unwrap().as_str())
+            .collect();
+        assert_eq!(icons, ["a.png", "a.png", "c.png", "c.png"]);
+        assert_eq!(
+            markers[3].attrs.get_icon_frames().unwrap(),
+            &[path("a.png"), path("c.png")]
+        );
+        // nothing left to deduplicate
+        assert!(pack.dedup_textures().is_empty());
+    }
+}
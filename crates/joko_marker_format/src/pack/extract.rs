@@ -0,0 +1,201 @@
+use std::collections::BTreeSet;
+
+use indexmap::IndexMap;
+
+use super::{Category, CommonAttributes, MapData, PackCore, RelativePath};
+
+impl PackCore {
+    /// A new pack with the category `full_name` (like `a.b.c`), its sub categories and the markers, trails, textures and tbins used by them.
+    /// The parent categories are kept without their other children, so that the full names of the categories and the inherited attributes stay the same.
+    /// returns None if the category doesn't exist. This pack is not changed.
+    pub fn extract_subtree(&self, full_name: &str) -> Option<PackCore> {
+        let mut pack = PackCore::default();
+        let mut textures = BTreeSet::new();
+        let mut tbins = BTreeSet::new();
+
+        let names: Vec<&str> = full_name.split('.').collect();
+        let mut cats = &self.categories;
+        let mut extracted = &mut pack.categories;
+        for (depth, name) in names.iter().enumerate() {
+            let source = cats.get(*name)?;
+            add_used_files(&source.props, &mut textures, &mut tbins);
+            let cat = if depth == names.len() - 1 {
+                recursive_add_used_files(&source.children, &mut textures, &mut tbins);
+                source.clone()
+            } else {
+                Category {
+                    children: IndexMap::new(),
+                    ..source.clone()
+                }
+            };
+            cats = &source.children;
+            extracted = &mut extracted.entry(name.to_string()).or_insert(cat).children;
+        }
+
+        let sub_category_prefix = format!("{full_name}.");
+        let in_subtree =
+            |category: &str| category == full_name || category.starts_with(&sub_category_prefix);
+        for (map_id, map) in self.maps.iter() {
+            let markers: Vec<_> = map
+                .markers
+                .iter()
+                .filter(|marker| in_subtree(&marker.category))
+                .cloned()
+                .collect();
+            let trails: Vec<_> = map
+                .trails
+                .iter()
+                .filter(|trail| in_subtree(&trail.category))
+                .cloned()
+                .collect();
+            if markers.is_empty() && trails.is_empty() {
+                continue;
+            }
+            for marker in markers.iter() {
+                add_used_files(&marker.attrs, &mut textures, &mut tbins);
+            }
+            for trail in trails.iter() {
+                add_used_files(&trail.props, &mut textures, &mut tbins);
+            }
+            pack.maps.insert(*map_id, MapData { markers, trails });
+        }
+        for path in textures {
+            if let Some(texture) = self.textures.get(&path) {
+                pack.textures.insert(path, texture.clone());
+            }
+        }
+        for path in tbins {
+            if let Some(tbin) = self.tbins.get(&path) {
+                pack.tbins.insert(path, tbin.clone());
+            }
+        }
+        Some(pack)
+    }
+}
+
+fn recursive_add_used_files(
+    cats: &IndexMap<String, Category>,
+    textures: &mut BTreeSet<RelativePath>,
+    tbins: &mut BTreeSet<RelativePath>,
+) {
+    for cat in cats.values() {
+        add_used_files(&cat.props, textures, tbins);
+        recursive_add_used_files(&cat.children, textures, tbins);
+    }
+}
+
+fn add_used_files(
+    attrs: &CommonAttributes,
+    textures: &mut BTreeSet<RelativePath>,
+    tbins: &mut BTreeSet<RelativePath>,
+) {
+    for texture in [attrs.get_icon_file(), attrs.get_texture()]
+        .into_iter()
+        .flatten()
+        .chain(attrs.get_icon_frames().into_iter().flatten())
+    {
+        textures.insert(texture.clone());
+    }
+    if let Some(tbin) = attrs.get_trail_data() {
+        tbins.insert(tbin.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::pack::{Marker, TBin, Trail};
+
+    fn category(children: &[&str]) -> Category {
+        Category {
+            display_name: String::new(),
+            separator: false,
+            default_enabled: true,
+            props: CommonAttributes::default(),
+            children: children
+                .iter()
+                .map(|name| (name.to_string(), category(&[])))
+                .collect(),
+        }
+    }
+    fn path(path: &str) -> RelativePath {
+        path.parse().unwrap()
+    }
+    fn marker(guid: u128, category: &str, texture: &str) -> Marker {
+        let mut attrs = CommonAttributes::default();
+        attrs.set_icon_file(Some(path(texture)));
+        Marker {
+            guid: Uuid::from_u128(guid),
+            position: Vec3::ZERO,
+            map_id: 15,
+            category: category.to_string(),
+            attrs,
+        }
+    }
+
+    #[test]
+    fn subtree_contains_only_what_it_uses() {
+        let mut pack = PackCore::default();
+        let mut root = category(&["b", "c"]);
+        root.children["b"]
+            .children
+            .insert("d".to_string(), category(&[]));
+        pack.categories.insert("a".to_string(), root);
+        pack.categories.insert("other".to_string(), category(&[]));
+        for texture in ["b.png", "d.png", "c.png", "unused.png"] {
+            pack.textures.insert(path(texture), texture.into());
+        }
+        pack.tbins
+            .insert(path("b.trl"), TBin::new(15, 0, vec![Vec3::X]));
+        pack.tbins
+            .insert(path("c.trl"), TBin::new(15, 0, vec![Vec3::Y]));
+        let map = pack.maps.entry(15).or_default();
+        map.markers.extend([
+            marker(1, "a.b", "b.png"),
+            marker(2, "a.b.d", "d.png"),
+            marker(3, "a.c", "c.png"),
+            marker(4, "a.bb", "c.png"),
+        ]);
+        for (guid, category, tbin) in [(5, "a.b", "b.trl"), (6, "a.c", "c.trl")] {
+            let mut props = CommonAttributes::default();
+            props.set_trail_data(Some(path(tbin)));
+            map.trails.push(Trail {
+                guid: Uuid::from_u128(guid),
+                map_id: 15,
+                category: category.to_string(),
+                props,
+            });
+        }
+        pack.maps
+            .entry(50)
+            .or_default()
+            .markers
+            .push(marker(7, "other", "unused.png"));
+
+        let extracted = pack.extract_subtree("a.b").unwrap();
+        assert_eq!(extracted.categories.keys().collect::<Vec<_>>(), ["a"]);
+        let a = &extracted.categories["a"];
+        assert_eq!(a.children.keys().collect::<Vec<_>>(), ["b"]);
+        assert_eq!(a.children["b"].children.keys().collect::<Vec<_>>(), ["d"]);
+
+        assert_eq!(extracted.maps.keys().collect::<Vec<_>>(), [&15]);
+        let map = &extracted.maps[&15];
+        let guids: Vec<u128> = map.markers.iter().map(|m| m.guid.as_u128()).collect();
+        assert_eq!(guids, [1, 2]);
+        let guids: Vec<u128> = map.trails.iter().map(|t| t.guid.as_u128()).collect();
+        assert_eq!(guids, [5]);
+        assert_eq!(
+            extracted.textures.keys().collect::<Vec<_>>(),
+            [&path("b.png"), &path("d.png")]
+        );
+        assert_eq!(extracted.tbins.keys().collect::<Vec<_>>(), [&path("b.trl")]);
+
+        // the source pack is left unchanged
+        assert_eq!(pack.maps[&15].markers.len(), 4);
+        assert_eq!(pack.textures.len(), 4);
+        assert!(pack.extract_subtree("a.missing").is_none());
+    }
+}
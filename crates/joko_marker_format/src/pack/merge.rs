@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, HashSet};
+
+use indexmap::IndexMap;
+
+use super::{Category, PackCore, RelativePath};
+
+/// How [PackCore::merge] resolves a category which exists (by its full name) in both packs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeStrategy {
+    /// keep the attributes of the category from the first pack. The sub categories are still merged
+    #[default]
+    KeepFirst,
+    /// use the attributes of the category from the second pack. The sub categories are still merged
+    KeepSecond,
+    /// add the category of the second pack next to the existing one with a new name like `name_2`.
+    /// The markers and trails of the second pack are moved to the renamed category.
+    Rename,
+}
+
+/// The conflicts found by [PackCore::merge]
+#[derive(Debug, Default, Clone)]
+pub struct PackMergeReport {
+    /// textures which exist in both packs with different contents. we keep the texture of the first pack
+    pub conflicting_textures: Vec<RelativePath>,
+    /// tbins which exist in both packs with different nodes. we keep the tbin of the first pack
+    pub conflicting_tbins: Vec<RelativePath>,
+    /// full names of the categories of the second pack which were renamed by [MergeStrategy::Rename] and their new full names
+    pub renamed_categories: BTreeMap<String, String>,
+    /// number of markers and trails of the second pack that were skipped, because a marker or trail with the same guid already exists
+    pub duplicate_guids: usize,
+}
+
+impl PackCore {
+    /// merges `other` into this pack. markers and trails are deduplicated by their guid.
+    pub fn merge(&mut self, other: PackCore, strategy: MergeStrategy) -> PackMergeReport {
+        let mut report = PackMergeReport::default();
+        for (path, bytes) in other.textures {
+            match self.textures.get(&path) {
+                Some(existing) if existing != &bytes => report.conflicting_textures.push(path),
+                Some(_) => {}
+                None => {
+                    self.textures.insert(path, bytes);
+                }
+            }
+        }
+        for (path, tbin) in other.tbins {
+            match self.tbins.get(&path) {
+                Some(existing) if existing.nodes != tbin.nodes => {
+                    report.conflicting_tbins.push(path)
+                }
+                Some(_) => {}
+                None => {
+                    self.tbins.insert(path, tbin);
+                }
+            }
+        }
+        merge_categories(
+            &mut self.categories,
+            other.categories,
+            strategy,
+            "",
+            &mut report.renamed_categories,
+        );
+
+        let mut guids: HashSet<_> = self
+            .maps
+            .values()
+            .flat_map(|map| {
+                map.markers
+                    .iter()
+                    .map(|marker| marker.guid)
+                    .chain(map.trails.iter().map(|trail| trail.guid))
+            })
+            .collect();
+        for (map_id, other_map) in other.maps {
+            let map = self.maps.entry(map_id).or_default();
+            for mut marker in other_map.markers {
+                if !guids.insert(marker.guid) {
+                    report.duplicate_guids += 1;
+                    continue;
+                }
+                marker.category = renamed_category(&report.renamed_categories, marker.category);
+                map.markers.push(marker);
+            }
+            for mut trail in other_map.trails {
+                if !guids.insert(trail.guid) {
+                    report.duplicate_guids += 1;
+                    continue;
+                }
+                trail.category = renamed_category(&report.renamed_categories, trail.category);
+                map.trails.push(trail);
+            }
+        }
+        report
+    }
+}
+
+fn merge_categories(
+    cats: &mut IndexMap<String, Category>,
+    other: IndexMap<String, Category>,
+    strategy: MergeStrategy,
+    parent_name: &str,
+    renamed: &mut BTreeMap<String, String>,
+) {
+    for (name, mut other_cat) in other {
+        let full_name = if parent_name.is_empty() {
+            name.clone()
+        } else {
+            format!("{parent_name}.{name}")
+        };
+        let Some(existing) = cats.get_mut(&name) else {
+            cats.insert(name, other_cat);
+            continue;
+        };
+        match strategy {
+            MergeStrategy::KeepFirst => {}
+            MergeStrategy::KeepSecond => {
+                existing.display_name = std::mem::take(&mut other_cat.display_name);
+                existing.separator = other_cat.separator;
+                existing.default_enabled = other_cat.default_enabled;
+                existing.props = other_cat.props.clone();
+            }
+            MergeStrategy::Rename => {
+                let new_name = (2..)
+                    .map(|index| format!("{name}_{index}"))
+                    .find(|new_name| !cats.contains_key(new_name))
+                    .unwrap();
+                let new_full_name = if parent_name.is_empty() {
+                    new_name.clone()
+                } else {
+                    format!("{parent_name}.{new_name}")
+                };
+                renamed.insert(full_name, new_full_name);
+                cats.insert(new_name, other_cat);
+                continue;
+            }
+        }
+        merge_categories(
+            &mut existing.children,
+            other_cat.children,
+            strategy,
+            &full_name,
+            renamed,
+        );
+    }
+}
+
+/// markers of the renamed categories (or their sub categories) are moved to the new name
+fn renamed_category(renamed: &BTreeMap<String, String>, category: String) -> String {
+    for (old, new) in renamed {
+        if let Some(rest) = category.strip_prefix(old.as_str()) {
+            if rest.is_empty() || rest.starts_with('.') {
+                return format!("{new}{rest}");
+            }
+        }
+    }
+    category
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::pack::{CommonAttributes, Marker};
+
+    fn category(display_name: &str, children: &[&str]) -> Category {
+        Category {
+            display_name: display_name.to_string(),
+            separator: false,
+            default_enabled: true,
+            props: CommonAttributes::default(),
+            children: children
+                .iter()
+                .map(|name| (name.to_string(), category(name, &[])))
+                .collect(),
+        }
+    }
+    fn pack(display_name: &str, children: &[&str], texture: &[u8], guids: &[u128]) -> PackCore {
+        let mut pack = PackCore::default();
+        pack.categories
+            .insert("cat".to_string(), category(display_name, children));
+        pack.textures
+            .insert("icon.png".parse().unwrap(), texture.to_vec());
+        let map = pack.maps.entry(15).or_default();
+        for &guid in guids {
+            map.markers.push(Marker {
+                guid: Uuid::from_u128(guid),
+                position: Vec3::ZERO,
+                map_id: 15,
+                category: "cat.a".to_string(),
+                attrs: CommonAttributes::default(),
+            });
+        }
+        pack
+    }
+    fn merged(strategy: MergeStrategy) -> (PackCore, PackMergeReport) {
+        let mut first = pack("first", &["a"], b"first", &[1, 2]);
+        let second = pack("second", &["a", "b"], b"second", &[2, 3]);
+        let report = first.merge(second, strategy);
+        (first, report)
+    }
+    fn categories(pack: &PackCore) -> Vec<&str> {
+        pack.maps[&15]
+            .markers
+            .iter()
+            .map(|marker| marker.category.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn keep_first_keeps_the_first_category() {
+        let (pack, report) = merged(MergeStrategy::KeepFirst);
+        let cat = &pack.categories["cat"];
+        assert_eq!(cat.display_name, "first");
+        assert_eq!(cat.children.keys().collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(pack.maps[&15].markers.len(), 3);
+        assert_eq!(report.duplicate_guids, 1);
+        assert_eq!(
+            report.conflicting_textures,
+            ["icon.png".parse::<RelativePath>().unwrap()]
+        );
+        assert_eq!(
+            pack.textures[&"icon.png".parse::<RelativePath>().unwrap()],
+            b"first"
+        );
+        assert!(report.renamed_categories.is_empty());
+    }
+
+    #[test]
+    fn keep_second_uses_the_second_category() {
+        let (pack, report) = merged(MergeStrategy::KeepSecond);
+        let cat = &pack.categories["cat"];
+        assert_eq!(cat.display_name, "second");
+        assert_eq!(cat.children.keys().collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(pack.maps[&15].markers.len(), 3);
+        assert_eq!(report.duplicate_guids, 1);
+        assert_eq!(categories(&pack), ["cat.a", "cat.a", "cat.a"]);
+    }
+
+    #[test]
+    fn rename_moves_the_second_category_and_its_markers() {
+        let (pack, report) = merged(MergeStrategy::Rename);
+        assert_eq!(pack.categories.keys().collect::<Vec<_>>(), ["cat", "cat_2"]);
+        assert_eq!(pack.categories["cat"].display_name, "first");
+        assert_eq!(pack.categories["cat"].children.len(), 1);
+        assert_eq!(pack.categories["cat_2"].display_name, "second");
+        assert_eq!(
+            report.renamed_categories,
+            BTreeMap::from([("cat".to_string(), "cat_2".to_string())])
+        );
+        // the duplicate marker is still skipped
+        assert_eq!(report.duplicate_guids, 1);
+        assert_eq!(categories(&pack), ["cat.a", "cat.a", "cat_2.a"]);
+    }
+}
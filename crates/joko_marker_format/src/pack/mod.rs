@@ -1,15 +1,22 @@
 mod common;
+mod dedup;
+mod extract;
 mod marker;
+mod merge;
 mod trail;
 
 use std::{collections::BTreeMap, str::FromStr};
 
+use glam::Vec3;
 use indexmap::IndexMap;
 
 pub use common::*;
 pub(crate) use marker::*;
+pub use merge::*;
 use smol_str::SmolStr;
+pub use trail::TrailParseConfig;
 pub(crate) use trail::*;
+use uuid::Uuid;
 
 #[derive(Default, Debug, Clone)]
 pub(crate) struct PackCore {
@@ -19,12 +26,135 @@ pub(crate) struct PackCore {
     pub maps: BTreeMap<u32, MapData>,
 }
 
+impl PackCore {
+    /// all the categories with their depth (0 for the root categories) in pre-order, following the order of the categories file
+    pub fn iter_categories(&self) -> impl Iterator<Item = (&Category, usize)> {
+        let mut stack = vec![self.categories.values()];
+        std::iter::from_fn(move || loop {
+            if stack.is_empty() {
+                return None;
+            }
+            let depth = stack.len() - 1;
+            match stack[depth].next() {
+                Some(cat) => {
+                    stack.push(cat.children.values());
+                    return Some((cat, depth));
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        })
+    }
+    /// full names of the categories whose display name or full name contains `query`, ignoring ascii case.
+    /// The most relevant come first: exact display name matches, then display names starting with the query,
+    /// then display names containing it and finally the full names containing it. Ties keep the order of the categories file.
+    pub fn search_categories(&self, query: &str) -> Vec<String> {
+        let query = query.trim();
+        if query.is_empty() {
+            return vec![];
+        }
+        let mut matches = vec![];
+        let mut full_name = String::new();
+        recursive_search_categories(&self.categories, query, &mut full_name, &mut matches);
+        matches.sort_by_key(|(relevance, _)| *relevance);
+        matches
+            .into_iter()
+            .map(|(_, full_name)| full_name)
+            .collect()
+    }
+    /// the category with this full name (like `a.b.c`). The names are compared ignoring ascii case, as the `type` of markers is lowercased
+    pub fn category(&self, full_name: &str) -> Option<&Category> {
+        let mut cats = &self.categories;
+        let mut category = None;
+        for name in full_name.split('.') {
+            let (_, cat) = cats
+                .iter()
+                .find(|(cat_name, _)| cat_name.eq_ignore_ascii_case(name))?;
+            category = Some(cat);
+            cats = &cat.children;
+        }
+        category
+    }
+    /// finds the trail with this guid and returns the path and data of its tbin.
+    /// Trails may share a tbin, so editing the tbin affects all of them.
+    pub fn trail_tbin_mut(&mut self, trail_guid: Uuid) -> Option<(RelativePath, &mut TBin)> {
+        let path = self
+            .maps
+            .values()
+            .flat_map(|map| map.trails.iter())
+            .find(|trail| trail.guid == trail_guid)?
+            .props
+            .get_trail_data()?
+            .clone();
+        let tbin = self.tbins.get_mut(&path)?;
+        Some((path, tbin))
+    }
+}
+
+/// reuses the `full_name` buffer while walking the tree, so that we only allocate for the matches
+fn recursive_search_categories(
+    cats: &IndexMap<String, Category>,
+    query: &str,
+    full_name: &mut String,
+    matches: &mut Vec<(u8, String)>,
+) {
+    for (name, cat) in cats {
+        let parent_len = full_name.len();
+        if parent_len != 0 {
+            full_name.push('.');
+        }
+        full_name.push_str(name);
+        let relevance = if cat.display_name.eq_ignore_ascii_case(query) {
+            Some(0)
+        } else if starts_with_ignore_case(&cat.display_name, query) {
+            Some(1)
+        } else if contains_ignore_case(&cat.display_name, query) {
+            Some(2)
+        } else if contains_ignore_case(full_name, query) {
+            Some(3)
+        } else {
+            None
+        };
+        if let Some(relevance) = relevance {
+            matches.push((relevance, full_name.clone()));
+        }
+        recursive_search_categories(&cat.children, query, full_name, matches);
+        full_name.truncate(parent_len);
+    }
+}
+fn starts_with_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.len() >= needle.len()
+        && haystack.as_bytes()[..needle.len()].eq_ignore_ascii_case(needle.as_bytes())
+}
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .any(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
 #[derive(Default, Debug, Clone)]
 pub(crate) struct MapData {
     pub markers: Vec<Marker>,
     pub trails: Vec<Trail>,
 }
 
+impl MapData {
+    /// markers within `radius` (in meters) of `center`, sorted by their distance from the center.
+    /// This is a linear scan for now. If it becomes a bottleneck, this is the place to use a spatial index (grid or kd-tree).
+    pub fn markers_near(&self, center: Vec3, radius: f32) -> Vec<(f32, &Marker)> {
+        let mut markers: Vec<(f32, &Marker)> = self
+            .markers
+            .iter()
+            .map(|marker| (marker.position.distance(center), marker))
+            .filter(|(distance, _)| *distance <= radius)
+            .collect();
+        markers.sort_by(|a, b| a.0.total_cmp(&b.0));
+        markers
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct Category {
     pub display_name: String,
@@ -64,17 +194,60 @@ impl RelativePath {
         Self(new.into())
     }
 
+    /// Like [Self::join_str], but collapses the `.` and `..` segments of the joined path.
+    /// returns None if a `..` would go above the root of the pack.
+    pub fn resolve_relative(&self, other: &str) -> Option<Self> {
+        let other = other.to_lowercase();
+        let mut segments: Vec<&str> = vec![];
+        for segment in self.0.split('/').chain(other.split('/')) {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    segments.pop()?;
+                }
+                segment => segments.push(segment),
+            }
+        }
+        let mut path = segments.join("/");
+        if other.ends_with('/') && !path.is_empty() {
+            path.push('/');
+        }
+        Some(Self(path.into()))
+    }
+
     pub fn ends_with(&self, ext: &str) -> bool {
         self.0.ends_with(ext)
     }
+    /// the last segment of the path. For directories, this is the name of the directory without the trailing `/`
+    pub fn file_name(&self) -> &str {
+        self.0
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+    }
+    /// the extension of [Self::file_name] without the `.`. hidden files like `.png` don't have an extension.
+    pub fn extension(&self) -> Option<&str> {
+        if self.is_dir() {
+            return None;
+        }
+        let (stem, extension) = self.file_name().rsplit_once('.')?;
+        (!stem.is_empty() && !extension.is_empty()).then_some(extension)
+    }
+    /// compares the extension case insensitively, as paths which were not normalized may not be lowercase
+    fn has_extension(&self, extension: &str) -> bool {
+        self.extension()
+            .map(|ext| ext.eq_ignore_ascii_case(extension))
+            .unwrap_or_default()
+    }
     pub fn is_png(&self) -> bool {
-        self.ends_with(".png")
+        self.has_extension("png")
     }
     pub fn is_tbin(&self) -> bool {
-        self.ends_with(".trl")
+        self.has_extension("trl")
     }
     pub fn is_xml(&self) -> bool {
-        self.ends_with(".xml")
+        self.has_extension("xml")
     }
     pub fn is_dir(&self) -> bool {
         self.ends_with("/")
@@ -112,3 +285,157 @@ impl FromStr for RelativePath {
         Ok(Self(path.to_lowercase().into()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_relative_collapses_dot_segments() {
+        let base: RelativePath = "icons/maps/".parse().unwrap();
+        assert_eq!(
+            base.resolve_relative("../Foo/./bar.png").unwrap().as_str(),
+            "icons/foo/bar.png"
+        );
+        assert_eq!(
+            base.resolve_relative("../../bar.png").unwrap().as_str(),
+            "bar.png"
+        );
+        assert_eq!(
+            base.resolve_relative("sub/").unwrap().as_str(),
+            "icons/maps/sub/"
+        );
+        // join_str doesn't collapse anything
+        assert_eq!(
+            base.join_str("../bar.png").as_str(),
+            "icons/maps/../bar.png"
+        );
+    }
+
+    #[test]
+    fn extensions_are_case_insensitive() {
+        // built without normalization, so the case is kept
+        let png = RelativePath("Icons/Maps/Marker.PNG".into());
+        assert!(png.is_png());
+        assert!(!png.is_tbin());
+        assert_eq!(png.extension(), Some("PNG"));
+        assert_eq!(png.file_name(), "Marker.PNG");
+
+        let tbin: RelativePath = "trails/a.b/Trail.TRL".parse().unwrap();
+        assert!(tbin.is_tbin());
+        assert_eq!(tbin.file_name(), "trail.trl");
+        assert!(RelativePath("Cats.Xml".into()).is_xml());
+
+        let dir: RelativePath = "trails/a.b/".parse().unwrap();
+        assert_eq!(dir.file_name(), "a.b");
+        assert_eq!(dir.extension(), None);
+        assert_eq!(RelativePath(".png".into()).extension(), None);
+        assert_eq!(RelativePath("readme".into()).extension(), None);
+    }
+
+    #[test]
+    fn resolve_relative_doesnt_escape_the_root() {
+        let base: RelativePath = "icons".parse().unwrap();
+        assert!(base.resolve_relative("../../bar.png").is_none());
+        assert!(RelativePath::default()
+            .resolve_relative("../bar.png")
+            .is_none());
+    }
+
+    fn category(display_name: &str, children: Vec<Category>) -> Category {
+        Category {
+            display_name: display_name.to_string(),
+            separator: false,
+            default_enabled: true,
+            props: Default::default(),
+            children: children
+                .into_iter()
+                .map(|cat| (cat.display_name.clone(), cat))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn categories_are_iterated_in_pre_order() {
+        let mut pack = PackCore::default();
+        for cat in [
+            category(
+                "a",
+                vec![
+                    category("a1", vec![category("a1x", vec![])]),
+                    category("a2", vec![]),
+                ],
+            ),
+            category("b", vec![]),
+        ] {
+            pack.categories.insert(cat.display_name.clone(), cat);
+        }
+        let order: Vec<(&str, usize)> = pack
+            .iter_categories()
+            .map(|(cat, depth)| (cat.display_name.as_str(), depth))
+            .collect();
+        assert_eq!(
+            order,
+            [("a", 0), ("a1", 1), ("a1x", 2), ("a2", 1), ("b", 0)]
+        );
+        assert_eq!(PackCore::default().iter_categories().count(), 0);
+    }
+
+    #[test]
+    fn search_categories_orders_by_relevance() {
+        let mut pack = PackCore::default();
+        let bosses = category(
+            "World Bosses",
+            vec![
+                category("The Tequatl Route", vec![]),
+                category("Tequatl", vec![category("Chest", vec![])]),
+                category("Tequatl Chests", vec![]),
+                category("Shatterer", vec![]),
+            ],
+        );
+        pack.categories.insert(bosses.display_name.clone(), bosses);
+        let expected = [
+            "World Bosses.Tequatl",
+            "World Bosses.Tequatl Chests",
+            "World Bosses.The Tequatl Route",
+            "World Bosses.Tequatl.Chest",
+        ];
+        assert_eq!(pack.search_categories("tequatl"), expected);
+        assert_eq!(pack.search_categories(" TEQUATL "), expected);
+        assert_eq!(pack.search_categories("shat"), ["World Bosses.Shatterer"]);
+        assert!(pack.search_categories("").is_empty());
+        assert!(pack.search_categories("jormag").is_empty());
+    }
+
+    #[test]
+    fn markers_near_are_sorted_by_distance() {
+        let mut map = MapData::default();
+        for (index, position) in [
+            Vec3::new(5.0, 0.0, 0.0),
+            Vec3::new(0.0, 20.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, -10.0),
+            Vec3::new(0.0, 0.0, 10.5),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            map.markers.push(Marker {
+                guid: Uuid::from_u128(index as u128),
+                position,
+                map_id: 15,
+                category: String::new(),
+                attrs: CommonAttributes::default(),
+            });
+        }
+        let near: Vec<u128> = map
+            .markers_near(Vec3::ZERO, 10.0)
+            .into_iter()
+            .map(|(_, marker)| marker.guid.as_u128())
+            .collect();
+        assert_eq!(near, [2, 0, 3]);
+        assert!(map
+            .markers_near(Vec3::new(100.0, 0.0, 0.0), 10.0)
+            .is_empty());
+    }
+}
@@ -1,7 +1,64 @@
+use std::sync::{Arc, Mutex};
+
+use glam::Vec3;
 use uuid::Uuid;
 
 use super::CommonAttributes;
 
+/// trail segments longer than this (in meters) are split when rendering. 400 game units (inches) like taco.
+pub(crate) const MAX_TRAIL_CHUNK_LENGTH: f32 = 400.0 / crate::INCHES_PER_METER;
+
+/// thresholds used to derive the rendered trail from the nodes of the trl files. defaults to the values used by taco.
+/// The nodes themselves are kept as they are in the trl file, see [TBin::render_nodes].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrailParseConfig {
+    /// segments longer than this (in meters) are split when rendering
+    pub max_chunk_length: f32,
+    /// consecutive nodes closer than this (in meters) are merged into one
+    pub iso_epsilon: f32,
+    /// if the ends of a strip are closer than this (in meters), the strip is treated as a closed loop and the last node snaps to the first
+    pub closed_epsilon: f32,
+}
+impl Default for TrailParseConfig {
+    fn default() -> Self {
+        Self {
+            max_chunk_length: MAX_TRAIL_CHUNK_LENGTH,
+            iso_epsilon: 0.01,
+            closed_epsilon: 0.1,
+        }
+    }
+}
+impl TrailParseConfig {
+    /// removes the near duplicate nodes and closes the strips that end where they start.
+    /// zero nodes separate the strips of a trail and are always kept.
+    pub(crate) fn clean_nodes(&self, nodes: Vec<Vec3>) -> Vec<Vec3> {
+        let mut result: Vec<Vec3> = Vec::with_capacity(nodes.len());
+        let mut strip_start = 0;
+        for node in nodes.into_iter().chain(std::iter::once(Vec3::ZERO)) {
+            if node == Vec3::ZERO {
+                let strip = &mut result[strip_start..];
+                if strip.len() > 2
+                    && strip[0].distance(strip[strip.len() - 1]) < self.closed_epsilon
+                {
+                    strip[strip.len() - 1] = strip[0];
+                }
+                result.push(node);
+                strip_start = result.len();
+                continue;
+            }
+            if let Some(previous) = result.last() {
+                if *previous != Vec3::ZERO && previous.distance(node) < self.iso_epsilon {
+                    continue;
+                }
+            }
+            result.push(node);
+        }
+        // remove the separator that we added at the end
+        result.pop();
+        result
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct Trail {
     pub guid: Uuid,
@@ -10,11 +67,165 @@ pub(crate) struct Trail {
     pub props: CommonAttributes,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub(crate) struct TBin {
     pub map_id: u32,
     pub version: u32,
-    pub nodes: Vec<glam::Vec3>,
+    /// the nodes as they are in the trl file
+    pub nodes: Vec<Vec3>,
+    /// config to use with [Self::render_nodes]. from the import
+    pub config: TrailParseConfig,
+    /// [Self::render_nodes] along with the config that they were computed with
+    render_nodes: Mutex<Option<(TrailParseConfig, Arc<[Vec3]>)>>,
+}
+impl Clone for TBin {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config,
+            ..Self::new(self.map_id, self.version, self.nodes.clone())
+        }
+    }
+}
+
+impl TBin {
+    pub fn new(map_id: u32, version: u32, nodes: Vec<Vec3>) -> Self {
+        Self {
+            map_id,
+            version,
+            nodes,
+            config: Default::default(),
+            render_nodes: Default::default(),
+        }
+    }
+    /// [Self::nodes] cleaned up with [TrailParseConfig::clean_nodes] and with the segments longer than `max_chunk_length` split into equal parts,
+    /// so that per vertex effects like fading stay smooth along long segments.
+    /// Most trails are never rendered, so this is computed on first use and cached until a different config is used.
+    pub fn render_nodes(&self, config: &TrailParseConfig) -> Arc<[Vec3]> {
+        let mut cache = self.render_nodes.lock().unwrap();
+        if let Some((cached_config, nodes)) = cache.as_ref() {
+            if cached_config == config {
+                return nodes.clone();
+            }
+        }
+        let nodes: Arc<[Vec3]> = subdivide_trail(
+            &config.clean_nodes(self.nodes.clone()),
+            config.max_chunk_length,
+        )
+        .into();
+        *cache = Some((*config, nodes.clone()));
+        nodes
+    }
+    /// appends a node at the end of the trail
+    pub fn push_node(&mut self, node: Vec3) {
+        self.nodes.push(node);
+        self.clear_render_nodes();
+    }
+    /// inserts a node before `index`. returns false if `index` is beyond the end of the trail
+    pub fn insert_node(&mut self, index: usize, node: Vec3) -> bool {
+        if index > self.nodes.len() {
+            return false;
+        }
+        self.nodes.insert(index, node);
+        self.clear_render_nodes();
+        true
+    }
+    /// removes the node at `index` and returns it
+    pub fn remove_node(&mut self, index: usize) -> Option<Vec3> {
+        if index >= self.nodes.len() {
+            return None;
+        }
+        let node = self.nodes.remove(index);
+        self.clear_render_nodes();
+        Some(node)
+    }
+    fn clear_render_nodes(&mut self) {
+        *self.render_nodes.get_mut().unwrap() = None;
+    }
 }
 
-impl TBin {}
+/// splits the segments longer than `max_chunk_length` into equal parts.
+/// zero nodes separate the strips of a trail, so segments which start or end with them are left alone.
+fn subdivide_trail(nodes: &[Vec3], max_chunk_length: f32) -> Vec<Vec3> {
+    let mut result = Vec::with_capacity(nodes.len());
+    for (index, &node) in nodes.iter().enumerate() {
+        if index > 0 && max_chunk_length > 0.0 {
+            let previous = nodes[index - 1];
+            if previous != Vec3::ZERO && node != Vec3::ZERO {
+                let chunks = (previous.distance(node) / max_chunk_length).ceil() as usize;
+                for chunk in 1..chunks {
+                    result.push(previous.lerp(node, chunk as f32 / chunks as f32));
+                }
+            }
+        }
+        result.push(node);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk_length(max_chunk_length: f32) -> TrailParseConfig {
+        TrailParseConfig {
+            max_chunk_length,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn long_segments_are_subdivided_lazily() {
+        let tbin = TBin::new(
+            15,
+            0,
+            vec![
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 30.0),
+                Vec3::ZERO,
+                Vec3::new(5.0, 0.0, 0.0),
+                Vec3::new(5.0, 0.0, 1.0),
+            ],
+        );
+        let subdivided = tbin.render_nodes(&chunk_length(10.0));
+        assert_eq!(
+            subdivided.as_ref(),
+            &[
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(1.0, 0.0, 10.0),
+                Vec3::new(1.0, 0.0, 20.0),
+                Vec3::new(1.0, 0.0, 30.0),
+                Vec3::ZERO,
+                Vec3::new(5.0, 0.0, 0.0),
+                Vec3::new(5.0, 0.0, 1.0),
+            ]
+        );
+        // the raw nodes are untouched
+        assert_eq!(tbin.nodes.len(), 5);
+        // cached for the same config
+        assert!(Arc::ptr_eq(
+            &subdivided,
+            &tbin.render_nodes(&chunk_length(10.0))
+        ));
+        assert_eq!(tbin.render_nodes(&chunk_length(100.0)).len(), 5);
+    }
+
+    #[test]
+    fn editing_nodes_invalidates_render_nodes() {
+        let mut tbin = TBin::new(15, 0, vec![Vec3::X, Vec3::new(1.0, 0.0, 30.0)]);
+        assert_eq!(tbin.render_nodes(&chunk_length(10.0)).len(), 4);
+
+        tbin.push_node(Vec3::new(1.0, 0.0, 40.0));
+        assert_eq!(tbin.nodes.last(), Some(&Vec3::new(1.0, 0.0, 40.0)));
+        assert_eq!(tbin.render_nodes(&chunk_length(10.0)).len(), 5);
+
+        assert!(tbin.insert_node(0, Vec3::new(1.0, 0.0, -10.0)));
+        assert_eq!(tbin.nodes[0], Vec3::new(1.0, 0.0, -10.0));
+        assert_eq!(tbin.render_nodes(&chunk_length(10.0)).len(), 6);
+        assert!(!tbin.insert_node(10, Vec3::X));
+
+        assert_eq!(tbin.remove_node(3), Some(Vec3::new(1.0, 0.0, 40.0)));
+        assert_eq!(tbin.remove_node(3), None);
+        assert_eq!(tbin.nodes.len(), 3);
+        assert_eq!(tbin.render_nodes(&chunk_length(10.0)).len(), 5);
+    }
+}
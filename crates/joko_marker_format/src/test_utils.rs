@@ -0,0 +1,30 @@
+//! Helpers shared by the tests of this crate
+
+use std::io::Write;
+
+use cap_std::{
+    ambient_authority,
+    fs_utf8::{camino::Utf8PathBuf, Dir},
+};
+use zip::{write::FileOptions, ZipWriter};
+
+/// a zip file with these files (path and contents), like a taco pack
+pub(crate) fn zip_pack(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(std::io::Cursor::new(vec![]));
+    for (name, bytes) in files {
+        writer.start_file(*name, FileOptions::default()).unwrap();
+        writer.write_all(bytes).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+/// a new directory in the temp dir, unique to `test_name` and this process.
+/// returns the path too, so that the test can remove the directory once it is done
+pub(crate) fn temp_jokolay_dir(test_name: &str) -> (Dir, Utf8PathBuf) {
+    let path = Utf8PathBuf::try_from(std::env::temp_dir())
+        .unwrap()
+        .join(format!("jokolay_{test_name}_{}", std::process::id()));
+    Dir::create_ambient_dir_all(&path, ambient_authority()).unwrap();
+    let dir = Dir::open_ambient_dir(&path, ambient_authority()).unwrap();
+    (dir, path)
+}
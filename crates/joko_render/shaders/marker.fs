@@ -7,9 +7,16 @@ layout(location = 2) in vec4 vcolor;
 layout(location = 0) out vec4 ocolor;
 
 layout(location = 1) uniform sampler2D sam;
+// scrolls the texture along trails. always zero for markers. see `trail_uv_offset` in billboard.rs
+layout(location = 3) uniform float texture_offset;
 
 void main() {
-    vec4 color = texture(sam, vtex_coord, -2.0);
+    vec2 tex_coord = vtex_coord;
+    if (texture_offset != 0.0) {
+        // trail textures repeat along the trail, but the texture itself is clamped. so we wrap it here
+        tex_coord.y = fract(tex_coord.y + texture_offset);
+    }
+    vec4 color = texture(sam, tex_coord, -2.0);
     color.a = color.a * valpha;
     if (color.a < 0.01) {
         discard;
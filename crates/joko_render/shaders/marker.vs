@@ -24,7 +24,8 @@ void main(
     vcolor = color;
 
     float dist = distance(camera_pos, position.xyz);
-    if (fade_near_far.x > 0.0 && dist >= fade_near_far.x) {
+    // without a fade_far bigger than fade_near, there's nothing to fade towards. see `fade_alpha` in billboard.rs
+    if (fade_near_far.x > 0.0 && fade_near_far.y > fade_near_far.x && dist >= fade_near_far.x) {
             // if distance is exactly fade_near, we will multiply with 1.0
             // if its more, then we will multiply with how far we are in between fade_near and fade_far
         float ratio = 1.0 - (abs(dist - fade_near_far.x) / abs(fade_near_far.y - fade_near_far.x));
@@ -0,0 +1,175 @@
+//! Packs the small marker icons into a few big textures, so that the billboard renderer binds far fewer textures.
+
+use glam::{UVec2, Vec2};
+
+/// width and height of an atlas page in pixels
+pub const ATLAS_PAGE_SIZE: u32 = 1024;
+/// icons bigger than this (in width or height) don't fit in a tile and stay standalone textures
+pub const ATLAS_MAX_TILE_SIZE: u32 = 128;
+/// transparent pixels between the tiles, so that linear filtering doesn't bleed the neighbouring icons
+const ATLAS_PADDING: u32 = 1;
+
+/// Where an icon was placed by [AtlasPacker::insert]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasPlacement {
+    /// the index of the page (texture) which contains the icon
+    pub page: usize,
+    /// top left corner of the icon inside the page in pixels
+    pub pixel_pos: UVec2,
+    /// size of the icon in pixels
+    pub pixel_size: UVec2,
+    /// texture coordinates of the top left corner of the icon
+    pub uv_min: Vec2,
+    /// texture coordinates of the bottom right corner of the icon
+    pub uv_max: Vec2,
+}
+impl AtlasPlacement {
+    /// maps the texture coordinates of a standalone icon (0.0..=1.0) to the texture coordinates of the icon inside its page
+    pub fn remap_uv(&self, uv: Vec2) -> Vec2 {
+        self.uv_min + uv * (self.uv_max - self.uv_min)
+    }
+}
+
+/// A row of tiles inside a page. every tile of the shelf starts at the same y
+#[derive(Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    /// the x where the next tile is placed
+    cursor: u32,
+}
+
+/// A shelf packer. icons are placed left to right on shelves, and a new shelf is started below when the row is full.
+/// When a page is full, a new page is started. It only computes the placements, the caller copies the pixels.
+#[derive(Debug)]
+pub struct AtlasPacker {
+    page_size: u32,
+    max_tile_size: u32,
+    /// the shelves of each page
+    pages: Vec<Vec<Shelf>>,
+}
+impl Default for AtlasPacker {
+    fn default() -> Self {
+        Self::new(ATLAS_PAGE_SIZE, ATLAS_MAX_TILE_SIZE)
+    }
+}
+impl AtlasPacker {
+    pub fn new(page_size: u32, max_tile_size: u32) -> Self {
+        Self {
+            page_size,
+            max_tile_size: max_tile_size.min(page_size.saturating_sub(ATLAS_PADDING)),
+            pages: vec![],
+        }
+    }
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+    }
+    /// number of pages which have atleast one icon
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+    /// None if the icon is empty or bigger than the max tile size. Those icons should stay standalone textures
+    pub fn insert(&mut self, size: UVec2) -> Option<AtlasPlacement> {
+        if size.x == 0 || size.y == 0 || size.x > self.max_tile_size || size.y > self.max_tile_size
+        {
+            return None;
+        }
+        let padded = size + ATLAS_PADDING;
+        let page_size = self.page_size;
+        for (page, shelves) in self.pages.iter_mut().enumerate() {
+            if let Some(pixel_pos) = Self::insert_into_page(shelves, padded, page_size) {
+                return Some(self.placement(page, pixel_pos, size));
+            }
+        }
+        let mut shelves = vec![];
+        let pixel_pos = Self::insert_into_page(&mut shelves, padded, page_size)?;
+        self.pages.push(shelves);
+        Some(self.placement(self.pages.len() - 1, pixel_pos, size))
+    }
+    /// uses the first shelf which is tall enough and has space left. otherwise starts a new shelf
+    fn insert_into_page(shelves: &mut Vec<Shelf>, padded: UVec2, page_size: u32) -> Option<UVec2> {
+        if let Some(shelf) = shelves
+            .iter_mut()
+            .find(|shelf| shelf.height >= padded.y && shelf.cursor + padded.x <= page_size)
+        {
+            let pos = UVec2::new(shelf.cursor, shelf.y);
+            shelf.cursor += padded.x;
+            return Some(pos);
+        }
+        let y = shelves
+            .last()
+            .map(|shelf| shelf.y + shelf.height)
+            .unwrap_or(0);
+        if y + padded.y > page_size {
+            return None;
+        }
+        shelves.push(Shelf {
+            y,
+            height: padded.y,
+            cursor: padded.x,
+        });
+        Some(UVec2::new(0, y))
+    }
+    fn placement(&self, page: usize, pixel_pos: UVec2, pixel_size: UVec2) -> AtlasPlacement {
+        let page_size = self.page_size as f32;
+        AtlasPlacement {
+            page,
+            pixel_pos,
+            pixel_size,
+            uv_min: pixel_pos.as_vec2() / page_size,
+            uv_max: (pixel_pos + pixel_size).as_vec2() / page_size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(first: &AtlasPlacement, second: &AtlasPlacement) -> bool {
+        first.page == second.page
+            && first.uv_min.x < second.uv_max.x
+            && second.uv_min.x < first.uv_max.x
+            && first.uv_min.y < second.uv_max.y
+            && second.uv_min.y < first.uv_max.y
+    }
+
+    #[test]
+    fn packed_icons_do_not_overlap() {
+        let mut packer = AtlasPacker::new(64, 32);
+        let sizes = [
+            UVec2::new(31, 31),
+            UVec2::new(16, 16),
+            UVec2::new(20, 10),
+            UVec2::new(8, 30),
+            UVec2::new(31, 31),
+            UVec2::new(31, 31),
+            UVec2::new(31, 31),
+            UVec2::new(1, 1),
+        ];
+        let placements: Vec<AtlasPlacement> = sizes
+            .iter()
+            .map(|size| packer.insert(*size).unwrap())
+            .collect();
+        for (index, first) in placements.iter().enumerate() {
+            assert!(first.uv_min.cmpge(Vec2::ZERO).all() && first.uv_max.cmple(Vec2::ONE).all());
+            for second in &placements[index + 1..] {
+                assert!(!overlaps(first, second), "{first:?} overlaps {second:?}");
+            }
+        }
+        // the small icons filled the first page, so the last big icons start a second page
+        assert_eq!(packer.page_count(), 2);
+        assert_eq!(
+            placements[1].remap_uv(Vec2::ONE),
+            (placements[1].pixel_pos + 16).as_vec2() / 64.0
+        );
+    }
+
+    #[test]
+    fn big_icons_stay_standalone() {
+        let mut packer = AtlasPacker::new(64, 32);
+        assert!(packer.insert(UVec2::new(33, 8)).is_none());
+        assert!(packer.insert(UVec2::new(0, 8)).is_none());
+        assert_eq!(packer.page_count(), 0);
+    }
+}
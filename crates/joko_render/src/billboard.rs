@@ -22,6 +22,10 @@ pub struct BillBoardRenderer {
 pub struct TrailObject {
     pub vertices: Arc<[MarkerVertex]>,
     pub texture: u64,
+    /// The map which this trail belongs to. see [is_on_map]
+    pub map_id: u32,
+    /// how fast the texture scrolls along the trail. in texture lengths per second. zero means a static texture. see [trail_uv_offset]
+    pub texture_speed: f32,
 }
 const MARKER_VS: &str = include_str!("../shaders/marker.vs");
 
@@ -79,10 +83,7 @@ impl BillBoardRenderer {
         unsafe {
             gl_error!(gl);
         }
-        // sort by depth
-        self.markers.sort_unstable_by(|first, second| {
-            first.distance.total_cmp(&second.distance).reverse() // we need the farther markers (more distance from camera) to be rendered first, for correct alpha blending
-        });
+        sort_markers(&mut self.markers);
 
         let mut required_size_in_bytes =
             (self.markers.len() * 6 * std::mem::size_of::<MarkerVertex>()) as u64;
@@ -90,8 +91,8 @@ impl BillBoardRenderer {
             let len = (trail.vertices.len() * std::mem::size_of::<MarkerVertex>()) as u64;
             required_size_in_bytes = required_size_in_bytes.max(len);
         }
-        let mut vb = vec![];
-        vb.reserve(self.markers.len() * 6 * std::mem::size_of::<MarkerVertex>());
+        let mut vb =
+            Vec::with_capacity(self.markers.len() * 6 * std::mem::size_of::<MarkerVertex>());
 
         for marker_object in self.markers.iter() {
             vb.extend_from_slice(&marker_object.vertices);
@@ -128,6 +129,7 @@ impl BillBoardRenderer {
         cam_pos: glam::Vec3,
         view_proj: &glam::Mat4,
         textures: &HashMap<u64, GpuTexture>,
+        time: f64,
     ) {
         unsafe {
             gl_error!(gl);
@@ -145,6 +147,10 @@ impl BillBoardRenderer {
             );
             for (trail, trail_buffer) in self.trails.iter().zip(self.trail_buffers.iter()) {
                 if let Some(texture) = textures.get(&trail.texture) {
+                    gl.uniform_1_f32(
+                        Some(&NativeUniformLocation(3)),
+                        trail_uv_offset(time, trail.texture_speed),
+                    );
                     gl.bind_vertex_buffer(0, Some(*trail_buffer), 0, MARKER_VERTEX_STRIDE);
                     gl.bind_buffer(ARRAY_BUFFER, Some(*trail_buffer));
                     gl.bind_texture(TEXTURE_2D, Some(texture.handle));
@@ -152,23 +158,59 @@ impl BillBoardRenderer {
                     gl.draw_arrays(TRIANGLES, 0, trail.vertices.len() as _);
                 }
             }
+            // markers don't scroll
+            gl.uniform_1_f32(Some(&NativeUniformLocation(3)), 0.0);
             gl.bind_vertex_buffer(0, Some(self.vb), 0, MARKER_VERTEX_STRIDE);
 
             gl.bind_buffer(ARRAY_BUFFER, Some(self.vb));
-            for (index, mo) in self.markers.iter().enumerate() {
-                let index: u32 = index.try_into().unwrap();
-                if let Some(texture) = textures.get(&mo.texture) {
-                    gl.bind_texture(TEXTURE_2D, Some(texture.handle));
-                    gl.bind_sampler(0, Some(texture.sampler));
-                    gl.draw_arrays(TRIANGLES, index as i32 * 6, 6);
-                }
-            }
+            // markers are sorted with occluded markers first. see [Self::prepare_render_data]
+            let (occluded, draw_through) = self.occlusion_batches();
+            gl.enable(DEPTH_TEST);
+            gl.depth_func(LESS);
+            self.draw_markers(gl, occluded, textures);
+            gl.disable(DEPTH_TEST);
+            self.draw_markers(gl, draw_through, textures);
+            // screen markers are already in normalized device coordinates
+            gl.uniform_matrix_4_f32_slice(
+                Some(&NativeUniformLocation(2)),
+                false,
+                glam::Mat4::IDENTITY.to_cols_array().as_ref(),
+            );
+            self.draw_markers(gl, self.screen_batch(), textures);
             gl_error!(gl);
             gl.bind_vertex_array(None);
         }
     }
 }
 
+impl BillBoardRenderer {
+    /// The index ranges of occluded markers (depth tested) and draw through markers (drawn on top of everything).
+    /// Only valid after the markers are sorted in [Self::prepare_render_data]
+    pub fn occlusion_batches(&self) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+        let batches = MarkerBatches::new(&self.markers);
+        (batches.occluded, batches.draw_through)
+    }
+    /// The index range of [BillboardSpace::Screen] markers. Only valid after the markers are sorted in [Self::prepare_render_data]
+    pub fn screen_batch(&self) -> std::ops::Range<usize> {
+        MarkerBatches::new(&self.markers).screen
+    }
+    /// one draw call per run of markers sharing a texture. see [texture_runs]
+    unsafe fn draw_markers(
+        &self,
+        gl: &Context,
+        batch: std::ops::Range<usize>,
+        textures: &HashMap<u64, GpuTexture>,
+    ) {
+        for (texture, run) in texture_runs(&self.markers, batch) {
+            if let Some(texture) = textures.get(&texture) {
+                gl.bind_texture(TEXTURE_2D, Some(texture.handle));
+                gl.bind_sampler(0, Some(texture.sampler));
+                gl.draw_arrays(TRIANGLES, run.start as i32 * 6, run.len() as i32 * 6);
+            }
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct MarkerVertex {
@@ -188,6 +230,246 @@ pub struct MarkerObject {
     /// As markers have transparency, we need to render them from far -> near order
     /// So, we will sort them using this distance just before rendering
     pub distance: f32,
+    /// If true, the marker is depth tested and can be hidden behind other occluding markers.
+    /// Otherwise (the default for markers without an `occlude` attribute), the marker is drawn on top of everything.
+    pub occlude: bool,
+    /// The map which this marker belongs to. see [is_on_map]
+    pub map_id: u32,
+    /// Whether the [Self::vertices] are in world space or screen space
+    pub space: BillboardSpace,
+}
+
+/// world markers first, then screen markers. within world markers, occluded markers come first. so, that each mode is a single contiguous batch.
+/// then sort by depth within each batch. faded/semi transparent markers still write depth, so even occluded markers must be drawn far -> near.
+/// the texture only breaks ties between markers at the same distance, so that [texture_runs] can merge them without changing the blending.
+fn sort_markers(markers: &mut [MarkerObject]) {
+    markers.sort_unstable_by(|first, second| {
+        first
+            .space
+            .cmp(&second.space)
+            .then(second.occlude.cmp(&first.occlude))
+            .then(
+                first.distance.total_cmp(&second.distance).reverse(), // we need the farther markers (more distance from camera) to be rendered first, for correct alpha blending
+            )
+            .then(first.texture.cmp(&second.texture))
+    });
+}
+
+/// splits the `batch` range of markers into consecutive runs with the same texture. each run is a single draw call
+fn texture_runs(
+    markers: &[MarkerObject],
+    batch: std::ops::Range<usize>,
+) -> Vec<(u64, std::ops::Range<usize>)> {
+    let mut runs: Vec<(u64, std::ops::Range<usize>)> = vec![];
+    for index in batch {
+        let texture = markers[index].texture;
+        match runs.last_mut() {
+            Some((last_texture, run)) if *last_texture == texture => run.end = index + 1,
+            _ => runs.push((texture, index..index + 1)),
+        }
+    }
+    runs
+}
+
+/// index ranges of markers which are drawn with the same depth test/transform state. markers must be sorted with [sort_markers]
+#[derive(Debug, PartialEq, Eq)]
+struct MarkerBatches {
+    /// world markers with depth test
+    occluded: std::ops::Range<usize>,
+    /// world markers without depth test
+    draw_through: std::ops::Range<usize>,
+    /// screen markers
+    screen: std::ops::Range<usize>,
+}
+impl MarkerBatches {
+    fn new(markers: &[MarkerObject]) -> Self {
+        let first_screen = markers.partition_point(|mo| mo.space == BillboardSpace::World);
+        let first_draw_through = markers[..first_screen].partition_point(|mo| mo.occlude);
+        Self {
+            occluded: 0..first_draw_through,
+            draw_through: first_draw_through..first_screen,
+            screen: first_screen..markers.len(),
+        }
+    }
+}
+
+/// The coordinate space of the vertices of a [MarkerObject]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BillboardSpace {
+    /// Anchored in the game world and projected through the camera
+    #[default]
+    World,
+    /// Already in normalized device coordinates. Drawn on top of everything with a constant size. eg: markers on the compass/minimap.
+    /// use [screen_quad] to create vertices
+    Screen,
+}
+
+/// the height of the area below the compass (when it is at the bottom right corner) in pixels.
+const COMPASS_BOTTOM_OFFSET: f32 = 36.0;
+
+/// Maps continent coordinates to the compass (minimap) of gw2 in pixels relative to the top left corner of the gw2 viewport
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CompassTransform {
+    /// size of gw2 viewport in pixels
+    pub viewport_size: Vec2,
+    /// size of compass in pixels
+    pub compass_size: Vec2,
+    /// whether the compass is at the top right corner instead of bottom right corner
+    pub top_right: bool,
+    /// the continent coordinates shown at the center of the compass
+    pub map_center: Vec2,
+    /// continent units per pixel of the compass
+    pub map_scale: f32,
+    /// radians. only non-zero if the compass rotation is enabled
+    pub rotation: f32,
+}
+
+impl CompassTransform {
+    /// None if the compass is not visible
+    pub fn from_link(link: &jokolink::MumbleLink) -> Option<Self> {
+        if link.compass_width == 0
+            || link.compass_height == 0
+            || link.map_scale <= 0.0
+            || link.is_map_open()
+        {
+            return None;
+        }
+        Some(Self {
+            viewport_size: link.client_size.as_vec2(),
+            compass_size: Vec2::new(link.compass_width as f32, link.compass_height as f32),
+            top_right: link.is_compass_top_right(),
+            map_center: Vec2::new(link.map_center_x, link.map_center_y),
+            map_scale: link.map_scale,
+            rotation: if link.is_compass_rotation_enabled() {
+                link.compass_rotation
+            } else {
+                0.0
+            },
+        })
+    }
+    /// top left corner of the compass in pixels
+    pub fn compass_min(&self) -> Vec2 {
+        Vec2::new(
+            self.viewport_size.x - self.compass_size.x,
+            if self.top_right {
+                0.0
+            } else {
+                self.viewport_size.y - self.compass_size.y - COMPASS_BOTTOM_OFFSET
+            },
+        )
+    }
+    /// The position in pixels for the continent coordinates. None if the position is outside of the compass
+    pub fn continent_to_screen(&self, continent: Vec2) -> Option<Vec2> {
+        let offset = (continent - self.map_center) / self.map_scale;
+        let offset = Vec2::from_angle(-self.rotation).rotate(offset);
+        let half_size = self.compass_size / 2.0;
+        if offset.abs().cmpgt(half_size).any() {
+            return None;
+        }
+        Some(self.compass_min() + half_size + offset)
+    }
+}
+
+/// A quad for a [BillboardSpace::Screen] marker with its center at `center` and `half_size` in pixels inside a viewport of `viewport_size` pixels.
+pub fn screen_quad(
+    center: Vec2,
+    half_size: f32,
+    viewport_size: Vec2,
+    alpha: f32,
+    color: [u8; 4],
+) -> [MarkerVertex; 6] {
+    let vertex = |offset: Vec2, texture_coordinates: Vec2| {
+        let pixel = center + offset * half_size;
+        MarkerVertex {
+            // y is down in pixels, but up in ndc
+            position: Vec3::new(
+                pixel.x / viewport_size.x * 2.0 - 1.0,
+                1.0 - pixel.y / viewport_size.y * 2.0,
+                0.0,
+            ),
+            alpha,
+            texture_coordinates,
+            // no fading for screen markers
+            fade_near_far: Vec2::splat(-1.0),
+            color,
+        }
+    };
+    let top_left = vertex(Vec2::new(-1.0, -1.0), Vec2::new(0.0, 0.0));
+    let bottom_left = vertex(Vec2::new(-1.0, 1.0), Vec2::new(0.0, 1.0));
+    let bottom_right = vertex(Vec2::new(1.0, 1.0), Vec2::new(1.0, 1.0));
+    let top_right = vertex(Vec2::new(1.0, -1.0), Vec2::new(1.0, 0.0));
+    [
+        top_left,
+        bottom_left,
+        bottom_right,
+        bottom_right,
+        top_right,
+        top_left,
+    ]
+}
+
+/// The offset added to the texture V coordinate of a trail at `time` (seconds). Only the fractional part matters as the texture repeats along the trail.
+/// we take the fract in f64, so that the offset doesn't lose precision when the time gets big
+pub fn trail_uv_offset(time: f64, texture_speed: f32) -> f32 {
+    (time * texture_speed as f64).fract() as f32
+}
+
+/// The index of the frame of an animated icon with `frame_count` frames at `time` (seconds), when `fps` frames are shown per second.
+/// The animation loops. If `fps` is not positive, the icon stays on the first frame
+pub fn animation_frame(time: f64, fps: f32, frame_count: usize) -> usize {
+    if frame_count == 0 || !fps.is_finite() || fps <= 0.0 {
+        return 0;
+    }
+    ((time.max(0.0) * fps as f64) as u64 % frame_count as u64) as usize
+}
+
+/// Whether an object of `object_map_id` should be rendered when we are on `current_map_id`.
+/// zero means the map is unknown, and we don't cull those objects.
+pub fn is_on_map(object_map_id: u32, current_map_id: u32) -> bool {
+    object_map_id == 0 || current_map_id == 0 || object_map_id == current_map_id
+}
+
+/// The offset (half width i.e. distance from center of the marker to its side) in world units, for a marker at `camera_distance`.
+/// The marker is projected onto the near plane to get its size in pixels, which is clamped between `min_pixel_size` and `max_pixel_size`
+/// (and half of the `viewport_width`), and then projected back. So, far away markers don't become tiny and nearby markers don't become huge.
+pub fn clamped_side_offset(
+    icon_size: f32,
+    camera_distance: f32,
+    z_near: f32,
+    viewport_width: f32,
+    min_pixel_size: f32,
+    max_pixel_size: f32,
+) -> f32 {
+    // markers are 1 meter in width/height by default
+    const SIDE_OFFSET_FAR: f32 = 1.0;
+    // the size of the projected on to the near plane
+    let near_offset = SIDE_OFFSET_FAR * icon_size * (z_near / camera_distance);
+    // convert the near_plane width offset into pixels by multiplying the near_ffset with gw2 window width
+    let near_offset_in_pixels = near_offset * viewport_width;
+    // not using clamp, as packs might have min size bigger than max size and clamp panics in that case
+    let near_offset_in_pixels = near_offset_in_pixels
+        .max(min_pixel_size)
+        .min(max_pixel_size)
+        .min(viewport_width / 2.0);
+    let near_offset_of_marker = near_offset_in_pixels / viewport_width;
+    near_offset_of_marker * camera_distance / z_near
+}
+
+/// The multiplier for the alpha of a marker/trail at `distance`. mirrors the fading in `marker.vs` shader.
+/// The alpha fades linearly from 1.0 at `fade_near` to 0.0 at `fade_far`. Negative values mean the attribute is not set.
+/// If only `fade_near` is set, there's nothing to fade towards, so the alpha stays 1.0. If only `fade_far` is set, the marker just disappears beyond it.
+pub fn fade_alpha(distance: f32, fade_near_far: Vec2) -> f32 {
+    let Vec2 {
+        x: fade_near,
+        y: fade_far,
+    } = fade_near_far;
+    if fade_far > 0.0 && distance >= fade_far {
+        return 0.0;
+    }
+    if fade_near > 0.0 && fade_far > fade_near && distance > fade_near {
+        return 1.0 - (distance - fade_near) / (fade_far - fade_near);
+    }
+    1.0
 }
 
 /// takes in strings containing vertex/fragment shaders and returns a Shaderprogram with them attached
@@ -281,3 +563,228 @@ unsafe fn create_marker_buffer(gl: &Context) -> NativeBuffer {
     gl_error!(gl);
     vb
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn side_offset_is_clamped_in_pixels() {
+        let pixels = |distance: f32| {
+            clamped_side_offset(1.0, distance, 1.0, 1000.0, 5.0, 100.0) / distance * 1000.0
+        };
+        // 1 meter at 5 meters distance would be 200 pixels
+        assert_eq!(pixels(5.0), 100.0);
+        // 1 meter at 1000 meters distance would be 1 pixel
+        assert_eq!(pixels(1000.0), 5.0);
+        // within the range, the marker keeps its world size
+        assert_eq!(clamped_side_offset(1.0, 50.0, 1.0, 1000.0, 5.0, 100.0), 1.0);
+    }
+
+    #[test]
+    fn size_clamp_keeps_distance_scaling_in_between() {
+        let pixels = |icon_size: f32, distance: f32, min_size: f32, max_size: f32| {
+            clamped_side_offset(icon_size, distance, 1.0, 1000.0, min_size, max_size) / distance
+                * 1000.0
+        };
+        // the icon size scales the distance based size before it is clamped
+        assert_eq!(pixels(2.0, 50.0, 5.0, 100.0), 40.0);
+        assert_eq!(pixels(2.0, 10.0, 5.0, 100.0), 100.0);
+        // the size shrinks with distance until it reaches the min size
+        assert_eq!(
+            [10.0, 20.0, 100.0, 500.0, 1000.0].map(|distance| pixels(1.0, distance, 5.0, 100.0)),
+            [100.0, 50.0, 10.0, 5.0, 5.0]
+        );
+        // packs with a min size bigger than the max size get the max size
+        assert_eq!(pixels(1.0, 1000.0, 50.0, 10.0), 10.0);
+        // never bigger than half of the viewport
+        assert_eq!(pixels(1.0, 1.0, 5.0, 2048.0), 500.0);
+    }
+
+    #[test]
+    fn compass_screen_transform() {
+        let compass = CompassTransform {
+            viewport_size: Vec2::new(1920.0, 1080.0),
+            compass_size: Vec2::new(300.0, 300.0),
+            top_right: false,
+            map_center: Vec2::new(1000.0, 1000.0),
+            map_scale: 2.0,
+            rotation: 0.0,
+        };
+        assert_eq!(compass.compass_min(), Vec2::new(1620.0, 744.0));
+        assert_eq!(
+            compass.continent_to_screen(Vec2::new(1000.0, 1000.0)),
+            Some(Vec2::new(1770.0, 894.0))
+        );
+        assert_eq!(
+            compass.continent_to_screen(Vec2::new(1200.0, 1000.0)),
+            Some(Vec2::new(1870.0, 894.0))
+        );
+        assert_eq!(compass.continent_to_screen(Vec2::new(1400.0, 1000.0)), None);
+        let top_right = CompassTransform {
+            top_right: true,
+            ..compass
+        };
+        assert_eq!(top_right.compass_min(), Vec2::new(1620.0, 0.0));
+        let quad = screen_quad(
+            Vec2::new(960.0, 540.0),
+            10.0,
+            compass.viewport_size,
+            1.0,
+            [0; 4],
+        );
+        assert!(quad[0]
+            .position
+            .abs_diff_eq(Vec3::new(-10.0 / 960.0, 10.0 / 540.0, 0.0), 1e-6));
+    }
+
+    #[test]
+    fn occluded_and_draw_through_markers_are_separate_batches() {
+        let marker = |occlude: bool, distance: f32, space: BillboardSpace| MarkerObject {
+            vertices: bytemuck::Zeroable::zeroed(),
+            texture: 0,
+            distance,
+            occlude,
+            map_id: 0,
+            space,
+        };
+        let mut markers = vec![
+            marker(false, 10.0, BillboardSpace::World),
+            marker(true, 5.0, BillboardSpace::World),
+            marker(false, 0.0, BillboardSpace::Screen),
+            marker(true, 20.0, BillboardSpace::World),
+        ];
+        sort_markers(&mut markers);
+        let batches = MarkerBatches::new(&markers);
+        assert_eq!(
+            batches,
+            MarkerBatches {
+                occluded: 0..2,
+                draw_through: 2..3,
+                screen: 3..4,
+            }
+        );
+        // farther markers first
+        assert_eq!(markers[0].distance, 20.0);
+    }
+
+    #[test]
+    fn markers_are_drawn_with_a_draw_call_per_texture() {
+        // synthetic scene of 2000 markers sharing 4 icons. nearby markers (eg: a cluster of nodes) often share an icon
+        // the workspace has no bench harness and timing draw calls needs a gl context, so the draw call count is what we measure
+        let mut markers: Vec<MarkerObject> = (0..2000)
+            .map(|index| MarkerObject {
+                vertices: bytemuck::Zeroable::zeroed(),
+                texture: (index / 50) % 4,
+                distance: index as f32,
+                occlude: index % 2 == 0,
+                map_id: 0,
+                space: BillboardSpace::World,
+            })
+            .collect();
+        // before batching, every marker was its own draw call
+        let before = markers.len();
+        sort_markers(&mut markers);
+        let batches = MarkerBatches::new(&markers);
+        for batch in [batches.occluded.clone(), batches.draw_through.clone()] {
+            // depth order is kept inside the batch, batching never reorders markers
+            assert!(markers[batch.clone()]
+                .windows(2)
+                .all(|w| w[0].distance > w[1].distance));
+            let runs = texture_runs(&markers, batch.clone());
+            // every 25 markers of a batch share a texture
+            assert_eq!(runs.len(), 40);
+            assert_eq!(runs[0], (3, batch.start..batch.start + 25));
+            for (texture, run) in runs.iter() {
+                assert!(markers[run.clone()].iter().all(|m| m.texture == *texture));
+            }
+            // neighbouring runs are always merged
+            assert!(runs.windows(2).all(|w| w[0].0 != w[1].0));
+            assert_eq!(runs.last().unwrap().1.end, batch.end);
+        }
+        let after = texture_runs(&markers, batches.occluded).len()
+            + texture_runs(&markers, batches.draw_through).len();
+        assert!(after < before);
+    }
+
+    #[test]
+    fn interleaved_textures_keep_far_to_near_order() {
+        // every marker uses a different icon than its neighbours, and some markers share a distance
+        let mut markers: Vec<MarkerObject> = (0..2000)
+            .map(|index| MarkerObject {
+                vertices: bytemuck::Zeroable::zeroed(),
+                texture: index % 4,
+                distance: (index / 4) as f32 * 0.1,
+                occlude: index % 2 == 0,
+                map_id: 0,
+                space: BillboardSpace::World,
+            })
+            .collect();
+        sort_markers(&mut markers);
+        let batches = MarkerBatches::new(&markers);
+        assert_eq!(batches.occluded, 0..1000);
+        for batch in [batches.occluded.clone(), batches.draw_through.clone()] {
+            // grouping by texture never draws a nearer marker before a farther one
+            assert!(markers[batch.clone()]
+                .windows(2)
+                .all(|w| w[0].distance >= w[1].distance));
+            let runs = texture_runs(&markers, batch.clone());
+            // each distance has two markers of this batch with different textures, so nothing can be merged
+            assert_eq!(runs.len(), 1000);
+            assert_eq!(
+                runs.iter().map(|(_, run)| run.len()).sum::<usize>(),
+                batch.len()
+            );
+        }
+    }
+
+    #[test]
+    fn trail_uv_offset_scrolls_with_time() {
+        assert_eq!(trail_uv_offset(10.0, 0.0), 0.0);
+        assert_eq!(trail_uv_offset(0.25, 1.0), 0.25);
+        assert_eq!(trail_uv_offset(2.25, 1.0), 0.25);
+        assert_eq!(trail_uv_offset(1.0, 0.5), 0.5);
+        assert_eq!(trail_uv_offset(1.0, -0.25), -0.25);
+        // large times keep their precision
+        assert_eq!(trail_uv_offset(1_000_000.5, 1.0), 0.5);
+    }
+
+    #[test]
+    fn animation_frame_loops_with_time() {
+        assert_eq!(animation_frame(0.0, 4.0, 3), 0);
+        assert_eq!(animation_frame(0.24, 4.0, 3), 0);
+        assert_eq!(animation_frame(0.25, 4.0, 3), 1);
+        assert_eq!(animation_frame(0.5, 4.0, 3), 2);
+        assert_eq!(animation_frame(0.75, 4.0, 3), 0);
+        assert_eq!(animation_frame(10.0, 0.5, 3), 2);
+        // static icons
+        assert_eq!(animation_frame(10.0, 0.0, 3), 0);
+        assert_eq!(animation_frame(10.0, -1.0, 3), 0);
+        assert_eq!(animation_frame(10.0, f32::NAN, 3), 0);
+        assert_eq!(animation_frame(10.0, 4.0, 1), 0);
+        assert_eq!(animation_frame(10.0, 4.0, 0), 0);
+    }
+
+    #[test]
+    fn off_map_objects_are_culled() {
+        assert!(is_on_map(15, 15));
+        assert!(!is_on_map(15, 50));
+        assert!(is_on_map(0, 50));
+        assert!(is_on_map(15, 0));
+    }
+
+    #[test]
+    fn fade_alpha_across_fade_band() {
+        let band = Vec2::new(10.0, 20.0);
+        assert_eq!(fade_alpha(5.0, band), 1.0);
+        assert_eq!(fade_alpha(10.0, band), 1.0);
+        assert_eq!(fade_alpha(15.0, band), 0.5);
+        assert_eq!(fade_alpha(20.0, band), 0.0);
+        assert_eq!(fade_alpha(25.0, band), 0.0);
+        // only fade near is set
+        assert_eq!(fade_alpha(1000.0, Vec2::new(10.0, -1.0)), 1.0);
+        // only fade far is set
+        assert_eq!(fade_alpha(19.0, Vec2::new(-1.0, 20.0)), 1.0);
+        assert_eq!(fade_alpha(20.0, Vec2::new(-1.0, 20.0)), 0.0);
+    }
+}
@@ -1,3 +1,4 @@
+pub mod atlas;
 pub mod billboard;
 use billboard::BillBoardRenderer;
 use billboard::MarkerObject;
@@ -14,7 +15,7 @@ use egui_render_three_d::ThreeDBackend;
 use egui_render_three_d::ThreeDConfig;
 use egui_window_glfw_passthrough::GlfwBackend;
 use glam::Mat4;
-use jokolink::MumbleLink;
+use jokolink::{MumbleChanges, MumbleLink};
 use raw_window_handle::HasRawWindowHandle;
 use std::sync::Arc;
 use three_d::prelude::*;
@@ -29,12 +30,29 @@ macro_rules! gl_error {
     }};
 }
 
+/// default distance of the near clipping plane in meters
+pub const DEFAULT_Z_NEAR: f32 = 1.0;
+/// default distance of the far clipping plane in meters
+pub const DEFAULT_Z_FAR: f32 = 5000.0;
+/// the near clipping plane is clamped to at least this distance in meters, as a zero near plane breaks the depth buffer
+pub const MIN_Z_NEAR: f32 = 0.01;
+
 pub struct JokoRenderer {
     pub view_proj: Mat4,
     pub cam_pos: glam::Vec3,
     pub camera: Camera,
     pub viewport: Viewport,
     pub link: Option<Arc<MumbleLink>>,
+    /// near clipping plane. see [Self::set_clip_planes]
+    z_near: f32,
+    /// far clipping plane. see [Self::set_clip_planes]
+    z_far: f32,
+    /// time of the latest tick in seconds. used to animate trails
+    pub time: f64,
+    /// while hidden, markers and trails are dropped and egui draws nothing. see [Self::set_hidden]
+    hidden: bool,
+    /// see [billboard_size_scale]. only updated when the dpi of the link changes
+    billboard_size_scale: f32,
     pub billboard_renderer: BillBoardRenderer,
     pub gl: egui_render_three_d::ThreeDBackend,
 }
@@ -69,51 +87,94 @@ impl JokoRenderer {
                 [0.0, 0.0, 0.0].into(),
                 Vector3::unit_y(),
                 Deg(90.0),
-                1.0,
-                5000.0,
+                DEFAULT_Z_NEAR,
+                DEFAULT_Z_FAR,
             ),
+            z_near: DEFAULT_Z_NEAR,
+            z_far: DEFAULT_Z_FAR,
             link: Default::default(),
+            time: 0.0,
+            hidden: false,
+            billboard_size_scale: 1.0,
             gl: backend,
             billboard_renderer,
             cam_pos: Default::default(),
         }
     }
     pub fn get_z_near(&self) -> f32 {
-        1.0
+        self.z_near
     }
     pub fn get_z_far(&self) -> f32 {
-        1000.0
+        self.z_far
     }
-    pub fn tick(&mut self, link: Option<Arc<MumbleLink>>) {
-        if let Some(link) = link.as_ref() {
-            let center = link.cam_pos + link.f_camera_front;
-            let camera = Camera::new_perspective(
-                self.viewport,
-                link.cam_pos.to_array().into(),
-                center.to_array().into(),
-                Vector3::unit_y(),
-                Rad(link.fov),
-                self.get_z_near(),
-                self.get_z_far(),
+    /// Changes the clipping planes used for the camera from the next tick. eg: a bigger far plane for large maps with distant markers
+    /// Invalid planes are clamped with [clamp_clip_planes] instead.
+    pub fn set_clip_planes(&mut self, z_near: f32, z_far: f32) {
+        let (clamped_near, clamped_far) = clamp_clip_planes(z_near, z_far);
+        if (clamped_near, clamped_far) != (z_near, z_far) {
+            tracing::warn!(
+                z_near,
+                z_far,
+                clamped_near,
+                clamped_far,
+                "invalid clip planes were clamped"
             );
+        }
+        self.z_near = clamped_near;
+        self.z_far = clamped_far;
+    }
+    /// the multiplier for the pixel sizes of markers (like `minSize`/`maxSize`). see [billboard_size_scale]
+    pub fn get_billboard_size_scale(&self) -> f32 {
+        self.billboard_size_scale
+    }
+    pub fn tick(&mut self, link: Option<Arc<MumbleLink>>, latest_time: f64) {
+        self.time = latest_time;
+        if let Some(link) = link.as_ref() {
+            if self.link.is_none() || link.changes.contains(MumbleChanges::Dpi) {
+                let scale = billboard_size_scale(link);
+                if scale != self.billboard_size_scale {
+                    tracing::info!(
+                        scale,
+                        link.dpi,
+                        link.dpi_scaling,
+                        "billboard size scale changed"
+                    );
+                    self.billboard_size_scale = scale;
+                }
+            }
+            let (camera, view_proj) =
+                link_camera(link, self.viewport, self.get_z_near(), self.get_z_far());
             self.camera = camera;
-            let view = Mat4::look_at_lh(link.cam_pos, center, glam::Vec3::Y);
-            let proj = Mat4::perspective_lh(
-                link.fov,
-                self.viewport.aspect(),
-                self.get_z_near(),
-                self.get_z_far(),
-            );
-            self.view_proj = proj * view;
+            self.view_proj = view_proj;
             self.cam_pos = link.cam_pos;
         }
         self.link = link;
     }
+    /// hides everything we draw, while the window itself stays. eg: for screenshots
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+    /// map id of the latest link. zero if there's no link
+    fn current_map_id(&self) -> u32 {
+        self.link
+            .as_ref()
+            .map(|link| link.map_id)
+            .unwrap_or_default()
+    }
+    /// markers of other maps are dropped
     pub fn add_billboard(&mut self, marker_object: MarkerObject) {
-        self.billboard_renderer.markers.push(marker_object);
+        if !self.hidden && billboard::is_on_map(marker_object.map_id, self.current_map_id()) {
+            self.billboard_renderer.markers.push(marker_object);
+        }
     }
+    /// trails of other maps are dropped
     pub fn add_trail(&mut self, trail_object: TrailObject) {
-        self.billboard_renderer.trails.push(trail_object);
+        if !self.hidden && billboard::is_on_map(trail_object.map_id, self.current_map_id()) {
+            self.billboard_renderer.trails.push(trail_object);
+        }
     }
     pub fn prepare_frame(&mut self, latest_framebuffer_size_getter: impl FnMut() -> [u32; 2]) {
         self.billboard_renderer.prepare_frame();
@@ -140,7 +201,7 @@ impl JokoRenderer {
         textures_delta: egui::TexturesDelta,
         logical_screen_size: [f32; 2],
     ) {
-        if let Some(link) = self.link.as_ref() {
+        if let Some(link) = should_render_billboards(self.hidden, self.link.as_deref()) {
             self.billboard_renderer
                 .prepare_render_data(link, &self.gl.context);
             self.billboard_renderer.render(
@@ -148,14 +209,48 @@ impl JokoRenderer {
                 self.cam_pos,
                 &self.view_proj,
                 &self.gl.glow_backend.painter.managed_textures,
+                self.time,
             );
         }
+        // while hidden, we still hand over the texture changes, or egui's textures would be out of sync when we show again
+        let meshes = if self.hidden { vec![] } else { meshes };
         self.gl
             .render_egui(meshes, textures_delta, logical_screen_size);
     }
 
     pub fn present(&mut self) {}
 
+    /// Reads back the current viewport of the framebuffer with alpha. Useful for bug reports or visual tests.
+    /// must be called after [Self::render_egui] (and [Self::present]), but before swapping buffers.
+    ///
+    /// The renderer needs a glfw window with a gl context, and CI runners have no display to create one, so there is no test which reads back a real frame.
+    /// The conversion of the read pixels is tested with [image_from_gl_pixels] instead.
+    #[cfg(feature = "capture")]
+    pub fn capture_frame(&self) -> image::RgbaImage {
+        use egui_render_three_d::three_d::context::{
+            PixelPackData, PACK_ALIGNMENT, RGBA, UNSIGNED_BYTE,
+        };
+        let width = self.viewport.width;
+        let height = self.viewport.height;
+        let mut pixels = vec![0u8; width as usize * height as usize * 4];
+        unsafe {
+            let gl = &self.gl.context;
+            gl_error!(gl);
+            gl.pixel_store_i32(PACK_ALIGNMENT, 1);
+            gl.read_pixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                RGBA,
+                UNSIGNED_BYTE,
+                PixelPackData::Slice(&mut pixels),
+            );
+            gl_error!(gl);
+        }
+        image_from_gl_pixels(&pixels, width, height)
+    }
+
     pub fn resize_framebuffer(&mut self, latest_size: [u32; 2]) {
         tracing::info!(?latest_size, "resizing framebuffer");
 
@@ -168,3 +263,176 @@ impl JokoRenderer {
         self.gl.resize_framebuffer(latest_size);
     }
 }
+
+/// the link used to prepare and render the markers and trails. None if there's no link or if we are hidden
+fn should_render_billboards(hidden: bool, link: Option<&MumbleLink>) -> Option<&MumbleLink> {
+    link.filter(|_| !hidden)
+}
+
+/// Pixel sizes of markers are meant for gw2's ui pixels. When gw2 scales its ui with the dpi (dpi scaling is enabled),
+/// we scale the markers by the same factor, so that they keep the same size relative to the ui when moving between monitors.
+/// The viewport itself is always in physical pixels.
+pub fn billboard_size_scale(link: &MumbleLink) -> f32 {
+    if link.dpi_scaling > 0 {
+        link.dpi_scale_factor()
+    } else {
+        1.0
+    }
+}
+
+/// converts the tightly packed rgba pixels of `glReadPixels` into an image
+#[cfg(feature = "capture")]
+fn image_from_gl_pixels(pixels: &[u8], width: u32, height: u32) -> image::RgbaImage {
+    image::RgbaImage::from_raw(width, height, flip_rows(pixels, width as usize * 4))
+        .expect("pixel buffer size must match the viewport size")
+}
+
+/// gl rows start from the bottom, but image rows start from the top. `row_len` is in bytes
+#[cfg_attr(not(feature = "capture"), allow(unused))]
+fn flip_rows(pixels: &[u8], row_len: usize) -> Vec<u8> {
+    pixels
+        .chunks_exact(row_len)
+        .rev()
+        .flatten()
+        .copied()
+        .collect()
+}
+
+/// makes sure that `0.0 < z_near < z_far` and that both are finite, by falling back to [MIN_Z_NEAR] and the defaults.
+pub fn clamp_clip_planes(z_near: f32, z_far: f32) -> (f32, f32) {
+    let z_near = if z_near.is_finite() {
+        z_near.max(MIN_Z_NEAR)
+    } else {
+        DEFAULT_Z_NEAR
+    };
+    let z_far = if z_far.is_finite() && z_far > z_near {
+        z_far
+    } else {
+        DEFAULT_Z_FAR.max(z_near * 2.0)
+    };
+    (z_near, z_far)
+}
+
+/// the camera of the link for egui/three_d and the view projection matrix for our billboards, both with the same clip planes
+fn link_camera(link: &MumbleLink, viewport: Viewport, z_near: f32, z_far: f32) -> (Camera, Mat4) {
+    let center = link.cam_pos + link.f_camera_front;
+    let camera = Camera::new_perspective(
+        viewport,
+        link.cam_pos.to_array().into(),
+        center.to_array().into(),
+        Vector3::unit_y(),
+        Rad(link.fov),
+        z_near,
+        z_far,
+    );
+    let view = Mat4::look_at_lh(link.cam_pos, center, glam::Vec3::Y);
+    let proj = perspective(link.fov, viewport.aspect(), z_near, z_far);
+    (camera, proj * view)
+}
+
+/// left handed perspective projection, as gw2 uses left handed coordinates. The depth goes from 0.0 at `z_near` to 1.0 at `z_far`
+pub fn perspective(fov: f32, aspect: f32, z_near: f32, z_far: f32) -> Mat4 {
+    Mat4::perspective_lh(fov, aspect, z_near, z_far)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flip_rows_reverses_row_order() {
+        assert_eq!(flip_rows(&[1, 2, 3, 4, 5, 6], 2), vec![5, 6, 3, 4, 1, 2]);
+    }
+
+    #[cfg(feature = "capture")]
+    #[test]
+    fn gl_pixels_become_a_top_down_image() {
+        let red = [255, 0, 0, 255];
+        let blue = [0, 0, 255, 128];
+        // the bottom row comes first in gl
+        let pixels = [red, red, red, blue, blue, blue].concat();
+        let image = image_from_gl_pixels(&pixels, 3, 2);
+        assert_eq!(image.dimensions(), (3, 2));
+        assert_eq!(image.get_pixel(0, 0).0, blue);
+        assert_eq!(image.get_pixel(2, 1).0, red);
+    }
+
+    #[test]
+    fn hidden_overlay_skips_billboards() {
+        let link = MumbleLink::default();
+        assert!(should_render_billboards(false, Some(&link)).is_some());
+        assert!(should_render_billboards(true, Some(&link)).is_none());
+        assert!(should_render_billboards(false, None).is_none());
+    }
+
+    #[test]
+    fn billboard_size_scale_follows_dpi() {
+        let mut link = MumbleLink {
+            dpi: 96,
+            dpi_scaling: 1,
+            ..Default::default()
+        };
+        assert_eq!(billboard_size_scale(&link), 1.0);
+        let previous = link.clone();
+        link.dpi = 192;
+        assert!(link.changes_from(&previous).contains(MumbleChanges::Dpi));
+        assert_eq!(billboard_size_scale(&link), 2.0);
+        // without dpi scaling, gw2 ui doesn't grow with the dpi
+        link.dpi_scaling = 0;
+        assert_eq!(billboard_size_scale(&link), 1.0);
+    }
+
+    #[test]
+    fn invalid_clip_planes_are_clamped() {
+        assert_eq!(clamp_clip_planes(2.0, 10000.0), (2.0, 10000.0));
+        assert_eq!(clamp_clip_planes(0.0, 10000.0), (MIN_Z_NEAR, 10000.0));
+        assert_eq!(clamp_clip_planes(-1.0, 10000.0), (MIN_Z_NEAR, 10000.0));
+        assert_eq!(
+            clamp_clip_planes(f32::NAN, 10000.0),
+            (DEFAULT_Z_NEAR, 10000.0)
+        );
+        assert_eq!(clamp_clip_planes(2.0, 1.0), (2.0, DEFAULT_Z_FAR));
+        assert_eq!(clamp_clip_planes(2.0, f32::INFINITY), (2.0, DEFAULT_Z_FAR));
+        assert_eq!(clamp_clip_planes(8000.0, 10.0), (8000.0, 16000.0));
+    }
+
+    #[test]
+    fn link_camera_uses_the_clip_planes() {
+        let link = MumbleLink {
+            cam_pos: glam::Vec3::new(10.0, 5.0, 0.0),
+            f_camera_front: glam::Vec3::Z,
+            fov: 1.0,
+            ..Default::default()
+        };
+        let viewport = Viewport::new_at_origo(1920, 1080);
+        let (camera, view_proj) = link_camera(&link, viewport, 2.0, DEFAULT_Z_FAR);
+        assert_eq!(camera.z_near(), 2.0);
+        assert_eq!(camera.z_far(), DEFAULT_Z_FAR);
+        for (distance, billboard_depth, camera_depth) in
+            [(2.0, 0.0, -1.0), (DEFAULT_Z_FAR, 1.0, 1.0)]
+        {
+            let point = link.cam_pos + glam::Vec3::Z * distance;
+            // our billboards use a 0..1 depth range
+            let depth = view_proj.project_point3(point).z;
+            assert!((depth - billboard_depth).abs() < 1e-4, "{distance} {depth}");
+            // three_d uses the -1..1 depth range of gl
+            let clip =
+                camera.projection() * camera.view() * three_d::vec4(point.x, point.y, point.z, 1.0);
+            let depth = clip.z / clip.w;
+            assert!((depth - camera_depth).abs() < 1e-4, "{distance} {depth}");
+        }
+        // markers beyond the far plane are clipped
+        let beyond =
+            view_proj.project_point3(link.cam_pos + glam::Vec3::Z * (DEFAULT_Z_FAR + 100.0));
+        assert!(beyond.z > 1.0);
+    }
+
+    #[test]
+    fn projection_uses_custom_clip_planes() {
+        let proj = perspective(1.0, 16.0 / 9.0, 2.0, 10000.0);
+        let near = proj.project_point3(glam::Vec3::new(0.0, 0.0, 2.0));
+        let far = proj.project_point3(glam::Vec3::new(0.0, 0.0, 10000.0));
+        assert!((near.z - 0.0).abs() < 1e-5);
+        assert!((far.z - 1.0).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,264 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{end_point::AUTHORIZATION_HEADER_NAME, prelude::*};
+
+/// gw2 api allows a burst of this many requests. `X-Rate-Limit-Limit` header overrides this
+const DEFAULT_RATE_LIMIT: f64 = 300.0;
+/// gw2 api refills 5 requests per second
+const RATE_LIMIT_REFILL_PER_SECOND: f64 = 5.0;
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_MAX_CACHE_ENTRIES: usize = 256;
+pub(crate) const RATE_LIMIT_HEADER_NAME: &str = "X-Rate-Limit-Limit";
+
+/// A blocking client for the gw2 api which caches the responses by url and waits when we run out of requests.
+/// It is `Send + Sync`, so it can be shared with background threads.
+pub struct ApiClient {
+    agent: HttpClient,
+    /// the urls of [EndPoint]s start with `https://api.guildwars2.com`. we replace that with this. useful for tests
+    base_url: String,
+    api_key: String,
+    /// how long a cached response is used for. expired responses are removed whenever a new response is cached
+    pub cache_ttl: Duration,
+    /// when the cache is full, the least recently used response is removed to make space for a new one
+    pub max_cache_entries: usize,
+    /// response bodies by url
+    cache: Mutex<HashMap<String, CachedResponse>>,
+    rate_limit: Mutex<TokenBucket>,
+}
+
+#[derive(Debug)]
+struct CachedResponse {
+    /// when we got the response
+    fetched: Instant,
+    /// when the response was last returned by [ApiClient::get_json]
+    last_used: Instant,
+    body: String,
+}
+
+/// The token bucket used by gw2 api for rate limiting
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+impl TokenBucket {
+    fn new(capacity: f64) -> Self {
+        Self {
+            capacity,
+            tokens: capacity,
+            last_refill: Instant::now(),
+        }
+    }
+    /// takes a token and returns how long to wait before making the request
+    fn take(&mut self, now: Instant) -> Duration {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * RATE_LIMIT_REFILL_PER_SECOND).min(self.capacity);
+        self.last_refill = now;
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / RATE_LIMIT_REFILL_PER_SECOND)
+        }
+    }
+    fn set_capacity(&mut self, capacity: f64) {
+        if capacity > 0.0 && capacity != self.capacity {
+            self.tokens = self.tokens.min(capacity);
+            self.capacity = capacity;
+        }
+    }
+}
+
+impl ApiClient {
+    /// use an empty api key if you only need the endpoints without authentication
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self::with_base_url(API_BASE_URL, api_key)
+    }
+    pub fn with_base_url(base_url: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            agent: ureq::agent(),
+            base_url: base_url.into(),
+            api_key: api_key.into(),
+            cache_ttl: DEFAULT_CACHE_TTL,
+            max_cache_entries: DEFAULT_MAX_CACHE_ENTRIES,
+            cache: Default::default(),
+            rate_limit: Mutex::new(TokenBucket::new(DEFAULT_RATE_LIMIT)),
+        }
+    }
+    /// the api key used for the endpoints which need authentication. empty if we don't have one
+    pub fn api_key(&self) -> &str {
+        &self.api_key
+    }
+    /// makes a GET request to `url` (like [EndPoint::URL]) unless we have a cached response for it which is younger than [Self::cache_ttl].
+    pub fn get_json<T: DeserializeOwned>(&self, url: &str, auth: bool) -> Result<T> {
+        let url = match url.strip_prefix(API_BASE_URL) {
+            Some(path) => format!("{}{path}", self.base_url),
+            None => url.to_string(),
+        };
+        let cached = self
+            .cache
+            .lock()
+            .unwrap()
+            .get_mut(&url)
+            .filter(|cached| cached.fetched.elapsed() < self.cache_ttl)
+            .map(|cached| {
+                cached.last_used = Instant::now();
+                cached.body.clone()
+            });
+        let body = match cached {
+            Some(body) => body,
+            None => {
+                let wait = self.rate_limit.lock().unwrap().take(Instant::now());
+                if !wait.is_zero() {
+                    std::thread::sleep(wait);
+                }
+                let req = self.agent.get(&url);
+                let req = if auth {
+                    req.set(
+                        AUTHORIZATION_HEADER_NAME,
+                        &format!("Bearer {}", self.api_key),
+                    )
+                } else {
+                    req
+                };
+                let response = req
+                    .call()
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("failed to get {url}"))?;
+                if let Some(limit) = response
+                    .header(RATE_LIMIT_HEADER_NAME)
+                    .and_then(|limit| limit.parse().ok())
+                {
+                    self.rate_limit.lock().unwrap().set_capacity(limit);
+                }
+                let body = response
+                    .into_string()
+                    .into_diagnostic()
+                    .wrap_err("failed to read response body")?;
+                self.cache_response(url.clone(), body.clone());
+                body
+            }
+        };
+        serde_json::from_str(&body)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to deserialize response of {url}"))
+    }
+    /// the list of ids of an [EndPoint]
+    pub fn get_ids<E: EndPoint>(&self) -> Result<Vec<E::Id>> {
+        self.get_json(E::URL, E::AUTH)
+    }
+    pub fn get_id<E: EndPoint>(&self, id: &E::Id) -> Result<E> {
+        self.get_json(&format!("{}/{id}", E::URL), E::AUTH)
+    }
+    /// removes the expired responses and the least recently used ones over [Self::max_cache_entries] before inserting `body`
+    fn cache_response(&self, url: String, body: String) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|_, cached| cached.fetched.elapsed() < self.cache_ttl);
+        while !cache.is_empty() && cache.len() >= self.max_cache_entries {
+            let least_recently_used = cache
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_used)
+                .map(|(url, _)| url.clone())
+                .unwrap();
+            cache.remove(&least_recently_used);
+        }
+        if self.max_cache_entries == 0 {
+            return;
+        }
+        let now = Instant::now();
+        cache.insert(
+            url,
+            CachedResponse {
+                fetched: now,
+                last_used: now,
+                body,
+            },
+        );
+    }
+    /// removes all the cached responses
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+    use crate::{end_point::worlds::World, mock::mock_server};
+
+    #[test]
+    fn cached_responses_avoid_a_second_request() {
+        let (base_url, requests) = mock_server(|_| "[1001,1002]".to_string());
+        let client = ApiClient::with_base_url(base_url, "");
+        assert_eq!(client.get_ids::<World>().unwrap(), [1001, 1002]);
+        assert_eq!(client.get_ids::<World>().unwrap(), [1001, 1002]);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert_eq!(client.rate_limit.lock().unwrap().capacity, 600.0);
+
+        client.clear_cache();
+        client.get_ids::<World>().unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn least_recently_used_response_is_evicted() {
+        let (base_url, requests) = mock_server(|_| "[1001,1002]".to_string());
+        let mut client = ApiClient::with_base_url(base_url, "");
+        client.max_cache_entries = 2;
+        let get = |id: u32| {
+            client
+                .get_json::<Vec<u32>>(&format!("{API_BASE_V2_URL}/worlds?page={id}"), false)
+                .unwrap();
+        };
+        get(1);
+        get(2);
+        // 1 is now used more recently than 2
+        get(1);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        get(3);
+        assert_eq!(client.cache.lock().unwrap().len(), 2);
+        get(1);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        get(2);
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn expired_responses_are_removed() {
+        let (base_url, requests) = mock_server(|_| "[1001,1002]".to_string());
+        let mut client = ApiClient::with_base_url(base_url, "");
+        client.cache_ttl = Duration::ZERO;
+        client.get_ids::<World>().unwrap();
+        client
+            .get_json::<Vec<u32>>(&format!("{API_BASE_V2_URL}/worlds?page=1"), false)
+            .unwrap();
+        client.get_ids::<World>().unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        // only the latest response is left, the others expired
+        assert_eq!(client.cache.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn token_bucket_waits_when_empty() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket {
+            capacity: 2.0,
+            tokens: 2.0,
+            last_refill: now,
+        };
+        assert_eq!(bucket.take(now), Duration::ZERO);
+        assert_eq!(bucket.take(now), Duration::ZERO);
+        assert_eq!(bucket.take(now), Duration::from_millis(200));
+        // refilled after a second
+        assert_eq!(bucket.take(now + Duration::from_secs(1)), Duration::ZERO);
+    }
+}
@@ -13,10 +13,12 @@ pub use serde::{Deserialize, Serialize};
 // pub mod outfits;
 // pub mod quaggans;
 // pub mod races;
+pub mod achievements;
+pub mod characters;
 pub mod mounts;
 pub mod races;
 pub mod worlds;
-const AUTHORIZATION_HEADER_NAME: &str = "Authorization";
+pub(crate) const AUTHORIZATION_HEADER_NAME: &str = "Authorization";
 
 /// We implement this for types which represent the data provided by a particular endpoint.
 /// eg: We create a Color struct with all the fields we expect in color. Then, we simply impl this trait for that
@@ -24,6 +26,7 @@ const AUTHORIZATION_HEADER_NAME: &str = "Authorization";
 /// 1. When you go to endpoint url, we get a list of ids
 /// 2. we can get an item of that type if we go to endpoint/id url, you always get the item type as return value
 /// 3. when you add the `ids` query parameter like endpoint?ids=1,2,3, you will get a list of values which are of the item type
+///
 /// Obviously, to get that item type from json, it needs to impl Deserialize
 ///
 /// If the endpoint doesn't need authentication, just use an empty string for the api_key
@@ -0,0 +1,52 @@
+use crate::{prelude::*, ApiClient};
+
+/// The progress of an achievement for the account of the api key.
+/// Only achievements with some progress are returned by the api.
+/// <https://wiki.guildwars2.com/wiki/API:2/account/achievements>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountAchievement {
+    pub id: u32,
+    /// indices of the completed bits. only present for achievements with bits
+    #[serde(default)]
+    pub bits: Vec<u32>,
+    #[serde(default)]
+    pub current: u32,
+    #[serde(default)]
+    pub max: u32,
+    pub done: bool,
+}
+impl AccountAchievement {
+    pub const URL: &'static str = const_format::concatcp!(API_BASE_V2_URL, "/account/achievements");
+
+    /// requires an api key with the `progression` permission
+    pub fn get(client: &ApiClient) -> Result<Vec<Self>> {
+        client
+            .get_json(Self::URL, true)
+            .wrap_err("failed to get account achievements")
+    }
+    /// if `bit` is None, whether the whole achievement is done. Otherwise, whether the bit is done.
+    pub fn is_done(&self, bit: Option<u32>) -> bool {
+        self.done || bit.map(|bit| self.bits.contains(&bit)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_account_achievements() {
+        let achievements: Vec<AccountAchievement> = serde_json::from_str(
+            r#"[
+                {"id": 1, "current": 1, "max": 1, "done": true},
+                {"id": 2, "bits": [0, 2], "current": 2, "max": 4, "done": false}
+            ]"#,
+        )
+        .unwrap();
+        assert!(achievements[0].is_done(None));
+        assert!(achievements[0].is_done(Some(5)));
+        assert!(!achievements[1].is_done(None));
+        assert!(achievements[1].is_done(Some(2)));
+        assert!(!achievements[1].is_done(Some(1)));
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{prelude::*, ApiClient};
+
+/// The core details of a character of the account of the api key.
+/// <https://wiki.guildwars2.com/wiki/API:2/characters#Core>
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CharacterCore {
+    pub name: String,
+    pub race: String,
+    pub gender: String,
+    /// the core profession like `Guardian`. matches the ids of v2/professions endpoint
+    pub profession: String,
+    pub level: u32,
+}
+impl CharacterCore {
+    pub const URL: &'static str = const_format::concatcp!(API_BASE_V2_URL, "/characters");
+
+    /// requires an api key with the `characters` permission
+    pub fn get(client: &ApiClient, name: &str) -> Result<Self> {
+        client
+            .get_json(&format!("{}/{}/core", Self::URL, encode_name(name)), true)
+            .wrap_err_with(|| format!("failed to get character {name}"))
+    }
+}
+
+/// character names can have spaces and non ascii letters, so we percent encode them for the url path
+fn encode_name(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_character_core() {
+        let character: CharacterCore = serde_json::from_str(
+            r#"{"name": "Hello World", "race": "Human", "gender": "Female", "profession": "Warrior", "level": 80, "age": 100}"#,
+        )
+        .unwrap();
+        assert_eq!(character.profession, "Warrior");
+        assert_eq!(encode_name(&character.name), "Hello%20World");
+        assert_eq!(encode_name("Zoë"), "Zo%C3%AB");
+    }
+}
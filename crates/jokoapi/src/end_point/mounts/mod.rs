@@ -4,7 +4,7 @@ use crate::prelude::*;
 
 #[bitflags]
 #[repr(u16)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Mount {
     Raptor = 1 << 0,
     Springer = 1 << 1,
@@ -70,8 +70,36 @@ impl AsRef<str> for Mount {
         }
     }
 }
-impl ToString for Mount {
-    fn to_string(&self) -> String {
-        self.as_ref().to_string()
+impl std::fmt::Display for Mount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use enumflags2::BitFlags;
+
+    use super::*;
+
+    #[test]
+    fn every_mount_round_trips_through_strings() {
+        for mount in BitFlags::<Mount>::all().iter() {
+            assert_eq!(mount.as_ref().parse::<Mount>(), Ok(mount));
+            assert_eq!(mount.to_string().parse::<Mount>(), Ok(mount));
+            assert_eq!(mount.to_string(), mount.as_ref());
+        }
+        assert_eq!(BitFlags::<Mount>::all().len(), 10);
+        assert!("horse".parse::<Mount>().is_err());
+    }
+
+    #[test]
+    fn every_mumble_link_mount_index_is_a_mount() {
+        let mounts: BitFlags<Mount> = (1..=10)
+            .map(|index| Mount::try_from_mumble_link(index).unwrap())
+            .collect();
+        assert_eq!(mounts, BitFlags::all());
+        assert_eq!(Mount::try_from_mumble_link(0), None);
+        assert_eq!(Mount::try_from_mumble_link(11), None);
     }
 }
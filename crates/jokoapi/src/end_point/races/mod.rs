@@ -4,15 +4,31 @@ use crate::prelude::*;
 
 #[bitflags]
 #[repr(u8)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Race {
     ASURA = 1 << 0,
     CHARR = 1 << 2,
     HUMAN = 1 << 3,
     NORN = 1 << 4,
     SYLVARI = 1 << 5,
+    /// a race id from mumble link that we don't know (yet). marker packs can't use it, so it is never parsed from a string
+    UNKNOWN = 1 << 6,
 }
 
+/// impl for mumble link
+impl Race {
+    /// converts the race id from mumble link identity. returns [Self::UNKNOWN] for unknown ids.
+    pub fn from_mumble_link(value: u32) -> Self {
+        match value {
+            0 => Self::ASURA,
+            1 => Self::CHARR,
+            2 => Self::HUMAN,
+            3 => Self::NORN,
+            4 => Self::SYLVARI,
+            _ => Self::UNKNOWN,
+        }
+    }
+}
 impl FromStr for Race {
     type Err = &'static str;
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
@@ -35,11 +51,44 @@ impl AsRef<str> for Race {
             Self::HUMAN => "human",
             Self::NORN => "norn",
             Self::SYLVARI => "sylvari",
+            Self::UNKNOWN => "unknown",
         }
     }
 }
-impl ToString for Race {
-    fn to_string(&self) -> String {
-        self.as_ref().to_string()
+impl std::fmt::Display for Race {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mumble_link_race_ids_are_decoded() {
+        let races: Vec<Race> = (0..5).map(Race::from_mumble_link).collect();
+        assert_eq!(
+            races,
+            [
+                Race::ASURA,
+                Race::CHARR,
+                Race::HUMAN,
+                Race::NORN,
+                Race::SYLVARI
+            ]
+        );
+        for race in races {
+            assert_eq!(race.as_ref().parse::<Race>(), Ok(race));
+        }
+    }
+
+    #[test]
+    fn out_of_range_race_ids_are_unknown() {
+        assert_eq!(Race::from_mumble_link(5), Race::UNKNOWN);
+        assert_eq!(Race::from_mumble_link(u32::MAX), Race::UNKNOWN);
+        assert_eq!(Race::UNKNOWN.to_string(), "unknown");
+        // packs can't filter markers by the unknown race
+        assert!("unknown".parse::<Race>().is_err());
     }
 }
@@ -10,14 +10,21 @@
 //!     6. language headers
 //!     7. pagination
 //!     8. schema
+//!
 //! for already great implementations look at
 //! 1. <https://github.com/GW2ToolBelt/GW2APIClient>
 //! 2. <https://github.com/greaka/gw2lib>
+//!
 //! resources:
 //! 1. <https://wiki.guildwars2.com/wiki/API:API_key>
 //! 2. <https://wiki.guildwars2.com/wiki/API:2>
 //! 3. <https://wiki.guildwars2.com/wiki/API:Main>
+pub mod client;
 pub mod end_point;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+
+pub use client::ApiClient;
 
 pub(crate) mod prelude {
     pub type HttpClient = ureq::Agent;
@@ -26,7 +33,7 @@ pub(crate) mod prelude {
     pub use miette::{IntoDiagnostic, Result, WrapErr};
     pub use serde::{de::DeserializeOwned, Deserialize, Serialize};
     pub use std::fmt::Display;
-    const API_BASE_URL: &str = "https://api.guildwars2.com";
+    pub(crate) const API_BASE_URL: &str = "https://api.guildwars2.com";
     pub const API_BASE_V2_URL: &str = const_format::concatcp!(API_BASE_URL, "/v2");
     // https://wiki.guildwars2.com/wiki/API:Changelog
     #[allow(unused)]
@@ -0,0 +1,50 @@
+//! A tiny local http server, so that the tests of the api (and of the crates using it) don't need the network.
+//! Use its url with [crate::ApiClient::with_base_url].
+
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::client::RATE_LIMIT_HEADER_NAME;
+
+/// serves the json returned by `respond` for the path of each request (like `/v2/worlds`) and counts the requests.
+/// returns the base url of the server and the request counter
+pub fn mock_server(
+    respond: impl Fn(&str) -> String + Send + 'static,
+) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = vec![];
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buffer).unwrap();
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+            counter.fetch_add(1, Ordering::SeqCst);
+            // the request line is like `GET /v2/worlds HTTP/1.1`
+            let request = String::from_utf8_lossy(&request);
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+            let body = respond(path);
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n{RATE_LIMIT_HEADER_NAME}: 600\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        }
+    });
+    (format!("http://{address}"), requests)
+}
@@ -0,0 +1,72 @@
+use cap_std::fs_utf8::Dir;
+use miette::{Context, IntoDiagnostic, Result};
+use tracing::{info, warn};
+
+/// egui memory (window positions, sizes, collapsed headers etc..) is saved to this file inside the jokolay dir
+pub const EGUI_MEMORY_FILE_NAME: &str = "egui_memory.json";
+
+/// loads the egui memory saved by [save_egui_memory].
+/// returns None if there's no saved memory or if it is corrupt, so that we just start with the default layout
+pub fn load_egui_memory(jdir: &Dir) -> Option<egui::Memory> {
+    if !jdir.exists(EGUI_MEMORY_FILE_NAME) {
+        info!("no egui memory file. starting with the default window layout");
+        return None;
+    }
+    match jdir
+        .read_to_string(EGUI_MEMORY_FILE_NAME)
+        .into_diagnostic()
+        .wrap_err("failed to read egui memory file")
+        .and_then(|json| {
+            serde_json::from_str(&json)
+                .into_diagnostic()
+                .wrap_err("failed to deserialize egui memory")
+        }) {
+        Ok(memory) => Some(memory),
+        Err(e) => {
+            warn!(
+                ?e,
+                "failed to load egui memory. starting with the default window layout"
+            );
+            None
+        }
+    }
+}
+/// saves the egui memory, so that [load_egui_memory] can restore the window layout on the next launch
+pub fn save_egui_memory(jdir: &Dir, memory: &egui::Memory) -> Result<()> {
+    let json = serde_json::to_string(memory)
+        .into_diagnostic()
+        .wrap_err("failed to serialize egui memory")?;
+    jdir.write(EGUI_MEMORY_FILE_NAME, json)
+        .into_diagnostic()
+        .wrap_err("failed to write egui memory file")
+}
+
+#[cfg(test)]
+mod tests {
+    use cap_std::{ambient_authority, fs_utf8::camino::Utf8PathBuf};
+
+    use super::*;
+
+    #[test]
+    fn egui_memory_round_trip() {
+        let path = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("jokolay_egui_memory_test_{}", std::process::id()));
+        Dir::create_ambient_dir_all(&path, ambient_authority()).unwrap();
+        let jdir = Dir::open_ambient_dir(&path, ambient_authority()).unwrap();
+
+        assert!(load_egui_memory(&jdir).is_none());
+
+        let id = egui::Id::new("test window");
+        let mut memory = egui::Memory::default();
+        memory.data.insert_persisted(id, 42u32);
+        save_egui_memory(&jdir, &memory).unwrap();
+        let mut loaded = load_egui_memory(&jdir).unwrap();
+        assert_eq!(loaded.data.get_persisted::<u32>(id), Some(42));
+
+        jdir.write(EGUI_MEMORY_FILE_NAME, "{ not json").unwrap();
+        assert!(load_egui_memory(&jdir).is_none());
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -5,10 +5,10 @@ use miette::{Context, IntoDiagnostic, Result};
 /// Inside this directory, we will store all of jokolay's data like configuration files, themes, logs etc..
 pub fn get_jokolay_dir() -> Result<cap_std::fs_utf8::Dir> {
     let authoratah = ambient_authority();
-    let jdir = if let Ok(env_dir) = std::env::var("JOKOLAY_DATA_DIR") {
-        let jkl_path = Utf8PathBuf::try_from(&env_dir)
+    let jdir = if let Some(env_dir) = std::env::var_os("JOKOLAY_DATA_DIR") {
+        let jkl_path = Utf8PathBuf::try_from(std::path::PathBuf::from(&env_dir))
             .into_diagnostic()
-            .wrap_err(env_dir)
+            .wrap_err(env_dir.to_string_lossy().into_owned())
             .wrap_err("failed to parse JOKOLAY_DATA_DIR")?;
 
         cap_std::fs_utf8::Dir::create_ambient_dir_all(&jkl_path, authoratah)
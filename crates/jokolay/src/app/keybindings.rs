@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+
+use cap_std::fs_utf8::Dir;
+use miette::{bail, Context, IntoDiagnostic, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use super::MenuPanel;
+
+/// The windows of [MenuPanel] which can be toggled with a key binding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Panel {
+    WindowManager,
+    MarkerManager,
+    MumbleManager,
+    ThemeManager,
+    Logs,
+    FrameTimeOverlay,
+    /// hides everything drawn by jokolay. eg: for screenshots
+    Overlay,
+}
+impl Panel {
+    pub const ALL: [Panel; 7] = [
+        Panel::WindowManager,
+        Panel::MarkerManager,
+        Panel::MumbleManager,
+        Panel::ThemeManager,
+        Panel::Logs,
+        Panel::FrameTimeOverlay,
+        Panel::Overlay,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            Panel::WindowManager => "Window Manager",
+            Panel::MarkerManager => "Marker Manager",
+            Panel::MumbleManager => "Mumble Manager",
+            Panel::ThemeManager => "Theme Manager",
+            Panel::Logs => "Logs",
+            Panel::FrameTimeOverlay => "FPS",
+            Panel::Overlay => "Hide Overlay",
+        }
+    }
+}
+
+/// keys which toggle the panels. A key can only be bound to one panel
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyBindings {
+    bindings: BTreeMap<Panel, egui::Key>,
+}
+impl KeyBindings {
+    pub const KEY_BINDINGS_FILE_NAME: &'static str = "key_bindings.json";
+
+    /// loads the bindings saved by [Self::save]. starts without bindings if the file is missing or corrupt.
+    pub fn load(jdir: &Dir) -> Self {
+        if !jdir.exists(Self::KEY_BINDINGS_FILE_NAME) {
+            return Self::default();
+        }
+        let saved: Self = match jdir
+            .read_to_string(Self::KEY_BINDINGS_FILE_NAME)
+            .into_diagnostic()
+            .wrap_err("failed to read key bindings file")
+            .and_then(|json| {
+                serde_json::from_str(&json)
+                    .into_diagnostic()
+                    .wrap_err("failed to deserialize key bindings")
+            }) {
+            Ok(saved) => saved,
+            Err(e) => {
+                warn!(?e, "failed to load key bindings. starting without bindings");
+                return Self::default();
+            }
+        };
+        // the file might have been edited by hand, so we check for conflicts again
+        let mut bindings = Self::default();
+        for (panel, key) in saved.bindings {
+            if let Err(e) = bindings.bind(panel, key) {
+                error!(?e, "skipping key binding from key bindings file");
+            }
+        }
+        bindings
+    }
+    pub fn save(&self, jdir: &Dir) -> Result<()> {
+        jdir.write(
+            Self::KEY_BINDINGS_FILE_NAME,
+            serde_json::to_string_pretty(self)
+                .into_diagnostic()
+                .wrap_err("failed to serialize key bindings")?,
+        )
+        .into_diagnostic()
+        .wrap_err("failed to write key bindings file")
+    }
+    /// binds `key` to `panel`, replacing the previous key of this panel.
+    /// fails if the key is already bound to a different panel
+    pub fn bind(&mut self, panel: Panel, key: egui::Key) -> Result<()> {
+        if let Some((other, _)) = self
+            .bindings
+            .iter()
+            .find(|(other, bound)| **other != panel && **bound == key)
+        {
+            bail!(
+                "{} is already bound to {}. cannot bind it to {}",
+                key.name(),
+                other.name(),
+                panel.name()
+            );
+        }
+        self.bindings.insert(panel, key);
+        Ok(())
+    }
+    pub fn unbind(&mut self, panel: Panel) {
+        self.bindings.remove(&panel);
+    }
+    pub fn get(&self, panel: Panel) -> Option<egui::Key> {
+        self.bindings.get(&panel).copied()
+    }
+    /// the panels whose key was pressed in these events. key repeats and releases are ignored
+    pub fn pressed_panels(&self, events: &[egui::Event]) -> Vec<Panel> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    repeat: false,
+                    ..
+                } => Some(*key),
+                _ => None,
+            })
+            .flat_map(|key| {
+                self.bindings
+                    .iter()
+                    .filter(move |(_, bound)| **bound == key)
+                    .map(|(panel, _)| *panel)
+            })
+            .collect()
+    }
+}
+
+/// state of the key bindings window
+#[derive(Debug, Default)]
+pub struct KeyBindingsUI {
+    /// the next pressed key is bound to this panel
+    waiting_for_key: Option<Panel>,
+    /// the error of the last failed binding
+    last_error: Option<String>,
+}
+
+impl MenuPanel {
+    fn panel_open_mut(&mut self, panel: Panel) -> &mut bool {
+        match panel {
+            Panel::WindowManager => &mut self.show_window_manager,
+            Panel::MarkerManager => &mut self.show_marker_manager_window,
+            Panel::MumbleManager => &mut self.show_mumble_manager_winodw,
+            Panel::ThemeManager => &mut self.show_theme_window,
+            Panel::Logs => &mut self.show_tracing_window,
+            Panel::FrameTimeOverlay => &mut self.show_frame_time_overlay,
+            Panel::Overlay => &mut self.hide_overlay,
+        }
+    }
+    /// toggles the panels whose key was pressed. does nothing while we wait for a key to bind
+    pub fn handle_key_bindings(&mut self, events: &[egui::Event]) {
+        if self.key_bindings_ui.waiting_for_key.is_some() {
+            return;
+        }
+        for panel in self.key_bindings.pressed_panels(events) {
+            let open = self.panel_open_mut(panel);
+            *open = !*open;
+        }
+    }
+    pub fn key_bindings_gui(&mut self, etx: &egui::Context, jdir: &Dir) {
+        let Self {
+            key_bindings,
+            key_bindings_ui,
+            show_key_bindings_window,
+            ..
+        } = self;
+        if let Some(panel) = key_bindings_ui.waiting_for_key {
+            let pressed = etx.input(|i| {
+                i.events.iter().find_map(|event| match event {
+                    egui::Event::Key {
+                        key, pressed: true, ..
+                    } => Some(*key),
+                    _ => None,
+                })
+            });
+            if let Some(key) = pressed {
+                key_bindings_ui.waiting_for_key = None;
+                key_bindings_ui.last_error = None;
+                if key == egui::Key::Escape {
+                    info!(?panel, "cancelled key binding");
+                } else if let Err(e) = key_bindings.bind(panel, key) {
+                    error!(?e, "failed to bind key");
+                    key_bindings_ui.last_error = Some(e.to_string());
+                } else if let Err(e) = key_bindings.save(jdir) {
+                    error!(?e, "failed to save key bindings");
+                }
+            }
+        }
+        egui::Window::new("Key Bindings")
+            .open(show_key_bindings_window)
+            .show(etx, |ui| {
+                egui::Grid::new("key bindings grid")
+                    .num_columns(3)
+                    .show(ui, |ui| {
+                        for panel in Panel::ALL {
+                            ui.label(panel.name());
+                            if key_bindings_ui.waiting_for_key == Some(panel) {
+                                ui.label("press a key (escape to cancel)");
+                            } else if ui
+                                .button(key_bindings.get(panel).map(|k| k.name()).unwrap_or("-"))
+                                .on_hover_text("click to bind a key")
+                                .clicked()
+                            {
+                                key_bindings_ui.waiting_for_key = Some(panel);
+                            }
+                            if ui.button("clear").clicked() {
+                                key_bindings.unbind(panel);
+                                if let Err(e) = key_bindings.save(jdir) {
+                                    error!(?e, "failed to save key bindings");
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if let Some(e) = key_bindings_ui.last_error.as_ref() {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_event(key: egui::Key, pressed: bool, repeat: bool) -> egui::Event {
+        egui::Event::Key {
+            key,
+            pressed,
+            repeat,
+            modifiers: Default::default(),
+        }
+    }
+
+    #[test]
+    fn bound_keys_toggle_panels() {
+        let mut menu_panel = MenuPanel::default();
+        menu_panel
+            .key_bindings
+            .bind(Panel::MarkerManager, egui::Key::F1)
+            .unwrap();
+        menu_panel
+            .key_bindings
+            .bind(Panel::Logs, egui::Key::F2)
+            .unwrap();
+        // a key can't be bound to two panels
+        assert!(menu_panel
+            .key_bindings
+            .bind(Panel::ThemeManager, egui::Key::F1)
+            .is_err());
+        assert_eq!(menu_panel.key_bindings.get(Panel::ThemeManager), None);
+        // rebinding the same panel is fine
+        menu_panel
+            .key_bindings
+            .bind(Panel::MarkerManager, egui::Key::F1)
+            .unwrap();
+
+        menu_panel.handle_key_bindings(&[key_event(egui::Key::F1, true, false)]);
+        assert!(menu_panel.show_marker_manager_window);
+        assert!(!menu_panel.show_tracing_window);
+        // repeats and releases don't toggle
+        menu_panel.handle_key_bindings(&[
+            key_event(egui::Key::F1, true, true),
+            key_event(egui::Key::F1, false, false),
+        ]);
+        assert!(menu_panel.show_marker_manager_window);
+        menu_panel.handle_key_bindings(&[
+            key_event(egui::Key::F1, true, false),
+            key_event(egui::Key::F2, true, false),
+        ]);
+        assert!(!menu_panel.show_marker_manager_window);
+        assert!(menu_panel.show_tracing_window);
+        // unbound keys do nothing
+        menu_panel.handle_key_bindings(&[key_event(egui::Key::F3, true, false)]);
+        assert!(menu_panel.show_tracing_window);
+    }
+}
@@ -2,13 +2,20 @@ use std::sync::Arc;
 
 use cap_std::fs_utf8::Dir;
 use egui_window_glfw_passthrough::{glfw::Context as _, GlfwBackend, GlfwConfig};
+mod egui_memory;
 mod init;
+mod keybindings;
 mod wm;
+use egui_memory::{load_egui_memory, save_egui_memory};
 use init::get_jokolay_dir;
 use jmf::MarkerManager;
-use joko_core::manager::{theme::ThemeManager, trace::JokolayTracingLayer};
+use joko_core::manager::{
+    theme::ThemeManager,
+    trace::{JokolayTracingLayer, LogRotationConfig},
+};
 use joko_render::JokoRenderer;
-use jokolink::{MumbleChanges, MumbleManager};
+use jokolink::MumbleManager;
+use keybindings::{KeyBindings, KeyBindingsUI};
 use miette::{Context, Result};
 use tracing::{error, info};
 #[allow(unused)]
@@ -25,13 +32,30 @@ pub struct Jokolay {
 }
 impl Jokolay {
     pub fn new(jdir: Arc<Dir>) -> Result<Self> {
-        let mumble =
-            MumbleManager::new("MumbleLink", None).wrap_err("failed to create mumble manager")?;
+        let mut mumble = MumbleManager::new(
+            "MumbleLink",
+            None,
+            std::time::Duration::from_secs(10),
+            Some(std::time::Duration::from_millis(5)),
+            false,
+        )
+        .wrap_err("failed to create mumble manager")?;
+        // poll fast while gw2 is live and back off while there's nothing to follow
+        mumble.set_adaptive_polling(true);
+        if let Err(e) = mumble.load_presets(jdir.clone()) {
+            error!(?e, "failed to load mumble presets");
+        }
+        if let Err(e) = mumble.load_config(jdir.clone()) {
+            error!(?e, "failed to load mumble manager config");
+        }
         let marker_manager =
             MarkerManager::new(&jdir).wrap_err("failed to create marker manager")?;
         let mut theme_manager =
             ThemeManager::new(&jdir).wrap_err("failed to create theme manager")?;
         let egui_context = egui::Context::default();
+        if let Some(memory) = load_egui_memory(&jdir) {
+            egui_context.memory_mut(|m| *m = memory);
+        }
         theme_manager.init_egui(&egui_context);
         let mut glfw_backend = GlfwBackend::new(GlfwConfig {
             glfw_callback: Box::new(|glfw_context| {
@@ -53,6 +77,10 @@ impl Jokolay {
         glfw_backend.window.set_floating(true);
         glfw_backend.window.set_decorated(false);
         let joko_renderer = JokoRenderer::new(&mut glfw_backend, Default::default());
+        let menu_panel = MenuPanel {
+            key_bindings: KeyBindings::load(&jdir),
+            ..Default::default()
+        };
         Ok(Self {
             mumble_manager: mumble,
             marker_manager,
@@ -62,7 +90,7 @@ impl Jokolay {
             jdir,
             egui_context,
             theme_manager,
-            menu_panel: MenuPanel::default(),
+            menu_panel,
         })
     }
     pub fn enter_event_loop(mut self) {
@@ -72,7 +100,7 @@ impl Jokolay {
         loop {
             let Self {
                 frame_stats,
-                jdir: _,
+                jdir,
                 menu_panel,
                 mumble_manager,
                 marker_manager,
@@ -89,6 +117,11 @@ impl Jokolay {
 
             if glfw_backend.window.should_close() {
                 tracing::warn!("should close is true. So, exiting event loop");
+                marker_manager.shutdown();
+                mumble_manager.stop_recording();
+                if let Err(e) = etx.memory(|memory| save_egui_memory(jdir, memory)) {
+                    error!(?e, "failed to save egui memory");
+                }
                 break;
             }
 
@@ -133,78 +166,98 @@ impl Jokolay {
                     None
                 }
             };
-            joko_renderer.tick(link.clone());
-            marker_manager.tick(&etx, latest_time, joko_renderer, &link);
+            joko_renderer.tick(link.clone(), latest_time);
+            // don't draw markers if the camera is frozen
+            let link_is_stale = mumble_manager.is_stale(std::time::Instant::now());
+            if !link_is_stale {
+                marker_manager.tick(&etx, latest_time, joko_renderer, &link);
+            }
             menu_panel.tick(&etx, link.clone().as_ref().map(|m| m.as_ref()));
+            if !etx.wants_keyboard_input() {
+                let events = etx.input(|i| i.events.clone());
+                menu_panel.handle_key_bindings(&events);
+            }
+            joko_renderer.set_hidden(menu_panel.hide_overlay);
 
             // do the gui stuff now
-            egui::Area::new("menu panel")
-                .fixed_pos(menu_panel.pos)
-                .interactable(true)
-                .order(egui::Order::Foreground)
-                .show(&etx, |ui| {
-                    ui.style_mut().visuals.widgets.inactive.weak_bg_fill =
-                        egui::Color32::TRANSPARENT;
-                    ui.horizontal(|ui| {
-                        ui.menu_button(
-                            egui::RichText::new("JKL")
-                                .size((MenuPanel::HEIGHT - 2.0) * menu_panel.ui_scaling_factor)
-                                .background_color(egui::Color32::TRANSPARENT),
-                            |ui| {
-                                ui.checkbox(
-                                    &mut menu_panel.show_window_manager,
-                                    "Show Window Manager",
-                                );
-                                ui.checkbox(
-                                    &mut menu_panel.show_marker_manager_window,
-                                    "Show Marker Manager",
-                                );
-                                ui.checkbox(
-                                    &mut menu_panel.show_mumble_manager_winodw,
-                                    "Show Mumble Manager",
-                                );
-                                ui.checkbox(
-                                    &mut menu_panel.show_theme_window,
-                                    "Show Theme Manager",
-                                );
-                                ui.checkbox(&mut menu_panel.show_tracing_window, "Show Logs");
-                                if ui.button("exit").clicked() {
-                                    info!("exiting jokolay");
-                                    glfw_backend.window.set_should_close(true);
-                                }
-                            },
-                        );
-                        marker_manager.menu_ui(ui);
+            // nothing is drawn while the overlay is hidden, but the window stays
+            if !menu_panel.hide_overlay {
+                egui::Area::new("menu panel")
+                    .fixed_pos(menu_panel.pos)
+                    .interactable(true)
+                    .order(egui::Order::Foreground)
+                    .show(&etx, |ui| {
+                        ui.style_mut().visuals.widgets.inactive.weak_bg_fill =
+                            egui::Color32::TRANSPARENT;
+                        ui.horizontal(|ui| {
+                            ui.menu_button(
+                                egui::RichText::new("JKL")
+                                    .size((MenuPanel::HEIGHT - 2.0) * menu_panel.ui_scaling_factor)
+                                    .background_color(egui::Color32::TRANSPARENT),
+                                |ui| {
+                                    ui.checkbox(
+                                        &mut menu_panel.show_window_manager,
+                                        "Show Window Manager",
+                                    );
+                                    ui.checkbox(
+                                        &mut menu_panel.show_marker_manager_window,
+                                        "Show Marker Manager",
+                                    );
+                                    ui.checkbox(
+                                        &mut menu_panel.show_mumble_manager_winodw,
+                                        "Show Mumble Manager",
+                                    );
+                                    ui.checkbox(
+                                        &mut menu_panel.show_theme_window,
+                                        "Show Theme Manager",
+                                    );
+                                    ui.checkbox(&mut menu_panel.show_tracing_window, "Show Logs");
+                                    ui.checkbox(
+                                        &mut menu_panel.show_frame_time_overlay,
+                                        "Show FPS",
+                                    );
+                                    ui.checkbox(
+                                        &mut menu_panel.show_key_bindings_window,
+                                        "Show Key Bindings",
+                                    );
+                                    if ui.button("exit").clicked() {
+                                        info!("exiting jokolay");
+                                        glfw_backend.window.set_should_close(true);
+                                    }
+                                },
+                            );
+                            marker_manager.menu_ui(ui);
+                        });
                     });
-                });
-            marker_manager.gui(&etx, &mut menu_panel.show_marker_manager_window);
-            mumble_manager.gui(&etx, &mut menu_panel.show_mumble_manager_winodw);
-            JokolayTracingLayer::gui(&etx, &mut menu_panel.show_tracing_window);
-            theme_manager.gui(&etx, &mut menu_panel.show_theme_window);
-            frame_stats.gui(&etx, glfw_backend, &mut menu_panel.show_window_manager);
-            // show notifications
-            JokolayTracingLayer::show_notifications(&etx);
-
+                marker_manager.gui(&etx, &mut menu_panel.show_marker_manager_window);
+                mumble_manager.gui(&etx, &mut menu_panel.show_mumble_manager_winodw);
+                JokolayTracingLayer::gui(&etx, &mut menu_panel.show_tracing_window);
+                theme_manager.gui(&etx, &mut menu_panel.show_theme_window);
+                frame_stats.gui(&etx, glfw_backend, &mut menu_panel.show_window_manager);
+                frame_stats.overlay_gui(&etx, menu_panel.show_frame_time_overlay);
+                menu_panel.key_bindings_gui(&etx, jdir);
+                // show notifications
+                JokolayTracingLayer::show_notifications(&etx);
+            }
             // end gui stuff
             // check if we need to change window position or size.
-            if let Some(link) = link.as_ref() {
-                if link.changes.contains(MumbleChanges::WindowPosition)
-                    || link.changes.contains(MumbleChanges::WindowSize)
+            // a stale link might have outdated dimensions, so we only follow gw2 while the link is live
+            if let Some(link) = link.as_ref().filter(|_| !link_is_stale) {
+                if let Some((client_pos, client_size)) =
+                    frame_stats.attach_to_gw2_window(link.physical_client_rect())
                 {
                     info!(
-                        ?link.client_pos, ?link.client_size,
+                        ?client_pos,
+                        ?client_size,
                         "resizing/repositioning to match gw2 window dimensions"
                     );
-
-                    glfw_backend
-                        .window
-                        .set_pos(link.client_pos.x, link.client_pos.y);
+                    glfw_backend.window.set_pos(client_pos.x, client_pos.y);
                     // if gw2 is in windowed fullscreen mode, then the size is full resolution of the screen/monitor.
                     // But if we set that size, when you focus jokolay, the screen goes blank on win11 (some kind of fullscreen optimization maybe?)
                     // so we remove a pixel from right/bottom edges. mostly indistinguishable, but makes sure that transparency works even in windowed fullscrene mode of gw2
                     glfw_backend
                         .window
-                        .set_size(link.client_size.x - 1, link.client_size.y - 1);
+                        .set_size(client_size.x as i32 - 1, client_size.y as i32 - 1);
                 }
             }
             etx.request_repaint();
@@ -245,13 +298,14 @@ pub fn start_jokolay() {
             panic!("failed to create jokolay_dir: {e:#?}");
         }
     };
-    let log_file_flush_guard = match JokolayTracingLayer::install_tracing(&jdir) {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("failed to install tracing: {e:#?}");
-            panic!("failed to install tracing: {e:#?}");
-        }
-    };
+    let log_file_flush_guard =
+        match JokolayTracingLayer::install_tracing(&jdir, LogRotationConfig::from_env()) {
+            Ok(g) => g,
+            Err(e) => {
+                eprintln!("failed to install tracing: {e:#?}");
+                panic!("failed to install tracing: {e:#?}");
+            }
+        };
 
     if let Err(e) = rayon::ThreadPoolBuilder::default()
         .panic_handler(|panic_info| {
@@ -278,33 +332,32 @@ pub fn start_jokolay() {
 /// Guild Wars 2 has an array of menu icons on top left corner of the game.
 /// Its size is affected by four different factors
 /// 1. UISZ:
-///     This is a setting in graphics options of gw2 and it comes in 4 variants
-///     small, normal, large and larger.
-///     This is something we can get from mumblelink's context.
+///    This is a setting in graphics options of gw2 and it comes in 4 variants
+///    small, normal, large and larger.
+///    This is something we can get from mumblelink's context.
 /// 2. DPI scaling
-///     This is a setting in graphics options too. When scaling is enabled, sizes of menu become bigger according to the dpi of gw2 window
-///     This is something we get from gw2's config file in AppData/Roaming and store in mumble link as dpi scaling
-///     We also get dpi of gw2 window and store it in mumble link.
+///    This is a setting in graphics options too. When scaling is enabled, sizes of menu become bigger according to the dpi of gw2 window
+///    This is something we get from gw2's config file in AppData/Roaming and store in mumble link as dpi scaling
+///    We also get dpi of gw2 window and store it in mumble link.
 /// 3. Dimensions of the gw2 window
-///     This is something we get from mumble link and win32 api. We store this as client pos/size in mumble link
-///     It is not just the width or height, but their ratio to the 1024x768 resolution
-
+///    This is something we get from mumble link and win32 api. We store this as client pos/size in mumble link
+///    It is not just the width or height, but their ratio to the 1024x768 resolution
 ///
 /// 1. By default, with dpi 96 (scale 1.0), at resolution 1024x768 these are the sizes of menu at different uisz settings
-///     UISZ   -> WIDTH   HEIGHT
-///     small  -> 288     27
-///     normal -> 319     31
-///     large  -> 355     34
-///     larger -> 391     38
-///     all units are in raw pixels.
-///     
-///     If we think of small uisz as the default. Then, we can express the rest of the sizes as ratio to small.
-///     small = 1.0
-///     normal = 1.1
-///     large = 1.23
-///     larger = 1.35
-///     
-///     So, just multiply small (288) with these ratios to get the actual pixels of each uisz.
+///    UISZ   -> WIDTH   HEIGHT
+///    small  -> 288     27
+///    normal -> 319     31
+///    large  -> 355     34
+///    larger -> 391     38
+///    all units are in raw pixels.
+///    
+///    If we think of small uisz as the default. Then, we can express the rest of the sizes as ratio to small.
+///    small = 1.0
+///    normal = 1.1
+///    large = 1.23
+///    larger = 1.35
+///    
+///    So, just multiply small (288) with these ratios to get the actual pixels of each uisz.
 /// 2. When dpi doubles, so do the sizes. 288 -> 576, 319 -> 638 etc.. So, when dpi scaling is enabled, we must multiply the above uisz ratio with dpi scale ratio to get the combined scaling ratio.
 /// 3. The dimensions thing is a little complicated. So, i will just list the actual steps here.
 ///     1. take gw2's actual width in raw pixels. lets call this gw2_width.
@@ -327,6 +380,12 @@ pub struct MenuPanel {
     show_marker_manager_window: bool,
     show_mumble_manager_winodw: bool,
     show_window_manager: bool,
+    show_frame_time_overlay: bool,
+    show_key_bindings_window: bool,
+    /// hides the markers, trails and all the windows. toggled with the key binding of [keybindings::Panel::Overlay]
+    hide_overlay: bool,
+    key_bindings: KeyBindings,
+    key_bindings_ui: KeyBindingsUI,
 }
 
 impl MenuPanel {
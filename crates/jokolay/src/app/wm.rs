@@ -1,10 +1,23 @@
 use egui_window_glfw_passthrough::GlfwBackend;
+use glam::{IVec2, UVec2};
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+
+/// number of recent frames used for the rolling average of frame times
+const FRAME_TIME_SAMPLES: usize = 128;
 
 pub struct WindowStatistics {
     pub fps_last_reset: f64,
     pub frame_count: u32,
     pub total_frame_count: u32,
     pub average_fps: u32,
+    /// the time of the previous tick
+    pub last_frame_time: f64,
+    /// durations (in seconds) of the recent frames
+    pub frame_times: AllocRingBuffer<f64>,
+    /// whether we move and resize jokolay to match the gw2 window
+    pub attach_to_gw2: bool,
+    /// the gw2 window position and size that we applied last. so that we don't set the same dimensions every frame
+    last_attached_rect: Option<(IVec2, UVec2)>,
 }
 
 impl WindowStatistics {
@@ -14,12 +27,18 @@ impl WindowStatistics {
             frame_count: 0,
             total_frame_count: 0,
             average_fps: 0,
+            last_frame_time: current_time,
+            frame_times: AllocRingBuffer::new(FRAME_TIME_SAMPLES),
+            attach_to_gw2: true,
+            last_attached_rect: None,
         }
     }
 
     pub fn tick(&mut self, current_time: f64) {
         self.total_frame_count += 1;
         self.frame_count += 1;
+        self.frame_times.push(current_time - self.last_frame_time);
+        self.last_frame_time = current_time;
         if current_time - self.fps_last_reset > 1.0 {
             self.average_fps = self.frame_count;
             self.frame_count = 0;
@@ -27,6 +46,49 @@ impl WindowStatistics {
         }
     }
 
+    /// returns the gw2 window position and size (like [jokolink::MumbleLink::physical_client_rect]) that jokolay must be moved/resized to.
+    /// None if attaching is disabled or if the dimensions didn't change since the last time
+    pub fn attach_to_gw2_window(&mut self, rect: (IVec2, UVec2)) -> Option<(IVec2, UVec2)> {
+        if !self.attach_to_gw2 || self.last_attached_rect == Some(rect) {
+            return None;
+        }
+        self.last_attached_rect = Some(rect);
+        Some(rect)
+    }
+    /// average duration of the recent frames in seconds
+    pub fn average_frame_time(&self) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+        self.frame_times.iter().sum::<f64>() / self.frame_times.len() as f64
+    }
+    /// fps based on [Self::average_frame_time]. unlike [Self::average_fps], this changes every frame
+    pub fn rolling_fps(&self) -> f64 {
+        let frame_time = self.average_frame_time();
+        if frame_time > 0.0 {
+            1.0 / frame_time
+        } else {
+            0.0
+        }
+    }
+    /// a small non-interactive label in the bottom right corner with fps and frame time
+    pub fn overlay_gui(&self, etx: &egui::Context, open: bool) {
+        if !open {
+            return;
+        }
+        egui::Area::new("frame time overlay")
+            .anchor(egui::Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+            .interactable(false)
+            .order(egui::Order::Foreground)
+            .show(etx, |ui| {
+                ui.label(format!(
+                    "{:.0} fps ({:.2} ms)",
+                    self.rolling_fps(),
+                    self.average_frame_time() * 1000.0
+                ));
+            });
+    }
+
     pub fn gui(&mut self, etx: &egui::Context, wb: &mut GlfwBackend, open: &mut bool) {
         egui::Window::new("Window Manager")
             .open(open)
@@ -35,23 +97,33 @@ impl WindowStatistics {
                     .num_columns(2)
                     .show(ui, |ui| {
                         ui.label("fps");
-                        ui.label(&format!("{}", self.average_fps));
+                        ui.label(format!("{}", self.average_fps));
                         ui.end_row();
                         ui.label("frame count");
-                        ui.label(&format!("{}", self.total_frame_count));
+                        ui.label(format!("{}", self.total_frame_count));
                         ui.end_row();
                         ui.label("jokolay pos");
-                        ui.label(&format!(
+                        ui.label(format!(
                             "x: {}; y: {}",
                             wb.window_position[0], wb.window_position[1]
                         ));
                         ui.end_row();
                         ui.label("jokolay size");
-                        ui.label(&format!(
+                        ui.label(format!(
                             "width: {}, height: {}",
                             wb.framebuffer_size_physical[0], wb.framebuffer_size_physical[1]
                         ));
                         ui.end_row();
+                        ui.label("attach to gw2");
+                        if ui
+                            .checkbox(&mut self.attach_to_gw2, "follow gw2 window")
+                            .on_hover_text("move and resize jokolay to match the gw2 window")
+                            .changed()
+                        {
+                            // apply the gw2 dimensions again, even if they didn't change while we were detached
+                            self.last_attached_rect = None;
+                        }
+                        ui.end_row();
                         ui.label("decorations (borders)");
                         let is_decorated = wb.window.is_decorated();
                         let mut result = is_decorated;
@@ -73,3 +145,44 @@ impl WindowStatistics {
             });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolling_average_of_frame_times() {
+        let mut stats = WindowStatistics::new(10.0);
+        assert_eq!(stats.rolling_fps(), 0.0);
+        for time in [10.01, 10.03, 10.06] {
+            stats.tick(time);
+        }
+        assert!((stats.average_frame_time() - 0.02).abs() < 1e-9);
+        assert!((stats.rolling_fps() - 50.0).abs() < 1e-6);
+
+        // only the latest frames are averaged
+        let mut time = 10.06;
+        for _ in 0..FRAME_TIME_SAMPLES {
+            time += 0.004;
+            stats.tick(time);
+        }
+        assert!((stats.average_frame_time() - 0.004).abs() < 1e-9);
+        assert!((stats.rolling_fps() - 250.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn attach_skips_unchanged_dimensions() {
+        let mut stats = WindowStatistics::new(0.0);
+        let rect = (IVec2::new(10, 20), UVec2::new(1920, 1080));
+        assert_eq!(stats.attach_to_gw2_window(rect), Some(rect));
+        for _ in 0..10 {
+            assert_eq!(stats.attach_to_gw2_window(rect), None);
+        }
+        let moved = (IVec2::new(0, 0), UVec2::new(1920, 1080));
+        assert_eq!(stats.attach_to_gw2_window(moved), Some(moved));
+        assert_eq!(stats.attach_to_gw2_window(moved), None);
+
+        stats.attach_to_gw2 = false;
+        assert_eq!(stats.attach_to_gw2_window(rect), None);
+    }
+}
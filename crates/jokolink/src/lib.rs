@@ -9,26 +9,59 @@
 //!
 
 mod mumble;
+mod presets;
+mod recorder;
+use cap_std::fs_utf8::Dir;
 use egui::DragValue;
-use enumflags2::BitFlags;
 use glam::IVec2;
 use jokoapi::end_point::mounts::Mount;
-use miette::{IntoDiagnostic, Result, WrapErr};
+use miette::{Context, IntoDiagnostic, Result};
 pub use mumble::*;
-use serde_json::from_str;
-use std::sync::Arc;
-use tracing::error;
+pub use presets::MumblePresets;
+pub use recorder::{MumbleRecorder, RECORDER_CSV_HEADER};
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, trace};
 
+use joko_log::UI_NOTIFICATIONS_TARGET;
+/// see [MumbleManager::set_reconnect_after]
+pub const DEFAULT_RECONNECT_AFTER: Duration = Duration::from_secs(5);
 /// The default mumble link name. can only be changed by passing the `-mumble` options to gw2 for multiboxing
 pub const DEFAULT_MUMBLELINK_NAME: &str = "MumbleLink";
+
+/// settings of [MumbleManager] which are saved in the jokolay data dir. see [MumbleManager::load_config]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MumbleManagerConfig {
+    /// whether the [MumbleRecorder] is running. It starts again when the config is loaded
+    pub record: bool,
+    /// the csv file of the recorder, relative to the jokolay data dir
+    pub recorder_path: String,
+}
+impl Default for MumbleManagerConfig {
+    fn default() -> Self {
+        Self {
+            record: false,
+            recorder_path: "mumble_recording.csv".to_string(),
+        }
+    }
+}
 #[cfg(target_os = "linux")]
 pub mod linux;
 #[cfg(target_os = "windows")]
 pub mod win;
 
-#[cfg(target_os = "linux")]
+#[cfg(feature = "mock")]
+pub mod mock;
+
+#[cfg(all(target_os = "linux", not(feature = "mock")))]
 use linux::MumbleLinuxImpl as MumblePlatformImpl;
-#[cfg(target_os = "windows")]
+#[cfg(feature = "mock")]
+use mock::MumbleMockImpl as MumblePlatformImpl;
+#[cfg(all(target_os = "windows", not(feature = "mock")))]
 use win::MumbleWinImpl as MumblePlatformImpl;
 // Useful link size is only [ctypes::USEFUL_C_MUMBLE_LINK_SIZE] . And we add 100 more bytes so that jokolink can put some extra stuff in there
 // pub(crate) const JOKOLINK_MUMBLE_BUFFER_SIZE: usize = ctypes::USEFUL_C_MUMBLE_LINK_SIZE + 100;
@@ -42,69 +75,320 @@ pub struct MumbleManager {
     /// This abstracts over the windows and linux impl of mumble link functionality.
     /// we use this to get the latest mumble link and latest window dimensions of the current mumble link
     backend: MumblePlatformImpl,
+    /// name of the mumble link. used to recreate the backend in [Self::reconnect]
+    name: String,
+    /// used to recreate the backend in [Self::reconnect]
+    refresh_interval: Option<Duration>,
+    /// since when is the backend dead. None if it is alive
+    dead_since: Option<Instant>,
+    /// If the backend is dead for this long, we [Self::reconnect]
+    reconnect_after: Duration,
     /// latest mumble link
     link: Arc<MumbleLink>,
+    /// If enabled, appends a csv row for every link that changed.
+    recorder: Option<MumbleRecorder>,
+    /// the jokolay data dir, which contains the config and the recordings. None until [Self::load_config] is called
+    dir: Option<Arc<Dir>>,
+    config: MumbleManagerConfig,
+    /// when did the ui_tick change the last time
+    last_ui_tick_change: Instant,
+    /// If ui_tick doesn't change for this long, the link is considered stale (game is paused/in loading screen/closed etc..)
+    stale_threshold: Duration,
+    /// If true, [Self::tick] returns None when nothing changed since the previous link. see [Self::new]
+    only_broadcast_on_change: bool,
+    /// whether [Self::tick] returned a link since the backend came alive. Used to make sure that the first link is always returned
+    broadcasted: bool,
+    /// If true, we ignore the backend and [Self::tick] returns the link edited in the gui. useful to test markers without gw2
+    editable: bool,
+    /// presets which can be loaded into the editable link. None until [Self::load_presets] is called
+    presets: Option<MumblePresets>,
+    /// name used by the gui when saving the edited link as a preset
+    preset_name: String,
+    /// the last error of the backend tick. we only notify the user when the error changes, instead of every frame
+    backend_error: Option<String>,
+    /// If true, [Self::tick] only reads the backend once [Self::poll_interval] passed since the previous read. see [Self::set_adaptive_polling]
+    adaptive_polling: bool,
+    /// when the backend will be read again, if `adaptive_polling` is enabled
+    next_read: Instant,
 }
 impl MumbleManager {
-    pub fn new(name: &str, _jokolay_window_id: Option<u32>) -> Result<Self> {
-        let backend = MumblePlatformImpl::new(name)?;
+    /// `stale_threshold` is the duration after which the link is considered stale if ui_tick doesn't advance. see [Self::is_stale]
+    /// `refresh_interval` rate limits the reads of the `/dev/shm` file on linux. It is ignored on windows as we read the shared memory directly.
+    /// `only_broadcast_on_change` makes [Self::tick] return None if the link has no changes, so that consumers can skip the frame.
+    /// The first link is always returned. The gui always shows the latest link regardless of this flag.
+    pub fn new(
+        name: &str,
+        _jokolay_window_id: Option<u32>,
+        stale_threshold: Duration,
+        refresh_interval: Option<Duration>,
+        only_broadcast_on_change: bool,
+    ) -> Result<Self> {
+        let backend = Self::new_backend(name, refresh_interval)?;
         Ok(Self {
             backend,
+            name: name.to_string(),
+            refresh_interval,
+            dead_since: None,
+            reconnect_after: DEFAULT_RECONNECT_AFTER,
             link: Arc::new(Default::default()),
+            last_ui_tick_change: Instant::now(),
+            stale_threshold,
+            recorder: None,
+            dir: None,
+            config: Default::default(),
+            only_broadcast_on_change,
+            broadcasted: false,
+            editable: false,
+            presets: None,
+            preset_name: String::new(),
+            backend_error: None,
+            adaptive_polling: false,
+            next_read: Instant::now(),
         })
     }
+    fn new_backend(name: &str, refresh_interval: Option<Duration>) -> Result<MumblePlatformImpl> {
+        #[cfg(all(target_os = "linux", not(feature = "mock")))]
+        let backend = MumblePlatformImpl::new(name, refresh_interval)?;
+        #[cfg(any(target_os = "windows", feature = "mock"))]
+        let backend = {
+            let _ = refresh_interval;
+            MumblePlatformImpl::new(name)?
+        };
+        Ok(backend)
+    }
+    /// Recreates the backend with the same name. The old backend might hold a dead handle after gw2 is closed and reopened.
+    /// On linux, this opens the `/dev/shm` file again. The link is reset, so that the next link reports all the changes
+    pub fn reconnect(&mut self) -> Result<()> {
+        info!(self.name, "reconnecting mumble link");
+        #[allow(unused_mut)]
+        let mut backend = Self::new_backend(&self.name, self.refresh_interval)
+            .wrap_err("failed to recreate mumble backend")?;
+        #[cfg(feature = "mock")]
+        backend.take_steps_from(&mut self.backend);
+        self.backend = backend;
+        self.link = Arc::new(Default::default());
+        self.broadcasted = false;
+        self.dead_since = None;
+        Ok(())
+    }
+    /// [Self::tick] calls [Self::reconnect] once the backend is dead for `duration`. defaults to [DEFAULT_RECONNECT_AFTER]
+    pub fn set_reconnect_after(&mut self, duration: Duration) {
+        self.reconnect_after = duration;
+    }
+    /// With adaptive polling, [Self::tick] skips reading the backend until [Self::poll_interval] passed since the previous read.
+    /// So, the link is polled fast while gw2 is live and less often while there's nothing to follow, even if the caller ticks every frame.
+    /// The skipped ticks return the latest link again without any changes (or None if `only_broadcast_on_change` is set). Disabled by default.
+    pub fn set_adaptive_polling(&mut self, enabled: bool) {
+        self.adaptive_polling = enabled;
+    }
+    pub const CONFIG_FILE_NAME: &'static str = "mumble_manager_config.json";
+    /// loads the config from `dir` (the default if the file doesn't exist yet) and starts the recorder if it is enabled.
+    /// The recordings are written into `dir` too.
+    pub fn load_config(&mut self, dir: Arc<Dir>) -> Result<()> {
+        let config = if dir.exists(Self::CONFIG_FILE_NAME) {
+            let json = dir
+                .read_to_string(Self::CONFIG_FILE_NAME)
+                .into_diagnostic()
+                .wrap_err("failed to read mumble manager config")?;
+            serde_json::from_str(&json)
+                .into_diagnostic()
+                .wrap_err("failed to deserialize mumble manager config")?
+        } else {
+            Default::default()
+        };
+        self.dir = Some(dir);
+        self.set_config(config)
+    }
+    pub fn config(&self) -> &MumbleManagerConfig {
+        &self.config
+    }
+    /// starts or stops the recorder to match the config and saves it into the data dir (if we have one).
+    /// If the recorder fails to start, recording is disabled in the saved config.
+    pub fn set_config(&mut self, config: MumbleManagerConfig) -> Result<()> {
+        let restart = config.recorder_path != self.config.recorder_path || !self.is_recording();
+        self.config = config;
+        let started = if !self.config.record {
+            self.stop_recording();
+            Ok(())
+        } else if restart {
+            self.start_recording()
+        } else {
+            Ok(())
+        };
+        if started.is_err() {
+            self.config.record = false;
+        }
+        if let Some(dir) = self.dir.as_ref() {
+            let json = serde_json::to_string_pretty(&self.config)
+                .into_diagnostic()
+                .wrap_err("failed to serialize mumble manager config")?;
+            dir.write(Self::CONFIG_FILE_NAME, json)
+                .into_diagnostic()
+                .wrap_err("failed to write mumble manager config")?;
+        }
+        started
+    }
+    /// Starts appending the links to the csv file at [MumbleManagerConfig::recorder_path] inside the data dir. Replaces any previous recorder.
+    pub fn start_recording(&mut self) -> Result<()> {
+        let dir = self
+            .dir
+            .as_ref()
+            .ok_or_else(|| miette::miette!("no data dir to record into. see load_config"))?;
+        self.recorder = Some(MumbleRecorder::new(dir, &self.config.recorder_path)?);
+        Ok(())
+    }
+    /// stops the recorder (if any) and flushes its remaining rows
+    pub fn stop_recording(&mut self) {
+        self.recorder = None;
+    }
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+    /// queues a frame for the mock backend. see [mock::MumbleMockImpl]
+    #[cfg(feature = "mock")]
+    pub fn push_mock_frame(&mut self, frame: ctypes::CMumbleLink) {
+        self.backend.push_frame(frame);
+    }
+    /// queues a disconnect for the mock backend. see [mock::MumbleMockImpl::push_disconnect]
+    #[cfg(feature = "mock")]
+    pub fn push_mock_disconnect(&mut self) {
+        self.backend.push_disconnect();
+    }
+    /// queues a failing tick for the mock backend. see [mock::MumbleMockImpl::push_error]
+    #[cfg(feature = "mock")]
+    pub fn push_mock_error(&mut self, message: impl Into<String>) {
+        self.backend.push_error(message);
+    }
+    /// rebuilds the identity out of the latest link. used as a fallback when the identity of the current frame can't be parsed
+    fn previous_identity(&self) -> ctypes::CIdentity {
+        ctypes::CIdentity {
+            name: self.link.name.clone(),
+            race: self.link.race_id,
+            map_id: self.link.map_id,
+            commander: self.link.commander,
+            fov: self.link.fov,
+            uisz: self.link.uisz as u32,
+            ..Default::default()
+        }
+    }
+    /// more precise than just checking if [Self::tick] returns a link. useful to show why we are not getting any data
+    pub fn liveness(&self) -> LinkLiveness {
+        self.backend.liveness()
+    }
+    /// Returns true if the ui_tick didn't advance for atleast `stale_threshold` duration before `now`.
+    /// The link is still returned from [Self::tick], but the data is frozen (game is paused/alt-tabbed/in a loading screen or closed)
+    pub fn is_stale(&self, now: Instant) -> bool {
+        !self.editable
+            && now.saturating_duration_since(self.last_ui_tick_change) >= self.stale_threshold
+    }
+    /// how long to wait before reading the backend again. [Self::tick] waits this long between reads with [Self::set_adaptive_polling]. see [LinkLiveness::poll_interval].
+    /// The edited link is always considered live.
+    pub fn poll_interval(&self, now: Instant) -> Duration {
+        if self.editable {
+            return ACTIVE_POLL_INTERVAL;
+        }
+        self.liveness().poll_interval(self.is_stale(now))
+    }
+    /// A convenience wrapper around [Self::tick] for small tools/tests which just want to read the latest link once.
+    /// ```no_run
+    /// let mut mumble = jokolink::MumbleManager::new(
+    ///     jokolink::DEFAULT_MUMBLELINK_NAME,
+    ///     None,
+    ///     std::time::Duration::from_secs(10),
+    ///     None,
+    ///     false,
+    /// )
+    /// .unwrap();
+    /// if let Some(link) = mumble.poll().unwrap() {
+    ///     println!("{} is on map {}", link.name, link.map_id);
+    /// }
+    /// ```
+    pub fn poll(&mut self) -> Result<Option<MumbleLink>> {
+        Ok(self.tick()?.map(|link| link.as_ref().clone()))
+    }
     pub fn tick(&mut self) -> Result<Option<Arc<MumbleLink>>> {
+        self.tick_at(Instant::now())
+    }
+    fn tick_at(&mut self, now: Instant) -> Result<Option<Arc<MumbleLink>>> {
+        if self.editable {
+            return Ok(self.tick_edited());
+        }
+        if self.adaptive_polling && now < self.next_read {
+            return Ok(self.tick_skipped());
+        }
+        let link = self.read_backend(now);
+        self.next_read = now + self.poll_interval(now);
+        link
+    }
+    /// A tick which didn't read the backend. so, there are no changes since the previous tick
+    fn tick_skipped(&mut self) -> Option<Arc<MumbleLink>> {
+        if !self.broadcasted || self.only_broadcast_on_change {
+            return None;
+        }
+        if !self.link.changes.is_empty() {
+            let mut cleared = self.link.as_ref().clone();
+            cleared.changes = Default::default();
+            self.link = Arc::new(cleared);
+        }
+        Some(self.link.clone())
+    }
+    fn read_backend(&mut self, now: Instant) -> Result<Option<Arc<MumbleLink>>> {
         if let Err(e) = self.backend.tick() {
-            error!(?e, "mumble backend tick error");
+            let message = e.to_string();
+            if self.backend_error.as_ref() != Some(&message) {
+                error!(
+                    target: UI_NOTIFICATIONS_TARGET,
+                    component = "mumble",
+                    ?e,
+                    "mumble backend failed: {message}"
+                );
+                self.backend_error = Some(message);
+            }
             return Ok(None);
         }
+        self.backend_error = None;
 
         if !self.backend.is_alive() {
             // reset link
             if self.link.ui_tick != 0 {
                 self.link = Arc::new(Default::default());
             }
+            self.broadcasted = false;
+            let dead_since = *self.dead_since.get_or_insert(now);
+            if now.saturating_duration_since(dead_since) >= self.reconnect_after {
+                if let Err(e) = self.reconnect() {
+                    error!(?e, "failed to reconnect mumble link");
+                    // try again after another `reconnect_after`
+                    self.dead_since = Some(now);
+                }
+            }
             return Ok(None);
         }
+        self.dead_since = None;
         // backend is alive and tick is successful. time to get link
         let cml: ctypes::CMumbleLink = self.backend.get_cmumble_link();
         if cml.ui_tick == 0 && self.link.ui_tick != 0 {
             self.link = Arc::new(Default::default());
+            self.broadcasted = false;
         }
 
         if cml.ui_tick == 0 || cml.context.client_pos_size == [0; 4] {
             return Ok(None);
         }
-        let mut changes: BitFlags<MumbleChanges> = Default::default();
-        // safety. as the link is valid, we can use as_ref
-        let json_string = widestring::U16CStr::from_slice_truncate(&cml.identity)
-            .into_diagnostic()
-            .wrap_err("failed to get widestring out of cml identity")?
-            .to_string()
-            .into_diagnostic()
-            .wrap_err("failed to convert widestring to cstring")?;
-
-        let identity: ctypes::CIdentity = from_str(&json_string)
-            .into_diagnostic()
-            .wrap_err("failed to deserialize identity from json string")?;
-        let uisz = identity
-            .get_uisz()
-            .ok_or(miette::miette!("uisz is invalid"))?;
-        let server_address = if cml.context.server_address[0] == 2 {
-            let addr = cml.context.server_address;
-            std::net::Ipv4Addr::new(addr[4], addr[5], addr[6], addr[7]).into()
-        } else {
-            std::net::Ipv4Addr::UNSPECIFIED.into()
-        };
-        if self.link.ui_tick != cml.ui_tick {
-            changes.insert(MumbleChanges::UiTick);
-        }
-        if self.link.name != identity.name {
-            changes.insert(MumbleChanges::Character);
-        }
-        if self.link.map_id != cml.context.map_id {
-            changes.insert(MumbleChanges::Map);
-        }
+        // identity is a json string and might be half written during map loads. Just keep the previous values until it is readable again
+        let identity = cml.get_identity().unwrap_or_else(|e| {
+            trace!(?e, "failed to parse identity. reusing previous identity");
+            self.previous_identity()
+        });
+        let uisz = identity.get_uisz().unwrap_or_else(|| {
+            trace!(identity.uisz, "invalid uisz. reusing previous uisz");
+            self.link.uisz
+        });
+        let server_address = cml
+            .context
+            .get_map_ip()
+            .unwrap_or(std::net::Ipv4Addr::UNSPECIFIED)
+            .into();
         // let window_pos = IVec2::new(
         //     cml.context.window_pos_size[0],
         //     cml.context.window_pos_size[1],
@@ -130,25 +414,22 @@ impl MumbleManager {
             cml.context.client_pos_size[3],
         );
 
-        if self.link.client_pos != client_pos {
-            changes.insert(MumbleChanges::WindowPosition);
-        }
-        if self.link.client_size != client_size {
-            changes.insert(MumbleChanges::WindowSize);
-        }
-        let link = Arc::new(MumbleLink {
+        let mut link = MumbleLink {
             ui_tick: cml.ui_tick,
             player_pos: cml.f_avatar_position.into(),
             f_avatar_front: cml.f_avatar_front.into(),
             cam_pos: cml.f_camera_position.into(),
             f_camera_front: cml.f_camera_front.into(),
+            race_id: identity.race,
+            race: identity.get_race(),
+            commander: identity.commander,
             name: identity.name,
             map_id: cml.context.map_id,
             fov: identity.fov,
             uisz,
             // window_pos,
             // window_size,
-            changes,
+            changes: Default::default(),
             // window_pos_without_borders,
             // window_size_without_borders,
             dpi_scaling: cml.context.dpi_scaling,
@@ -170,21 +451,159 @@ impl MumbleManager {
             map_center_y: cml.context.map_center_y,
             map_scale: cml.context.map_scale,
             process_id: cml.context.process_id,
+            mount_index: cml.context.mount_index,
             mount: Mount::try_from_mumble_link(cml.context.mount_index),
-        });
+            xid: (cml.context.xid != 0).then_some(cml.context.xid),
+        };
+        link.changes = link.changes_from(&self.link);
+        if link.changes.contains(MumbleChanges::UiTick) {
+            self.last_ui_tick_change = now;
+        }
+        let link = Arc::new(link);
         self.link = link.clone();
-        Ok(if self.link.ui_tick == 0 {
-            None
-        } else {
-            Some(link)
-        })
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.record(&link) {
+                error!(?e, "failed to record mumble link. stopping recorder");
+                self.recorder = None;
+                self.config.record = false;
+            }
+        }
+        if self.link.ui_tick == 0
+            || (self.only_broadcast_on_change && self.broadcasted && link.changes.is_empty())
+        {
+            return Ok(None);
+        }
+        self.broadcasted = true;
+        Ok(Some(link))
+    }
+    /// The edits from gui set the changes of the link. So, we return those changes once and clear them for the next tick
+    fn tick_edited(&mut self) -> Option<Arc<MumbleLink>> {
+        let link = self.link.clone();
+        if !link.changes.is_empty() {
+            let mut cleared = link.as_ref().clone();
+            cleared.changes = Default::default();
+            self.link = Arc::new(cleared);
+        } else if self.only_broadcast_on_change && self.broadcasted {
+            return None;
+        }
+        self.broadcasted = true;
+        Some(link)
+    }
+    /// Switches between the link from gw2 and the link edited in gui.
+    /// When we stop editing, the link is reset, so that the next link from backend reports all the changes
+    pub fn set_editable(&mut self, editable: bool) {
+        if self.editable && !editable {
+            self.link = Arc::new(Default::default());
+            self.broadcasted = false;
+        }
+        self.editable = editable;
+    }
+    pub fn is_editable(&self) -> bool {
+        self.editable
+    }
+    /// loads the presets file from `dir` and enables presets in the editable mode of gui. see [MumblePresets]
+    pub fn load_presets(&mut self, dir: Arc<cap_std::fs_utf8::Dir>) -> Result<()> {
+        self.presets = Some(MumblePresets::new(dir)?);
+        Ok(())
+    }
+    /// replaces the editable link with the preset. The fields which differ from the current link are reported as changes in the next tick
+    pub fn apply_preset(&mut self, name: &str) {
+        if let Some(preset) = self.presets.as_ref().and_then(|p| p.get(name)) {
+            let mut link = preset.clone();
+            link.changes = self.link.changes | link.changes_from(&self.link);
+            self.link = Arc::new(link);
+        }
+    }
+    fn presets_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(presets) = self.presets.as_mut() else {
+            return;
+        };
+        let mut selected = None;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("presets")
+                .selected_text("load preset")
+                .show_ui(ui, |ui| {
+                    for name in presets.names() {
+                        if ui.selectable_label(false, name).clicked() {
+                            selected = Some(name.to_string());
+                        }
+                    }
+                });
+            ui.text_edit_singleline(&mut self.preset_name);
+            if ui.button("save preset").clicked() && !self.preset_name.is_empty() {
+                if let Err(e) = presets.insert(self.preset_name.clone(), self.link.as_ref().clone())
+                {
+                    error!(?e, "failed to save mumble preset");
+                }
+            }
+            if ui.button("delete preset").clicked() {
+                if let Err(e) = presets.remove(&self.preset_name) {
+                    error!(?e, "failed to delete mumble preset");
+                }
+            }
+        });
+        if let Some(name) = selected {
+            self.preset_name = name.clone();
+            self.apply_preset(&name);
+        }
     }
     pub fn gui(&mut self, etx: &egui::Context, open: &mut bool) {
         egui::Window::new("Mumble Manager")
             .open(open)
             .show(etx, |ui| {
-                if self.link.ui_tick == 0 {
-                    ui.label("Mumble is not initialized");
+                let mut config = self.config.clone();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut config.record, "record").on_hover_text(
+                        "appends a csv row to this file in the jokolay data dir whenever the link changes",
+                    );
+                    ui.add_enabled(
+                        !config.record,
+                        egui::TextEdit::singleline(&mut config.recorder_path),
+                    );
+                });
+                if config != self.config {
+                    if let Err(e) = self.set_config(config) {
+                        error!(?e, "failed to apply mumble manager config");
+                    }
+                }
+                if ui
+                    .button("copy link as json")
+                    .on_hover_text("useful for bug reports")
+                    .clicked()
+                {
+                    match serde_json::to_string_pretty(self.link.as_ref()) {
+                        Ok(json) => ui.output_mut(|o| o.copied_text = json),
+                        Err(e) => error!(?e, "failed to serialize mumble link"),
+                    }
+                }
+                let mut editable = self.editable;
+                ui.checkbox(&mut editable, "edit link").on_hover_text(
+                    "ignore gw2 and use the values edited below instead. useful for testing markers without the game",
+                );
+                self.set_editable(editable);
+                if self.editable {
+                    self.presets_ui(ui);
+                    let mut edited = mumble_ui(ui, self.link.as_ref().clone());
+                    // pending changes from previous edits (which are not yet returned from tick) are kept too
+                    if edited != *self.link {
+                        edited.changes |= edited.changes_from(&self.link);
+                        self.link = Arc::new(edited);
+                    }
+                } else if self.link.ui_tick == 0 {
+                    ui.label(match self.liveness() {
+                        LinkLiveness::NoBridge => "jokolink is not running",
+                        LinkLiveness::NoGame => "gw2 is not running",
+                        LinkLiveness::Live => "Mumble is not initialized",
+                    });
+                } else if self.is_stale(Instant::now()) {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!(
+                            "ui tick didn't change for more than {:?}. link is stale",
+                            self.stale_threshold
+                        ),
+                    );
+                    mumble_ui(ui, self.link.as_ref().clone());
                 } else {
                     let link: MumbleLink = self.link.as_ref().clone();
                     mumble_ui(ui, link);
@@ -193,7 +612,8 @@ impl MumbleManager {
     }
 }
 
-fn mumble_ui(ui: &mut egui::Ui, mut link: MumbleLink) {
+/// shows the link in a grid and returns the link with any edits made by the user
+fn mumble_ui(ui: &mut egui::Ui, mut link: MumbleLink) -> MumbleLink {
     egui::Grid::new("link grid")
         .num_columns(2)
         .striped(true)
@@ -241,6 +661,9 @@ fn mumble_ui(ui: &mut egui::Ui, mut link: MumbleLink) {
             ui.label("character");
             ui.label(&link.name);
             ui.end_row();
+            ui.label("race");
+            ui.label(link.race.as_ref());
+            ui.end_row();
             ui.label("map id");
             ui.add(DragValue::new(&mut link.map_id));
             ui.end_row();
@@ -257,7 +680,30 @@ fn mumble_ui(ui: &mut egui::Ui, mut link: MumbleLink) {
             ui.add(DragValue::new(&mut link.shard_id));
             ui.end_row();
             ui.label("mount");
-            ui.label(format!("{:?}", link.mount));
+            ui.label(
+                link.mount
+                    .as_ref()
+                    .map(|mount| mount.as_ref())
+                    .unwrap_or("none"),
+            );
+            ui.end_row();
+            ui.label("ui state");
+            ui.add_enabled_ui(false, |ui| {
+                ui.vertical(|ui| {
+                    for (mut state, name) in [
+                        (link.is_map_open(), "map open"),
+                        (link.is_compass_top_right(), "compass top right"),
+                        (link.is_compass_rotation_enabled(), "compass rotation"),
+                        (link.game_has_focus(), "game has focus"),
+                        (link.is_in_competitive_mode(), "competitive mode"),
+                        (link.is_textbox_focused(), "textbox focus"),
+                        (link.is_in_combat(), "in combat"),
+                        (link.is_commander(), "commander"),
+                    ] {
+                        ui.checkbox(&mut state, name);
+                    }
+                });
+            });
             ui.end_row();
             ui.label("client pos");
             ui.horizontal(|ui| {
@@ -277,6 +723,13 @@ fn mumble_ui(ui: &mut egui::Ui, mut link: MumbleLink) {
             ui.label("dpi");
             ui.add(DragValue::new(&mut link.dpi));
             ui.end_row();
+            ui.label("xid");
+            ui.label(
+                link.xid
+                    .map(|xid| xid.to_string())
+                    .unwrap_or_else(|| "none".to_string()),
+            );
+            ui.end_row();
 
             // ui.label("position");
             // ui.horizontal(|ui| {
@@ -303,4 +756,5 @@ fn mumble_ui(ui: &mut egui::Ui, mut link: MumbleLink) {
             // });
             // ui.end_row();
         });
+    link
 }
@@ -1,7 +1,11 @@
-use crate::ctypes::{CMumbleLink, C_MUMBLE_LINK_SIZE_FULL};
-use miette::{Context, IntoDiagnostic, Result};
+use crate::ctypes::{
+    CMumbleContext, CMumbleLink, C_MUMBLE_LINK_SIZE_FULL, USEFUL_C_MUMBLE_LINK_SIZE,
+};
+use crate::LinkLiveness;
+use miette::{bail, Context, IntoDiagnostic, Result};
 use std::fs::File;
 use std::io::{Read, Seek};
+use std::time::{Duration, Instant};
 use time::OffsetDateTime;
 use tracing::info;
 // use x11rb::protocol::xproto::{change_property, intern_atom, AtomEnum, GetGeometryReply, PropMode};
@@ -13,55 +17,104 @@ pub use x11rb::rust_connection::RustConnection;
 pub struct MumbleLinuxImpl {
     mfile: File,
     link_buffer: LinkBuffer,
+    /// The link decoded from [Self::link_buffer] during the last tick
+    link: CMumbleLink,
     /// we basically use this as the ui_tick of mumblelink
     /// If this changed recently, it means jokolink is running (i.e. gw2 is running)
     previous_jokolink_timestamp: i128,
+    /// If set, we won't read the mumble file more often than this interval
+    refresh_interval: Option<Duration>,
+    /// when did we read the mumble file the last time
+    last_read: Instant,
 }
 
 type LinkBuffer = Box<[u8; C_MUMBLE_LINK_SIZE_FULL]>;
 
 impl MumbleLinuxImpl {
-    pub fn new(link_name: &str) -> Result<Self> {
+    /// byte range of the jokolink timestamp inside the link buffer
+    const TIMESTAMP_RANGE: std::ops::Range<usize> = {
+        let start = std::mem::offset_of!(CMumbleLink, context)
+            + std::mem::offset_of!(CMumbleContext, timestamp);
+        start..start + 16
+    };
+    /// `refresh_interval` rate limits the reads of the mumble file. If None, we read it every tick.
+    pub fn new(link_name: &str, refresh_interval: Option<Duration>) -> Result<Self> {
         let mumble_file_name = format!("/dev/shm/{link_name}");
         info!("creating mumble file at {mumble_file_name}");
         let mut mfile = File::options()
             .read(true)
             .write(true) // write/append is needed for the create flag
             .create(true)
+            // jokolink in wine writes to the same file, so keep whatever is already there
+            .truncate(false)
             .open(&mumble_file_name)
             .into_diagnostic()
             .wrap_err("failed to create mumble file")?;
         let mut link_buffer = LinkBuffer::new([0u8; C_MUMBLE_LINK_SIZE_FULL]);
         mfile.rewind().into_diagnostic()?;
-        mfile
+        let bytes_read = mfile
             .read(link_buffer.as_mut())
             .into_diagnostic()
             .wrap_err("failed to get link buffer from mfile")?;
-        let previous_jokolink_timestamp =
-            unsafe { CMumbleLink::get_timestamp(link_buffer.as_ptr() as _) };
+        // the file might have just been created by us, so it is fine if it is empty.
+        let link = CMumbleLink::from_bytes(&link_buffer[..bytes_read]).unwrap_or_default();
+        let previous_jokolink_timestamp = i128::from_le_bytes(link.context.timestamp);
         Ok(MumbleLinuxImpl {
             mfile,
             link_buffer,
+            link,
             previous_jokolink_timestamp,
+            refresh_interval,
+            last_read: Instant::now(),
         })
     }
     pub fn tick(&mut self) -> Result<()> {
+        if let Some(interval) = self.refresh_interval {
+            if self.last_read.elapsed() < interval {
+                return Ok(());
+            }
+        }
+        self.last_read = Instant::now();
         self.mfile.rewind().into_diagnostic()?;
-        self.mfile
+        let bytes_read = self
+            .mfile
             .read(self.link_buffer.as_mut())
             .into_diagnostic()
             .wrap_err("failed to get link buffer")?;
-        self.previous_jokolink_timestamp =
-            unsafe { CMumbleLink::get_timestamp(self.link_buffer.as_ptr() as _) };
+        // jokolink didn't write the link yet (or the file was just created by us). same as a dead bridge.
+        if bytes_read < USEFUL_C_MUMBLE_LINK_SIZE {
+            self.link = Default::default();
+            self.previous_jokolink_timestamp = 0;
+            return Ok(());
+        }
+        // jokolink writes a new timestamp everytime it copies the link. If it didn't change, there's nothing new to decode.
+        if let Some(timestamp) = self.link_buffer[..bytes_read].get(Self::TIMESTAMP_RANGE) {
+            if timestamp == self.link.context.timestamp {
+                return Ok(());
+            }
+        }
+        self.link = CMumbleLink::from_bytes(&self.link_buffer[..bytes_read])
+            .wrap_err("failed to decode mumble link from mumble file")?;
+        self.previous_jokolink_timestamp = i128::from_le_bytes(self.link.context.timestamp);
         Ok(())
     }
     pub fn is_alive(&self) -> bool {
         OffsetDateTime::now_utc().unix_timestamp_nanos() - self.previous_jokolink_timestamp
             < std::time::Duration::from_secs(1).as_nanos() as i128
     }
+    /// If jokolink didn't write for a while, then it is not running. Otherwise, ui_tick tells us if gw2 initialized mumble link
+    pub fn liveness(&self) -> LinkLiveness {
+        if !self.is_alive() {
+            LinkLiveness::NoBridge
+        } else if self.link.ui_tick == 0 {
+            LinkLiveness::NoGame
+        } else {
+            LinkLiveness::Live
+        }
+    }
     pub fn get_cmumble_link(&self) -> CMumbleLink {
         if self.is_alive() {
-            unsafe { std::ptr::read(self.link_buffer.as_ptr() as _) }
+            self.link
         } else {
             Default::default()
         }
@@ -74,6 +127,38 @@ impl MumbleLinuxImpl {
     // }
 }
 
+/// Gets the position (relative to root window) and size of the window with `xid`. returns `[x, y, width, height]`
+/// xid is zero until jokolink finds the gw2 window, which is an error.
+pub fn get_window_dimensions(xc: &RustConnection, xid: u32) -> Result<[i32; 4]> {
+    if xid == 0 {
+        bail!("gw2 window id is not available yet");
+    }
+    let geometry = x11rb::protocol::xproto::get_geometry(xc, xid)
+        .into_diagnostic()
+        .wrap_err("get geometry fn failed")?
+        .reply()
+        .into_diagnostic()
+        .wrap_err("geometry reply is wrong")?;
+    let translated_coordinates = x11rb::protocol::xproto::translate_coordinates(
+        xc,
+        xid,
+        geometry.root,
+        geometry.x,
+        geometry.y,
+    )
+    .into_diagnostic()
+    .wrap_err("failed to translate coords")?
+    .reply()
+    .into_diagnostic()
+    .wrap_err("translate coords reply error")?;
+    Ok([
+        translated_coordinates.dst_x as i32,
+        translated_coordinates.dst_y as i32,
+        geometry.width as i32,
+        geometry.height as i32,
+    ])
+}
+
 // struct X11Connection {
 //     jokolay_window_id: u32,
 //     transient_for_atom: u32,
@@ -302,3 +387,39 @@ impl MumbleLinuxImpl {
 //     buffer.copy_from_slice(&reply.value);
 //     Ok(u32::from_ne_bytes(buffer))
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_or_short_mumble_file_is_no_bridge() {
+        let name = format!("jokolink_test_empty_{}", std::process::id());
+        let path = format!("/dev/shm/{name}");
+        let _ = std::fs::remove_file(&path);
+        let mut backend = MumbleLinuxImpl::new(&name, None).unwrap();
+        // the file was just created by the backend, so it is empty
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+        backend.tick().unwrap();
+        assert_eq!(backend.liveness(), LinkLiveness::NoBridge);
+
+        // a half written link is not an error either
+        std::fs::write(&path, [1u8; USEFUL_C_MUMBLE_LINK_SIZE - 1]).unwrap();
+        backend.tick().unwrap();
+        assert_eq!(backend.liveness(), LinkLiveness::NoBridge);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn window_dimensions_of_xid_zero_is_an_error() {
+        // no x server is needed, as we must fail before sending any request
+        let (stream, _server) = std::os::unix::net::UnixStream::pair().unwrap();
+        let stream = x11rb::rust_connection::DefaultStream::from_unix_stream(stream).unwrap();
+        let setup = x11rb::protocol::xproto::Setup {
+            resource_id_mask: 0xff,
+            ..Default::default()
+        };
+        let xc = RustConnection::for_connected_stream(stream, setup).unwrap();
+        assert!(get_window_dimensions(&xc, 0).is_err());
+    }
+}
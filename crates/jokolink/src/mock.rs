@@ -0,0 +1,394 @@
+//! A fake backend which replays a scripted sequence of [CMumbleLink] states instead of reading shared memory.
+//! Enabled with the `mock` feature. It replaces the platform backend, so that we can drive [crate::MumbleManager] in tests/CI.
+
+use std::collections::VecDeque;
+
+use miette::Result;
+
+use crate::{ctypes::CMumbleLink, LinkLiveness};
+
+/// A scripted step which is played by a tick
+enum MockStep {
+    Frame(Box<CMumbleLink>),
+    /// fails the tick with this message
+    Error(String),
+    /// the handle dies, like the shared memory disappearing when gw2 is closed. Only a new backend sees the next steps
+    Disconnect,
+}
+
+pub struct MumbleMockImpl {
+    /// steps which are yet to be played. every tick pops one step
+    frames: VecDeque<MockStep>,
+    /// The frame played during the last tick. once we run out of frames, we just keep returning this one
+    link: CMumbleLink,
+    /// set by [MockStep::Disconnect]
+    dead: bool,
+}
+
+impl MumbleMockImpl {
+    pub fn new(_link_name: &str) -> Result<Self> {
+        Ok(Self {
+            frames: Default::default(),
+            link: Default::default(),
+            dead: false,
+        })
+    }
+    /// queues a frame to be played after all the existing frames
+    pub fn push_frame(&mut self, frame: CMumbleLink) {
+        self.frames.push_back(MockStep::Frame(Box::new(frame)));
+    }
+    /// queues a tick which fails with `message` like a broken shared memory would
+    pub fn push_error(&mut self, message: impl Into<String>) {
+        self.frames.push_back(MockStep::Error(message.into()));
+    }
+    /// queues a tick after which the backend is dead until it is recreated
+    pub fn push_disconnect(&mut self) {
+        self.frames.push_back(MockStep::Disconnect);
+    }
+    /// moves the remaining steps of `old` into this backend. The steps play the role of the shared memory, which outlives the handles
+    pub fn take_steps_from(&mut self, old: &mut Self) {
+        self.frames = std::mem::take(&mut old.frames);
+    }
+    pub fn tick(&mut self) -> Result<()> {
+        if self.dead {
+            return Ok(());
+        }
+        match self.frames.pop_front() {
+            Some(MockStep::Frame(frame)) => self.link = *frame,
+            Some(MockStep::Error(message)) => miette::bail!("{message}"),
+            Some(MockStep::Disconnect) => {
+                self.dead = true;
+                self.link = Default::default();
+            }
+            None => {}
+        }
+        Ok(())
+    }
+    /// The mock only "dies" after a [MockStep::Disconnect]
+    pub fn is_alive(&self) -> bool {
+        !self.dead
+    }
+    pub fn liveness(&self) -> LinkLiveness {
+        if self.dead || self.link.ui_tick == 0 {
+            LinkLiveness::NoGame
+        } else {
+            LinkLiveness::Live
+        }
+    }
+    pub fn get_cmumble_link(&self) -> CMumbleLink {
+        self.link
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use tracing_subscriber::{layer::SubscriberExt, Layer};
+
+    use crate::{
+        ctypes::CIdentity, MumbleChanges, MumbleManager, UISize, ACTIVE_POLL_INTERVAL,
+        IDLE_POLL_INTERVAL, UI_NOTIFICATIONS_TARGET,
+    };
+
+    use super::*;
+
+    fn frame(ui_tick: u32, name: &str, map_id: u32) -> CMumbleLink {
+        frame_with_fov(ui_tick, name, map_id, 1.0)
+    }
+    fn frame_with_fov(ui_tick: u32, name: &str, map_id: u32, fov: f32) -> CMumbleLink {
+        let mut cml = CMumbleLink {
+            ui_tick,
+            ..Default::default()
+        };
+        cml.set_identity(&CIdentity {
+            name: name.to_string(),
+            map_id,
+            uisz: 1,
+            fov,
+            ..Default::default()
+        })
+        .unwrap();
+        cml.context.map_id = map_id;
+        cml.context.client_pos_size = [0, 0, 1920, 1080];
+        cml
+    }
+
+    #[test]
+    fn map_and_character_changes() {
+        let mut mm =
+            MumbleManager::new("MockLink", None, Duration::from_secs(10), None, false).unwrap();
+        mm.push_mock_frame(frame(1, "first", 15));
+        mm.push_mock_frame(frame(2, "first", 15));
+        mm.push_mock_frame(frame(3, "first", 50));
+        mm.push_mock_frame(frame(4, "second", 50));
+        mm.push_mock_frame(frame(4, "second", 50));
+
+        let link = mm.tick().unwrap().unwrap();
+        assert!(link.changes.contains(MumbleChanges::UiTick));
+        assert!(link.changes.contains(MumbleChanges::Character));
+        assert!(link.changes.contains(MumbleChanges::Map));
+
+        let link = mm.tick().unwrap().unwrap();
+        assert_eq!(link.changes, MumbleChanges::UiTick);
+
+        let link = mm.tick().unwrap().unwrap();
+        assert_eq!(link.changes, MumbleChanges::UiTick | MumbleChanges::Map);
+        assert_eq!(link.map_id, 50);
+
+        let link = mm.tick().unwrap().unwrap();
+        assert_eq!(
+            link.changes,
+            MumbleChanges::UiTick | MumbleChanges::Character
+        );
+        assert_eq!(link.name, "second");
+
+        let link = mm.tick().unwrap().unwrap();
+        assert!(link.changes.is_empty());
+    }
+
+    #[test]
+    fn fov_mount_and_compass_changes() {
+        let mut mm =
+            MumbleManager::new("MockLink", None, Duration::from_secs(10), None, false).unwrap();
+        mm.push_mock_frame(frame(1, "first", 15));
+        mm.push_mock_frame(frame_with_fov(2, "first", 15, 1.2));
+        let mut mounted = frame_with_fov(3, "first", 15, 1.2);
+        mounted.context.mount_index = 1;
+        mm.push_mock_frame(mounted);
+        let mut compass = mounted;
+        compass.ui_tick = 4;
+        compass.context.compass_width = 300;
+        mm.push_mock_frame(compass);
+        let mut rotated = compass;
+        rotated.ui_tick = 5;
+        rotated.context.compass_rotation = 1.0;
+        mm.push_mock_frame(rotated);
+
+        mm.tick().unwrap().unwrap();
+        let link = mm.tick().unwrap().unwrap();
+        assert_eq!(link.changes, MumbleChanges::UiTick | MumbleChanges::Fov);
+        let link = mm.tick().unwrap().unwrap();
+        assert_eq!(link.changes, MumbleChanges::UiTick | MumbleChanges::Mount);
+        let link = mm.tick().unwrap().unwrap();
+        assert_eq!(link.changes, MumbleChanges::UiTick | MumbleChanges::Compass);
+        let link = mm.tick().unwrap().unwrap();
+        assert_eq!(link.changes, MumbleChanges::UiTick | MumbleChanges::Compass);
+    }
+
+    #[test]
+    fn malformed_identity_keeps_previous_identity() {
+        let mut mm =
+            MumbleManager::new("MockLink", None, Duration::from_secs(10), None, false).unwrap();
+        let mut first = frame_with_fov(1, "first", 15, 1.2);
+        first
+            .set_identity(&CIdentity {
+                name: "first".to_string(),
+                map_id: 15,
+                uisz: 2,
+                fov: 1.2,
+                ..Default::default()
+            })
+            .unwrap();
+        mm.push_mock_frame(first);
+        let mut truncated = frame(2, "second", 50);
+        truncated.identity[10..].fill(0);
+        mm.push_mock_frame(truncated);
+        let mut invalid_uisz = frame(3, "third", 50);
+        invalid_uisz
+            .set_identity(&CIdentity {
+                name: "third".to_string(),
+                map_id: 50,
+                uisz: 42,
+                fov: 1.2,
+                ..Default::default()
+            })
+            .unwrap();
+        mm.push_mock_frame(invalid_uisz);
+
+        mm.tick().unwrap().unwrap();
+        let link = mm.tick().unwrap().unwrap();
+        assert_eq!(link.name, "first");
+        assert_eq!(link.fov, 1.2);
+        assert_eq!(link.uisz, UISize::Large);
+        assert_eq!(link.map_id, 50);
+        assert_eq!(link.changes, MumbleChanges::UiTick | MumbleChanges::Map);
+        // the rest of the identity is still used, only the uisz falls back
+        let link = mm.tick().unwrap().unwrap();
+        assert_eq!(link.name, "third");
+        assert_eq!(link.uisz, UISize::Large);
+    }
+
+    /// collects the targets of all events
+    struct TargetsLayer(Arc<Mutex<Vec<String>>>);
+    impl<S: tracing::Subscriber> Layer<S> for TargetsLayer {
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(event.metadata().target().to_string());
+        }
+    }
+
+    #[test]
+    fn backend_errors_are_notified_once() {
+        let targets = Arc::new(Mutex::new(vec![]));
+        let subscriber = tracing_subscriber::registry().with(TargetsLayer(targets.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            let mut mm =
+                MumbleManager::new("MockLink", None, Duration::from_secs(10), None, false).unwrap();
+            mm.push_mock_error("shared memory is gone");
+            mm.push_mock_error("shared memory is gone");
+            mm.push_mock_frame(frame(1, "first", 15));
+            mm.push_mock_error("shared memory is gone");
+
+            assert!(mm.tick().unwrap().is_none());
+            assert!(mm.tick().unwrap().is_none());
+            assert!(mm.tick().unwrap().is_some());
+            assert!(mm.tick().unwrap().is_none());
+        });
+        let notifications = targets
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|target| target.as_str() == UI_NOTIFICATIONS_TARGET)
+            .count();
+        // the repeated error is only reported once, until a tick succeeds
+        assert_eq!(notifications, 2);
+    }
+
+    #[test]
+    fn reconnects_after_the_backend_is_dead_for_a_while() {
+        let mut mm =
+            MumbleManager::new("MockLink", None, Duration::from_secs(10), None, false).unwrap();
+        mm.set_reconnect_after(Duration::ZERO);
+        mm.push_mock_frame(frame(1, "first", 15));
+        mm.push_mock_disconnect();
+        mm.push_mock_frame(frame(1, "first", 15));
+
+        assert!(mm.tick().unwrap().is_some());
+        // the shared memory disappeared. reconnecting opens it again
+        assert!(mm.tick().unwrap().is_none());
+        let link = mm.tick().unwrap().unwrap();
+        // the link was reset, so the reopened link reports all the changes again
+        assert!(link.changes.contains(MumbleChanges::Character));
+        assert!(link.changes.contains(MumbleChanges::Map));
+    }
+
+    #[test]
+    fn stays_dead_until_the_reconnect_duration() {
+        let mut mm =
+            MumbleManager::new("MockLink", None, Duration::from_secs(10), None, false).unwrap();
+        mm.set_reconnect_after(Duration::from_secs(60));
+        mm.push_mock_frame(frame(1, "first", 15));
+        mm.push_mock_disconnect();
+        mm.push_mock_frame(frame(2, "first", 15));
+
+        assert!(mm.tick().unwrap().is_some());
+        assert!(mm.tick().unwrap().is_none());
+        assert!(mm.tick().unwrap().is_none());
+        // reconnecting manually doesn't wait
+        mm.reconnect().unwrap();
+        assert_eq!(mm.tick().unwrap().unwrap().ui_tick, 2);
+    }
+
+    #[test]
+    fn only_broadcast_on_change() {
+        let mut mm =
+            MumbleManager::new("MockLink", None, Duration::from_secs(10), None, true).unwrap();
+        mm.push_mock_frame(frame(1, "first", 15));
+        mm.push_mock_frame(frame(1, "first", 15));
+        mm.push_mock_frame(frame(2, "first", 15));
+
+        assert!(mm.tick().unwrap().is_some());
+        assert!(mm.tick().unwrap().is_none());
+        assert!(mm.tick().unwrap().is_some());
+    }
+
+    #[test]
+    fn adaptive_polling_skips_reads_until_the_poll_interval() {
+        let mut mm =
+            MumbleManager::new("MockLink", None, Duration::from_secs(10), None, false).unwrap();
+        mm.set_adaptive_polling(true);
+        mm.push_mock_frame(frame(1, "first", 15));
+        mm.push_mock_frame(frame(2, "first", 50));
+        let start = std::time::Instant::now();
+
+        let link = mm.tick_at(start).unwrap().unwrap();
+        assert_eq!(link.ui_tick, 1);
+        assert!(link.changes.contains(MumbleChanges::Map));
+        // too early. the same link without changes and the next frame is still queued
+        let link = mm
+            .tick_at(start + ACTIVE_POLL_INTERVAL / 2)
+            .unwrap()
+            .unwrap();
+        assert_eq!(link.ui_tick, 1);
+        assert!(link.changes.is_empty());
+        let link = mm.tick_at(start + ACTIVE_POLL_INTERVAL).unwrap().unwrap();
+        assert_eq!(link.ui_tick, 2);
+        assert!(link.changes.contains(MumbleChanges::Map));
+    }
+
+    #[test]
+    fn adaptive_polling_backs_off_without_game() {
+        let mut mm =
+            MumbleManager::new("MockLink", None, Duration::from_secs(10), None, false).unwrap();
+        mm.set_adaptive_polling(true);
+        let start = std::time::Instant::now();
+        assert!(mm.tick_at(start).unwrap().is_none());
+
+        mm.push_mock_frame(frame(1, "first", 15));
+        // there was no game during the previous read, so the frame is only read after the idle interval
+        assert!(mm.tick_at(start + ACTIVE_POLL_INTERVAL).unwrap().is_none());
+        let link = mm.tick_at(start + IDLE_POLL_INTERVAL).unwrap().unwrap();
+        assert_eq!(link.ui_tick, 1);
+    }
+
+    #[test]
+    fn recorder_config_is_saved_in_the_data_dir() {
+        use cap_std::{
+            ambient_authority,
+            fs_utf8::{camino::Utf8PathBuf, Dir},
+        };
+
+        use crate::MumbleManagerConfig;
+
+        let path = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("jokolink_config_test_{}", std::process::id()));
+        Dir::create_ambient_dir_all(&path, ambient_authority()).unwrap();
+        let dir = Arc::new(Dir::open_ambient_dir(&path, ambient_authority()).unwrap());
+
+        let mut mm =
+            MumbleManager::new("MockLink", None, Duration::from_secs(10), None, false).unwrap();
+        mm.load_config(dir.clone()).unwrap();
+        assert_eq!(mm.config(), &MumbleManagerConfig::default());
+        assert!(!mm.is_recording());
+        mm.set_config(MumbleManagerConfig {
+            record: true,
+            recorder_path: "links.csv".to_string(),
+        })
+        .unwrap();
+        mm.push_mock_frame(frame(1, "first", 15));
+        mm.tick().unwrap().unwrap();
+        mm.stop_recording();
+        // the header and a row for the link
+        assert_eq!(dir.read_to_string("links.csv").unwrap().lines().count(), 2);
+
+        // a new manager starts recording again with the saved config
+        let mut mm =
+            MumbleManager::new("MockLink", None, Duration::from_secs(10), None, false).unwrap();
+        mm.load_config(dir).unwrap();
+        assert!(mm.is_recording());
+        assert_eq!(mm.config().recorder_path, "links.csv");
+        mm.stop_recording();
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -1,6 +1,6 @@
 use enumflags2::BitFlags;
 use jokoapi::end_point::{mounts::Mount, races::Race};
-use miette::bail;
+use miette::{bail, IntoDiagnostic, Result, WrapErr};
 use serde::{Deserialize, Serialize};
 
 use crate::{UISize, UIState};
@@ -69,6 +69,61 @@ impl Default for CMumbleLink {
     }
 }
 impl CMumbleLink {
+    /// Decodes the link from raw bytes (eg: the contents of `/dev/shm/MumbleLink`).
+    /// Unlike [Self::get_cmumble_link], this checks that `bytes` contains atleast [USEFUL_C_MUMBLE_LINK_SIZE] bytes
+    /// and that the identity/context regions fit inside it before reading.
+    /// The bytes beyond `bytes.len()` (upto [C_MUMBLE_LINK_SIZE_FULL]) are zeroed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<CMumbleLink> {
+        const IDENTITY_END: usize =
+            std::mem::offset_of!(CMumbleLink, identity) + std::mem::size_of::<[u16; 256]>();
+        const CONTEXT_USEFUL_END: usize = std::mem::offset_of!(CMumbleLink, context)
+            + std::mem::offset_of!(CMumbleContext, padding);
+        if bytes.len() < USEFUL_C_MUMBLE_LINK_SIZE {
+            bail!(
+                "mumble link buffer is too small. expected atleast {USEFUL_C_MUMBLE_LINK_SIZE} bytes, but got {} bytes",
+                bytes.len()
+            );
+        }
+        if bytes.len() < IDENTITY_END || bytes.len() < CONTEXT_USEFUL_END {
+            bail!(
+                "mumble link buffer of {} bytes doesn't contain the identity (ends at {IDENTITY_END}) and context (ends at {CONTEXT_USEFUL_END})",
+                bytes.len()
+            );
+        }
+        let mut buffer = [0u8; C_MUMBLE_LINK_SIZE_FULL];
+        let len = bytes.len().min(C_MUMBLE_LINK_SIZE_FULL);
+        buffer[..len].copy_from_slice(&bytes[..len]);
+        // safety: buffer is exactly the size of CMumbleLink and every bit pattern is a valid CMumbleLink as it only contains integers/floats
+        Ok(unsafe { std::ptr::read_unaligned(buffer.as_ptr() as *const CMumbleLink) })
+    }
+    /// Decodes the null terminated widestring json in [Self::identity]
+    pub fn get_identity(&self) -> Result<CIdentity> {
+        let json_string = widestring::U16CStr::from_slice_truncate(&self.identity)
+            .into_diagnostic()
+            .wrap_err("failed to get widestring out of cml identity")?
+            .to_string()
+            .into_diagnostic()
+            .wrap_err("failed to convert widestring to cstring")?;
+        serde_json::from_str(&json_string)
+            .into_diagnostic()
+            .wrap_err("failed to deserialize identity from json string")
+    }
+    /// Encodes `identity` as a null terminated widestring json into [Self::identity]. The inverse of [Self::get_identity]
+    pub fn set_identity(&mut self, identity: &CIdentity) -> Result<()> {
+        let json_string = serde_json::to_string(identity)
+            .into_diagnostic()
+            .wrap_err("failed to serialize identity")?;
+        let wide = widestring::U16CString::from_str(json_string)
+            .into_diagnostic()
+            .wrap_err("failed to convert identity json to widestring")?;
+        let wide = wide.as_slice_with_nul();
+        if wide.len() > self.identity.len() {
+            bail!("identity json is too long: {} u16s", wide.len());
+        }
+        self.identity = [0; 256];
+        self.identity[..wide.len()].copy_from_slice(wide);
+        Ok(())
+    }
     /// This takes a point and reads out the CMumbleLink struct from it. wrapper for unsafe ptr read
     pub fn get_cmumble_link(link_ptr: *const CMumbleLink) -> CMumbleLink {
         unsafe { std::ptr::read_volatile(link_ptr) }
@@ -187,8 +242,8 @@ pub struct CMumbleContext {
     pub dpi: i32,
     /// This is the client (gw2 window's viewport/surface) position and area. This tells jokolay where to position and size itself to match gw2 window.
     pub client_pos_size: [i32; 4],
-    /// to make the struct the right size. everything upto now is 120 bytes, so this rounds upto 256 bytes.
-    pub padding: [u8; 96],
+    /// to make the struct the right size. everything upto now is 132 bytes, so this rounds upto 256 bytes.
+    pub padding: [u8; 124],
 }
 impl Default for CMumbleContext {
     fn default() -> Self {
@@ -213,7 +268,7 @@ impl Default for CMumbleContext {
             mount_index: Default::default(),
             timestamp: Default::default(),
             // window_pos_size: Default::default(),
-            padding: [0; 96],
+            padding: [0; 124],
             xid: Default::default(),
             // window_pos_size_without_borders: Default::default(),
             dpi_scaling: Default::default(),
@@ -244,19 +299,7 @@ impl CMumbleContext {
     }
 
     pub fn get_mount(&self) -> Option<Mount> {
-        Some(match self.mount_index {
-            1 => Mount::Jackal,
-            2 => Mount::Griffon,
-            3 => Mount::Springer,
-            4 => Mount::Skimmer,
-            5 => Mount::Raptor,
-            6 => Mount::RollerBeetle,
-            7 => Mount::Warclaw,
-            8 => Mount::Skyscale,
-            9 => Mount::Skiff,
-            10 => Mount::SiegeTurtle,
-            _ => return None,
-        })
+        Mount::try_from_mumble_link(self.mount_index)
     }
 }
 
@@ -299,14 +342,74 @@ impl CIdentity {
             _ => return None,
         })
     }
-    pub fn get_race(&self) -> Option<Race> {
-        Some(match self.race {
-            0 => Race::ASURA,
-            1 => Race::CHARR,
-            2 => Race::HUMAN,
-            3 => Race::NORN,
-            4 => Race::SYLVARI,
-            _ => return None,
-        })
+    pub fn get_race(&self) -> Race {
+        Race::from_mumble_link(self.race)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_fills_the_256_bytes_reserved_by_mumble() {
+        assert_eq!(std::mem::size_of::<CMumbleContext>(), 256);
+        assert_eq!(std::mem::offset_of!(CMumbleContext, mount_index), 84);
+        assert_eq!(std::mem::offset_of!(CMumbleContext, timestamp), 85);
+        assert_eq!(std::mem::offset_of!(CMumbleContext, xid), 104);
+        assert_eq!(std::mem::offset_of!(CMumbleContext, client_pos_size), 116);
+        assert_eq!(std::mem::offset_of!(CMumbleContext, padding), 132);
+        assert_eq!(std::mem::offset_of!(CMumbleLink, context), 1108);
+        assert_eq!(C_MUMBLE_LINK_SIZE_FULL, 1108 + 256);
+        // must not panic
+        let _ = CMumbleLink::default();
+    }
+
+    fn as_bytes(link: &CMumbleLink) -> &[u8] {
+        // safety: CMumbleLink is repr(C) without padding and only contains integers/floats
+        unsafe {
+            std::slice::from_raw_parts(
+                link as *const CMumbleLink as *const u8,
+                C_MUMBLE_LINK_SIZE_FULL,
+            )
+        }
+    }
+
+    #[test]
+    fn short_buffer_is_an_error() {
+        let link = CMumbleLink::default();
+        let bytes = as_bytes(&link);
+        assert!(CMumbleLink::from_bytes(&[]).is_err());
+        assert!(CMumbleLink::from_bytes(&bytes[..USEFUL_C_MUMBLE_LINK_SIZE - 1]).is_err());
+    }
+
+    #[test]
+    fn full_buffer_round_trips() {
+        let mut link = CMumbleLink {
+            ui_version: 2,
+            ui_tick: 42,
+            f_avatar_position: [1.0, 2.0, 3.0],
+            f_camera_front: [0.0, 0.0, 1.0],
+            ..Default::default()
+        };
+        link.context.map_id = 15;
+        link.context.ui_state = 0b101;
+        link.context.client_pos_size = [0, 0, 1920, 1080];
+        let identity = CIdentity {
+            name: "character".to_string(),
+            map_id: 15,
+            uisz: 2,
+            fov: 1.2,
+            ..Default::default()
+        };
+        link.set_identity(&identity).unwrap();
+
+        let decoded = CMumbleLink::from_bytes(as_bytes(&link)).unwrap();
+        assert_eq!(as_bytes(&decoded), as_bytes(&link));
+        assert_eq!(decoded.ui_tick, 42);
+        assert_eq!(decoded.f_avatar_position, [1.0, 2.0, 3.0]);
+        assert_eq!(decoded.context.map_id, 15);
+        assert_eq!(decoded.context.client_pos_size, [0, 0, 1920, 1080]);
+        assert_eq!(decoded.get_identity().unwrap(), identity);
     }
 }
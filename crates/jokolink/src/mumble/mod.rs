@@ -1,18 +1,19 @@
 #![allow(clippy::not_unsafe_ptr_arg_deref)]
 
 pub mod ctypes;
-use std::net::IpAddr;
+use std::{net::IpAddr, time::Duration};
 
 use enumflags2::{bitflags, BitFlags};
-use glam::{IVec2, Vec3};
-use jokoapi::end_point::mounts::Mount;
+use glam::{IVec2, UVec2, Vec2, Vec3};
+use jokoapi::end_point::{mounts::Mount, races::Race};
 use num_derive::FromPrimitive;
 use num_derive::ToPrimitive;
 use serde::Deserialize;
 use serde::Serialize;
 
 /// As the CMumbleLink has all the fields multiple
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "serde")]
 pub struct MumbleLink {
     /// ui tick. (more or less represents the frame number of gw2)
     pub ui_tick: u32,
@@ -26,6 +27,12 @@ pub struct MumbleLink {
     pub f_camera_front: Vec3,
     /// The name of the character
     pub name: String,
+    /// The raw race id from identity. does not match gw2 api
+    pub race_id: u32,
+    /// The race of the character decoded from [Self::race_id]. [Race::UNKNOWN] if we don't know the id
+    pub race: Race,
+    /// Whether the character has a commander tag active
+    pub commander: bool,
     /// API:2/maps
     pub map_id: u32,
     pub map_type: u32,
@@ -53,8 +60,11 @@ pub struct MumbleLink {
     /// On linux, this is useless because this is the process ID inside wine, and not the actual linux pid
     /// But, the jokolink binary uses this to get the window handle and then the X Window ID of gw2
     pub process_id: u32,
+    /// The raw mount index from the context. 0 means not mounted. does not match gw2 api
+    pub mount_index: u8,
     /// refers to [Mount]
-    /// Identifies whether the character is currently mounted, if so, identifies the specific mount. does not match gw2 api
+    /// Identifies whether the character is currently mounted, if so, identifies the specific mount.
+    /// decoded from [Self::mount_index]. None if not mounted (or if the index is unknown)
     pub mount: Option<Mount>,
 
     /// Vertical field-of-view
@@ -77,6 +87,8 @@ pub struct MumbleLink {
     pub client_pos: IVec2,
     /// This is the size of gw2's viewport (width/height) in screen coordinates
     pub client_size: IVec2,
+    /// linux only. The X11 window id of gw2 written by jokolink. None if jokolink couldn't find it (or on windows)
+    pub xid: Option<u32>,
     /// changes since last mumble link update
     pub changes: BitFlags<MumbleChanges>,
 }
@@ -89,6 +101,9 @@ impl Default for MumbleLink {
             cam_pos: Default::default(),
             f_camera_front: Default::default(),
             name: Default::default(),
+            race_id: Default::default(),
+            race: Race::UNKNOWN,
+            commander: Default::default(),
             map_id: Default::default(),
             map_type: Default::default(),
             server_address: std::net::Ipv4Addr::UNSPECIFIED.into(),
@@ -105,6 +120,7 @@ impl Default for MumbleLink {
             map_center_y: Default::default(),
             map_scale: Default::default(),
             process_id: Default::default(),
+            mount_index: Default::default(),
             mount: Default::default(),
             fov: Default::default(),
             uisz: Default::default(),
@@ -112,20 +128,170 @@ impl Default for MumbleLink {
             dpi_scaling: Default::default(),
             client_pos: Default::default(),
             client_size: Default::default(),
+            xid: Default::default(),
             changes: Default::default(),
         }
     }
 }
+/// one continent coordinate unit is 24 inches. and mumble positions are in meters.
+const CONTINENT_UNITS_PER_METER: f32 = 39.37008 / 24.0;
+impl MumbleLink {
+    /// The position of the player in continent coordinates (same as the gw2 api maps/continents endpoints).
+    pub fn map_position(&self) -> Vec2 {
+        Vec2::new(self.player_x, self.player_y)
+    }
+    /// Projects a world position (in meters, same as [Self::player_pos]) onto continent coordinates.
+    /// As we know both the world and the continent position of the player, we use it as the reference point.
+    /// world x is continent x. but world z (north) is negative continent y, as continent coordinates start from the top left corner.
+    pub fn world_to_map(&self, world: Vec3) -> Vec2 {
+        let offset = world - self.player_pos;
+        self.map_position() + Vec2::new(offset.x, -offset.z) * CONTINENT_UNITS_PER_METER
+    }
+    /// linux only. Queries the X server for the position/size of gw2 window using [Self::xid].
+    /// returns `[x, y, width, height]` in screen coords. None if we don't have a xid
+    #[cfg(target_os = "linux")]
+    pub fn get_x11_window_rect(
+        &self,
+        xc: &crate::linux::RustConnection,
+    ) -> Option<miette::Result<[i32; 4]>> {
+        self.xid
+            .map(|xid| crate::linux::get_window_dimensions(xc, xid))
+    }
+    /// The ratio of [Self::dpi] to the default 96 dpi. eg: 1.5 for 150% scaling. 1.0 if dpi is unknown.
+    /// This is the scaling of the monitor (as seen by windows), and it applies to window geometry even if gw2's [Self::dpi_scaling] setting is disabled.
+    /// [Self::dpi_scaling] only decides whether gw2 scales its *UI elements* by this factor.
+    pub fn dpi_scale_factor(&self) -> f32 {
+        if self.dpi > 0 {
+            self.dpi as f32 / 96.0
+        } else {
+            1.0
+        }
+    }
+    /// gw2's viewport position and size in physical pixels (screen coords). These are just [Self::client_pos] and [Self::client_size].
+    /// This is what GLFW's `set_pos`/`set_size` expect on windows and x11, as jokolay is dpi aware and glfw screen coords match raw pixels there.
+    pub fn physical_client_rect(&self) -> (IVec2, UVec2) {
+        (
+            self.client_pos,
+            self.client_size.max(IVec2::ZERO).as_uvec2(),
+        )
+    }
+    /// gw2's viewport position and size in logical pixels. i.e. [Self::physical_client_rect] divided by [Self::dpi_scale_factor].
+    /// Use this for anything that works in logical points like egui.
+    pub fn logical_client_rect(&self) -> (IVec2, UVec2) {
+        let scale = self.dpi_scale_factor();
+        let (pos, size) = self.physical_client_rect();
+        (
+            (pos.as_vec2() / scale).round().as_ivec2(),
+            (size.as_vec2() / scale).round().as_uvec2(),
+        )
+    }
+    /// compares the fields tracked by [MumbleChanges] with `previous`. Doesn't look at [Self::changes]
+    pub fn changes_from(&self, previous: &MumbleLink) -> BitFlags<MumbleChanges> {
+        let mut changes: BitFlags<MumbleChanges> = Default::default();
+        if self.ui_tick != previous.ui_tick {
+            changes.insert(MumbleChanges::UiTick);
+        }
+        if self.name != previous.name {
+            changes.insert(MumbleChanges::Character);
+        }
+        if self.map_id != previous.map_id {
+            changes.insert(MumbleChanges::Map);
+        }
+        if self.fov != previous.fov {
+            changes.insert(MumbleChanges::Fov);
+        }
+        if self.mount_index != previous.mount_index {
+            changes.insert(MumbleChanges::Mount);
+        }
+        if self.compass_width != previous.compass_width
+            || self.compass_height != previous.compass_height
+            || self.compass_rotation != previous.compass_rotation
+        {
+            changes.insert(MumbleChanges::Compass);
+        }
+        if self.client_pos != previous.client_pos {
+            changes.insert(MumbleChanges::WindowPosition);
+        }
+        if self.client_size != previous.client_size {
+            changes.insert(MumbleChanges::WindowSize);
+        }
+        if self.dpi != previous.dpi || self.dpi_scaling != previous.dpi_scaling {
+            changes.insert(MumbleChanges::Dpi);
+        }
+        changes
+    }
+    /// decodes [Self::ui_state]. unknown bits are ignored
+    pub fn get_ui_state(&self) -> BitFlags<UIState> {
+        BitFlags::from_bits_truncate(self.ui_state)
+    }
+    pub fn is_map_open(&self) -> bool {
+        self.get_ui_state().contains(UIState::IsMapOpen)
+    }
+    pub fn is_compass_top_right(&self) -> bool {
+        self.get_ui_state().contains(UIState::IsCompassTopRight)
+    }
+    pub fn is_compass_rotation_enabled(&self) -> bool {
+        self.get_ui_state()
+            .contains(UIState::DoesCompassHaveRotationEnabled)
+    }
+    pub fn game_has_focus(&self) -> bool {
+        self.get_ui_state().contains(UIState::GameHasFocus)
+    }
+    pub fn is_in_competitive_mode(&self) -> bool {
+        self.get_ui_state().contains(UIState::InCompetitiveGamemode)
+    }
+    pub fn is_textbox_focused(&self) -> bool {
+        self.get_ui_state().contains(UIState::TextboxFocus)
+    }
+    pub fn is_in_combat(&self) -> bool {
+        self.get_ui_state().contains(UIState::IsInCombat)
+    }
+    /// This comes from the identity and not ui_state. but is here for convenience
+    pub fn is_commander(&self) -> bool {
+        self.commander
+    }
+}
+/// Whether we are getting live data from gw2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkLiveness {
+    /// linux only. jokolink (which copies the link from wine into `/dev/shm`) is not running
+    NoBridge,
+    /// gw2 is not running or hasn't initialized mumble link yet
+    NoGame,
+    /// gw2 is running and writing to mumble link
+    Live,
+}
+/// poll interval while gw2 is writing a fresh link every frame
+pub const ACTIVE_POLL_INTERVAL: Duration = Duration::from_millis(5);
+/// poll interval while there's no game or the link is stale, so that we don't waste cpu
+pub const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+impl LinkLiveness {
+    /// how long to wait before polling the link again. we only poll fast if the link is live and not stale
+    pub fn poll_interval(self, stale: bool) -> Duration {
+        match self {
+            LinkLiveness::Live if !stale => ACTIVE_POLL_INTERVAL,
+            _ => IDLE_POLL_INTERVAL,
+        }
+    }
+}
 /// These flags represent the changes in mumble link compared to previous values
 #[bitflags]
 #[repr(u32)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum MumbleChanges {
     UiTick = 1,
     Map = 1 << 1,
     Character = 1 << 2,
     WindowPosition = 1 << 3,
     WindowSize = 1 << 4,
+    /// vertical field of view changed. eg: player changed the fov slider in settings
+    Fov = 1 << 5,
+    /// mounted/dismounted or switched to a different mount
+    Mount = 1 << 6,
+    /// compass width/height/rotation changed
+    Compass = 1 << 7,
+    /// dpi or dpi scaling changed. eg: gw2 moved to a monitor with a different scaling
+    Dpi = 1 << 8,
 }
 
 /// represents the ui scale set in settings -> graphics options -> interface size
@@ -166,3 +332,170 @@ pub enum UIState {
     TextboxFocus = 0b00100000,
     IsInCombat = 0b01000000,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_rect_at_150_percent_scaling() {
+        let link = MumbleLink {
+            dpi: 144,
+            dpi_scaling: 1,
+            client_pos: IVec2::new(300, 150),
+            client_size: IVec2::new(1920, 1080),
+            ..Default::default()
+        };
+        assert_eq!(link.dpi_scale_factor(), 1.5);
+        assert_eq!(
+            link.physical_client_rect(),
+            (IVec2::new(300, 150), UVec2::new(1920, 1080))
+        );
+        assert_eq!(
+            link.logical_client_rect(),
+            (IVec2::new(200, 100), UVec2::new(1280, 720))
+        );
+    }
+
+    #[test]
+    fn changes_from_only_reports_edited_fields() {
+        let previous = MumbleLink::default();
+        let mut edited = previous.clone();
+        edited.map_id = 15;
+        edited.player_pos.x = 10.0;
+        edited.fov = 1.2;
+        assert_eq!(
+            edited.changes_from(&previous),
+            MumbleChanges::Map | MumbleChanges::Fov
+        );
+        edited.dpi = 144;
+        assert!(edited.changes_from(&previous).contains(MumbleChanges::Dpi));
+        assert!(previous.changes_from(&previous).is_empty());
+    }
+
+    #[test]
+    fn poll_interval_backs_off_without_live_link() {
+        assert_eq!(
+            LinkLiveness::Live.poll_interval(false),
+            ACTIVE_POLL_INTERVAL
+        );
+        assert_eq!(LinkLiveness::Live.poll_interval(true), IDLE_POLL_INTERVAL);
+        assert_eq!(
+            LinkLiveness::NoGame.poll_interval(false),
+            IDLE_POLL_INTERVAL
+        );
+        assert_eq!(
+            LinkLiveness::NoBridge.poll_interval(false),
+            IDLE_POLL_INTERVAL
+        );
+    }
+
+    #[test]
+    fn link_json_round_trip() {
+        let link = MumbleLink {
+            ui_tick: 5,
+            name: "first".to_string(),
+            map_id: 15,
+            player_pos: Vec3::new(1.0, 2.0, 3.0),
+            mount_index: 1,
+            mount: Some(Mount::Jackal),
+            race: Race::NORN,
+            changes: MumbleChanges::Map | MumbleChanges::UiTick,
+            client_pos: IVec2::new(-10, 20),
+            client_size: IVec2::new(1920, 1080),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&link).unwrap();
+        // glam types are serialized as compact arrays instead of structs with field names
+        assert!(json.contains(r#""player_pos":[1.0,2.0,3.0]"#));
+        assert!(json.contains(r#""client_pos":[-10,20]"#));
+        assert!(json.contains(r#""client_size":[1920,1080]"#));
+        let parsed: MumbleLink = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, link);
+    }
+
+    /// continent position of a world position (in meters) on Queensdale (map 15), using the map_rect/continent_rect from the gw2 api.
+    /// the map_rect is in inches with y pointing north, while the continent y points south.
+    fn queensdale_continent_position(world: Vec3) -> Vec2 {
+        const CONTINENT_RECT: [[f32; 2]; 2] = [[9856.0, 11648.0], [13440.0, 14080.0]];
+        const MAP_RECT: [[f32; 2]; 2] = [[-43008.0, -27648.0], [43008.0, 30720.0]];
+        let inches = Vec2::new(world.x, world.z) * 39.37008;
+        let continent_size = Vec2::from(CONTINENT_RECT[1]) - Vec2::from(CONTINENT_RECT[0]);
+        let map_size = Vec2::from(MAP_RECT[1]) - Vec2::from(MAP_RECT[0]);
+        Vec2::new(
+            CONTINENT_RECT[0][0] + (inches.x - MAP_RECT[0][0]) / map_size.x * continent_size.x,
+            CONTINENT_RECT[0][1] + (MAP_RECT[1][1] - inches.y) / map_size.y * continent_size.y,
+        )
+    }
+
+    #[test]
+    fn world_to_map_matches_map_rect_projection() {
+        let player_pos = Vec3::new(-250.0, 30.0, 100.0);
+        let player_continent = queensdale_continent_position(player_pos);
+        let link = MumbleLink {
+            map_id: 15,
+            player_pos,
+            player_x: player_continent.x,
+            player_y: player_continent.y,
+            ..Default::default()
+        };
+        assert_eq!(link.map_position(), player_continent);
+        assert_eq!(link.world_to_map(player_pos), player_continent);
+        for world in [
+            Vec3::new(0.0, 0.0, 0.0),
+            // north east of the player, so continent y is smaller
+            Vec3::new(-150.0, 30.0, 300.0),
+            Vec3::new(500.0, -20.0, -400.0),
+        ] {
+            let expected = queensdale_continent_position(world);
+            let projected = link.world_to_map(world);
+            assert!(
+                projected.distance(expected) < 0.01,
+                "{world}: {projected} != {expected}"
+            );
+        }
+        // a meter is 39.37 inches and a continent unit is 24 inches
+        let north = link.world_to_map(player_pos + Vec3::new(24.0, 0.0, 24.0));
+        assert!((north - player_continent - Vec2::new(39.37008, -39.37008)).length() < 0.01);
+    }
+
+    #[test]
+    fn ui_state_flags() {
+        type Accessor = fn(&MumbleLink) -> bool;
+        let accessors: [(UIState, Accessor); 7] = [
+            (UIState::IsMapOpen, MumbleLink::is_map_open),
+            (UIState::IsCompassTopRight, MumbleLink::is_compass_top_right),
+            (
+                UIState::DoesCompassHaveRotationEnabled,
+                MumbleLink::is_compass_rotation_enabled,
+            ),
+            (UIState::GameHasFocus, MumbleLink::game_has_focus),
+            (
+                UIState::InCompetitiveGamemode,
+                MumbleLink::is_in_competitive_mode,
+            ),
+            (UIState::TextboxFocus, MumbleLink::is_textbox_focused),
+            (UIState::IsInCombat, MumbleLink::is_in_combat),
+        ];
+        for (flag, _) in accessors.iter() {
+            // unknown bits are ignored
+            let link = MumbleLink {
+                ui_state: *flag as u32 | 1 << 10,
+                ..Default::default()
+            };
+            for (other_flag, accessor) in accessors.iter() {
+                assert_eq!(
+                    accessor(&link),
+                    *flag as u32 == *other_flag as u32,
+                    "{other_flag:?} with ui_state {:#b}",
+                    link.ui_state
+                );
+            }
+        }
+        let all = MumbleLink {
+            ui_state: 0b1111111,
+            ..Default::default()
+        };
+        assert!(accessors.iter().all(|(_, accessor)| accessor(&all)));
+    }
+}
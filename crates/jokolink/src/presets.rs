@@ -0,0 +1,95 @@
+//! Named snapshots of [MumbleLink] which can be loaded into the editable link of [crate::MumbleManager].
+//! Useful to test marker placement without having to run the game or edit the same values every session.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use cap_std::fs_utf8::Dir;
+use miette::{IntoDiagnostic, Result, WrapErr};
+
+use crate::MumbleLink;
+
+pub struct MumblePresets {
+    /// directory which contains [Self::PRESETS_FILE_NAME]
+    dir: Arc<Dir>,
+    presets: BTreeMap<String, MumbleLink>,
+}
+
+impl MumblePresets {
+    pub const PRESETS_FILE_NAME: &'static str = "mumble_presets.json";
+    /// loads the presets from `dir`. If the presets file doesn't exist yet, we start with no presets.
+    pub fn new(dir: Arc<Dir>) -> Result<Self> {
+        let presets = if dir.exists(Self::PRESETS_FILE_NAME) {
+            let json = dir
+                .read_to_string(Self::PRESETS_FILE_NAME)
+                .into_diagnostic()
+                .wrap_err("failed to read mumble presets file")?;
+            serde_json::from_str(&json)
+                .into_diagnostic()
+                .wrap_err("failed to deserialize mumble presets")?
+        } else {
+            Default::default()
+        };
+        Ok(Self { dir, presets })
+    }
+    pub fn save(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.presets)
+            .into_diagnostic()
+            .wrap_err("failed to serialize mumble presets")?;
+        self.dir
+            .write(Self::PRESETS_FILE_NAME, json)
+            .into_diagnostic()
+            .wrap_err("failed to write mumble presets file")
+    }
+    /// inserts (or replaces) the preset and saves all presets to disk. changes of the link are not stored.
+    pub fn insert(&mut self, name: String, mut link: MumbleLink) -> Result<()> {
+        link.changes = Default::default();
+        self.presets.insert(name, link);
+        self.save()
+    }
+    /// removes the preset (if it exists) and saves all presets to disk
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        if self.presets.remove(name).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+    pub fn get(&self, name: &str) -> Option<&MumbleLink> {
+        self.presets.get(name)
+    }
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.presets.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cap_std::{ambient_authority, fs_utf8::camino::Utf8PathBuf};
+    use glam::Vec3;
+
+    use super::*;
+
+    #[test]
+    fn save_and_load_preset() {
+        let path = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("jokolink_presets_test_{}", std::process::id()));
+        Dir::create_ambient_dir_all(&path, ambient_authority()).unwrap();
+        let dir = Arc::new(Dir::open_ambient_dir(&path, ambient_authority()).unwrap());
+
+        let link = MumbleLink {
+            map_id: 15,
+            player_pos: Vec3::new(1.0, 2.0, 3.0),
+            cam_pos: Vec3::new(1.0, 5.0, 3.0),
+            fov: 1.2,
+            ..Default::default()
+        };
+        let mut presets = MumblePresets::new(dir.clone()).unwrap();
+        presets
+            .insert("queensdale".to_string(), link.clone())
+            .unwrap();
+
+        let loaded = MumblePresets::new(dir).unwrap();
+        assert_eq!(loaded.get("queensdale"), Some(&link));
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+//! An append only csv recorder of mumble link data.
+//! Useful for movement analysis in spreadsheets or for QA. Unlike a full dump of the link, it only records a few columns.
+
+use std::{
+    io::{BufWriter, Write},
+    time::{Duration, Instant},
+};
+
+use cap_std::fs_utf8::{Dir, File, OpenOptions};
+use miette::{IntoDiagnostic, Result, WrapErr};
+
+use crate::{MumbleChanges, MumbleLink};
+
+/// columns of the csv file. `map_changed` is 1 for the first row after a map transition.
+pub const RECORDER_CSV_HEADER: &str =
+    "ui_tick,time,player_x,player_y,player_z,cam_x,cam_y,cam_z,map_id,map_changed,mount";
+
+/// How often we flush the buffered rows to the file
+const RECORDER_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct MumbleRecorder {
+    /// path of the csv file we are appending to. relative to the dir it was opened in
+    path: String,
+    writer: BufWriter<File>,
+    last_flush: Instant,
+}
+
+impl MumbleRecorder {
+    /// Opens the file at `path` inside `dir` in append mode. If the file is empty, we write the header first.
+    pub fn new(dir: &Dir, path: &str) -> Result<Self> {
+        let file = dir
+            .open_with(path, OpenOptions::new().create(true).append(true))
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to open mumble recorder file {path:?}"))?;
+        let is_empty = file
+            .metadata()
+            .into_diagnostic()
+            .wrap_err("failed to get metadata of mumble recorder file")?
+            .len()
+            == 0;
+        let mut writer = BufWriter::new(file);
+        if is_empty {
+            writeln!(writer, "{RECORDER_CSV_HEADER}")
+                .into_diagnostic()
+                .wrap_err("failed to write header of mumble recorder file")?;
+        }
+        Ok(Self {
+            path: path.to_string(),
+            writer,
+            last_flush: Instant::now(),
+        })
+    }
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+    /// appends a row for this link if anything changed. flushes if [RECORDER_FLUSH_INTERVAL] has passed since the last flush.
+    pub fn record(&mut self, link: &MumbleLink) -> Result<()> {
+        if link.changes.is_empty() {
+            return Ok(());
+        }
+        let time = time::OffsetDateTime::now_utc().unix_timestamp_nanos() as f64 / 1_000_000_000.0;
+        writeln!(
+            self.writer,
+            "{},{:.3},{},{},{},{},{},{},{},{},{}",
+            link.ui_tick,
+            time,
+            link.player_pos.x,
+            link.player_pos.y,
+            link.player_pos.z,
+            link.cam_pos.x,
+            link.cam_pos.y,
+            link.cam_pos.z,
+            link.map_id,
+            u8::from(link.changes.contains(MumbleChanges::Map)),
+            link.mount.as_ref().map(|m| m.as_ref()).unwrap_or_default(),
+        )
+        .into_diagnostic()
+        .wrap_err("failed to write mumble recorder row")?;
+        if self.last_flush.elapsed() > RECORDER_FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+    pub fn flush(&mut self) -> Result<()> {
+        self.last_flush = Instant::now();
+        self.writer
+            .flush()
+            .into_diagnostic()
+            .wrap_err("failed to flush mumble recorder file")
+    }
+}
+
+impl Drop for MumbleRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::error!(?e, "failed to flush mumble recorder on drop");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cap_std::{ambient_authority, fs_utf8::camino::Utf8PathBuf};
+    use enumflags2::BitFlags;
+    use glam::Vec3;
+    use jokoapi::end_point::mounts::Mount;
+
+    use super::*;
+
+    fn link(ui_tick: u32, map_id: u32, changes: BitFlags<MumbleChanges>) -> MumbleLink {
+        MumbleLink {
+            ui_tick,
+            map_id,
+            changes,
+            player_pos: Vec3::new(1.0, 2.0, 3.0),
+            cam_pos: Vec3::new(4.0, 5.0, 6.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn records_changed_links() {
+        let path = Utf8PathBuf::try_from(std::env::temp_dir())
+            .unwrap()
+            .join(format!("jokolink_recorder_test_{}", std::process::id()));
+        Dir::create_ambient_dir_all(&path, ambient_authority()).unwrap();
+        let dir = Dir::open_ambient_dir(&path, ambient_authority()).unwrap();
+        {
+            let mut recorder = MumbleRecorder::new(&dir, "recording.csv").unwrap();
+            recorder
+                .record(&link(1, 15, MumbleChanges::UiTick | MumbleChanges::Map))
+                .unwrap();
+            let mut mounted = link(2, 15, MumbleChanges::UiTick.into());
+            mounted.mount = Some(Mount::Raptor);
+            recorder.record(&mounted).unwrap();
+            // nothing changed, so this is skipped
+            recorder.record(&link(2, 15, BitFlags::empty())).unwrap();
+            recorder
+                .record(&link(3, 50, MumbleChanges::UiTick | MumbleChanges::Map))
+                .unwrap();
+        }
+        // a second recorder appends without repeating the header
+        MumbleRecorder::new(&dir, "recording.csv").unwrap();
+
+        let csv = dir.read_to_string("recording.csv").unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            "ui_tick,time,player_x,player_y,player_z,cam_x,cam_y,cam_z,map_id,map_changed,mount"
+        );
+        let columns: Vec<Vec<&str>> = lines[1..]
+            .iter()
+            .map(|line| line.split(',').collect())
+            .collect();
+        for row in columns.iter() {
+            assert_eq!(row.len(), 11);
+            assert_eq!(row[2..8], ["1", "2", "3", "4", "5", "6"]);
+        }
+        let ticks_maps_mounts: Vec<[&str; 4]> = columns
+            .iter()
+            .map(|row| [row[0], row[8], row[9], row[10]])
+            .collect();
+        assert_eq!(
+            ticks_maps_mounts,
+            [
+                ["1", "15", "1", ""],
+                ["2", "15", "0", Mount::Raptor.as_ref()],
+                ["3", "50", "1", ""]
+            ]
+        );
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -255,6 +255,7 @@ pub mod d3d11 {
         use crate::mumble::ctypes::*;
         use crate::win::MumbleWinImpl;
         use crate::DEFAULT_MUMBLELINK_NAME;
+        use joko_log::{LogRotationConfig, RotatingLogWriter};
         use miette::{Context, IntoDiagnostic, Result};
         use serde::{Deserialize, Serialize};
         use std::io::Write;
@@ -273,6 +274,10 @@ pub mod d3d11 {
             pub mumble_link_name: String,
             pub interval: u32,
             pub copy_dest_dir: PathBuf,
+            /// the logfile is rotated when it grows beyond this size in MiB
+            pub log_max_size_mb: u64,
+            /// number of rotated logfiles (jokolink.log.1, jokolink.log.2 ...) to keep
+            pub log_max_files: usize,
         }
 
         impl Default for JokolinkConfig {
@@ -283,6 +288,8 @@ pub mod d3d11 {
                     mumble_link_name: DEFAULT_MUMBLELINK_NAME.to_string(),
                     interval: 5,
                     copy_dest_dir: PathBuf::from("z:\\dev\\shm"),
+                    log_max_size_mb: 10,
+                    log_max_files: 3,
                 }
             }
         }
@@ -345,7 +352,9 @@ pub mod d3d11 {
                 let guard = match log_init(
                     LevelFilter::from_str(&config.loglevel).unwrap_or(LevelFilter::INFO),
                     &config.logdir,
-                    Path::new("jokolink.log"),
+                    "jokolink.log",
+                    config.log_max_size_mb * 1024 * 1024,
+                    config.log_max_files,
                 ) {
                     Ok(g) => g,
                     Err(e) => {
@@ -459,21 +468,34 @@ pub mod d3d11 {
         }
 
         /// initializes global logging backend that is used by log macros
-        /// Takes in a filter for stdout/stderr, a filter for logfile and finally the path to logfile
+        /// Takes in a filter for stdout/stderr, a filter for logfile and finally the directory and name of logfile
+        /// The logfile is rotated when it grows beyond `max_size` bytes, keeping `max_files` older logfiles.
         pub fn log_init(
             file_filter: LevelFilter,
             log_directory: &Path,
-            log_file_name: &Path,
+            log_file_name: &str,
+            max_size: u64,
+            max_files: usize,
         ) -> Result<tracing_appender::non_blocking::WorkerGuard> {
             // let file_appender = tracing_appender::rolling::never(log_directory, log_file_name);
-            let file_path = log_directory.join(log_file_name);
-            let writer = std::io::BufWriter::new(
-                std::fs::File::create(&file_path)
+            let dir =
+                cap_std::fs::Dir::open_ambient_dir(log_directory, cap_std::ambient_authority())
                     .into_diagnostic()
                     .wrap_err_with(|| {
-                        format!("failed to create logfile at path: {:#?}", &file_path)
-                    })?,
-            );
+                        format!("failed to open log directory: {log_directory:#?}")
+                    })?;
+            let writer = RotatingLogWriter::new(
+                cap_std::fs_utf8::Dir::from_cap_std(dir),
+                log_file_name,
+                LogRotationConfig {
+                    max_file_size: max_size,
+                    max_rotated_files: max_files,
+                },
+            )
+            .into_diagnostic()
+            .wrap_err_with(|| {
+                format!("failed to create logfile {log_file_name} in {log_directory:#?}")
+            })?;
             let (nb, guard) = tracing_appender::non_blocking(writer);
             tracing_subscriber::fmt()
                 .with_writer(nb)
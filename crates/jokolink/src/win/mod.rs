@@ -184,6 +184,14 @@ impl MumbleWinImpl {
     pub fn is_alive(&self) -> bool {
         !self.process_handle.is_invalid()
     }
+    /// There's no bridge on windows. So, it is live if the game is running and has initialized mumble link (non-zero ui_tick)
+    pub fn liveness(&self) -> crate::LinkLiveness {
+        if self.is_alive() && unsafe { CMumbleLink::is_valid(self.link_ptr) } {
+            crate::LinkLiveness::Live
+        } else {
+            crate::LinkLiveness::NoGame
+        }
+    }
     pub fn get_cmumble_link(&mut self) -> CMumbleLink {
         let mut link = unsafe { std::ptr::read_volatile(self.link_ptr) };
         link.context.timestamp = OffsetDateTime::now_utc()